@@ -0,0 +1,7 @@
+//! Builds the functions of `lazy_sort::capi`, declared in `include/lazy_sort.h`, into a shared
+//! library. It's a package of its own because a `cdylib` can't be built without `std`, which the
+//! `lazy-sort` crate otherwise doesn't need.
+
+extern crate lazy_sort;
+
+pub use lazy_sort::capi::*;
@@ -0,0 +1,41 @@
+/* The C interface of the lazy-sort crate, enabled by its `capi` feature and built into a shared
+ * library by the lazy-sort-capi package. */
+
+#ifndef LAZY_SORT_H
+#define LAZY_SORT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define LAZY_SORT_DECLARE(name, type)                                                    \
+    /* Copies the k smallest of the len elements at data to out in ascending order,   */ \
+    /* returning how many were copied.                                                 */ \
+    size_t lazy_sort_smallest_##name(const type *data, size_t len, size_t k, type *out); \
+                                                                                         \
+    /* Yields the elements of a buffer in ascending order on demand.                   */ \
+    typedef struct lazy_sort_##name lazy_sort_##name;                                    \
+    lazy_sort_##name *lazy_sort_##name##_new(const type *data, size_t len);              \
+    bool lazy_sort_##name##_next(lazy_sort_##name *handle, type *out);                   \
+    size_t lazy_sort_##name##_next_n(lazy_sort_##name *handle, type *out, size_t n);     \
+    size_t lazy_sort_##name##_remaining(const lazy_sort_##name *handle);                 \
+    void lazy_sort_##name##_free(lazy_sort_##name *handle);
+
+LAZY_SORT_DECLARE(i32, int32_t)
+LAZY_SORT_DECLARE(u32, uint32_t)
+LAZY_SORT_DECLARE(i64, int64_t)
+LAZY_SORT_DECLARE(u64, uint64_t)
+LAZY_SORT_DECLARE(f32, float)
+LAZY_SORT_DECLARE(f64, double)
+
+#undef LAZY_SORT_DECLARE
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,78 @@
+//! Choosing quicksort's insertion sort threshold from the measured cost of comparisons.
+//!
+//! Insertion sort makes about `m^2 / 4` comparisons on a partition of `m` elements, against
+//! quicksort's `m log2(m)`, but saves quicksort's per-partition overhead of choosing a pivot and
+//! allocating the split-off vec. The cheaper a comparison is, the more of them that overhead is
+//! worth, and so the larger the partitions worth insertion sorting.
+
+use std::time::{Duration, Instant};
+
+/// The threshold for comparisons that take `REFERENCE_PICOS`; the default threshold was tuned
+/// for about that.
+const REFERENCE_THRESHOLD: usize = 32;
+/// The cost, partitioning swaps included, of comparing two integers.
+const REFERENCE_PICOS: u128 = 1500;
+const MIN_THRESHOLD: usize = 4;
+const MAX_THRESHOLD: usize = 128;
+
+/// How many partitions are timed before choosing the threshold.
+const SAMPLES: u32 = 4;
+/// Partitions smaller than this are too quick to time reliably.
+const MIN_SAMPLE_LEN: usize = 1 << 12;
+
+/// Times the first few large partitions of a sort.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AdaptiveThreshold {
+    samples: u32,
+    elapsed: Duration,
+    comparisons: u64,
+}
+
+impl AdaptiveThreshold {
+    /// Runs `partition`, which makes `len` comparisons, timing it if the threshold hasn't been
+    /// chosen yet. Returns the result, and the threshold once enough partitions have been timed.
+    pub(crate) fn measure<R, F>(&mut self, len: usize, partition: F) -> (R, Option<usize>)
+        where F: FnOnce() -> R
+    {
+        if self.samples >= SAMPLES || len < MIN_SAMPLE_LEN {
+            return (partition(), None);
+        }
+        let start = Instant::now();
+        let result = partition();
+        (result, self.record(len, start.elapsed()))
+    }
+
+    /// Records a partition of `len` comparisons that took `elapsed`, returning the threshold if
+    /// it was the last sample needed.
+    fn record(&mut self, len: usize, elapsed: Duration) -> Option<usize> {
+        self.samples += 1;
+        self.elapsed += elapsed;
+        self.comparisons += len as u64;
+        if self.samples < SAMPLES {
+            return None;
+        }
+        let picos = (self.elapsed.as_nanos() * 1000 / u128::from(self.comparisons)).max(1);
+        let threshold = REFERENCE_THRESHOLD as u128 * REFERENCE_PICOS / picos;
+        Some((threshold.min(MAX_THRESHOLD as u128) as usize).max(MIN_THRESHOLD))
+    }
+}
+
+#[test]
+fn adaptive_threshold() {
+    use super::LazySortBuilder;
+
+    let choose = |nanos_per_comparison: u64| {
+        let mut adaptive = AdaptiveThreshold::default();
+        let elapsed = Duration::from_nanos(nanos_per_comparison * 10_000);
+        (0..SAMPLES).filter_map(|_| adaptive.record(10_000, elapsed)).collect::<Vec<_>>()
+    };
+    assert_eq!(choose(0), [MAX_THRESHOLD]);
+    assert_eq!(choose(3), [16]);
+    assert_eq!(choose(1000), [MIN_THRESHOLD]);
+
+    let v: Vec<u32> = (0..100_000).map(|i| (i * 7919) % 100_003).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    let sorter = LazySortBuilder::new().adaptive_threshold(true).sort(v).unwrap();
+    assert_eq!(sorter.collect::<Vec<_>>(), expected);
+}
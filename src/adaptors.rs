@@ -0,0 +1,475 @@
+//! Adaptors over the sorted output of the lazy sorters.
+
+use std::marker::PhantomData;
+use std::vec::Vec;
+use super::LazySorted;
+
+/// An iterator over runs of equal elements of a lazy sorter.
+///
+/// Every group yielded is non-empty. A quicksort yields the elements it set aside with an equal
+/// pivot as part of the pivot's group, without comparing them again.
+#[derive(Debug, Clone)]
+pub struct GroupEqual<I: Iterator> {
+    iter: I,
+}
+
+impl<I: Iterator> GroupEqual<I> {
+    pub(crate) fn new(iter: I) -> GroupEqual<I> {
+        GroupEqual { iter }
+    }
+}
+
+impl<I, T> Iterator for GroupEqual<I>
+    where I: Iterator<Item = T> + LazySorted<T>,
+          T: Ord
+{
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let first = self.iter.next()?;
+        let mut group = Vec::new();
+        self.iter.drain_equal(&first, |el| group.push(el));
+        // `first` can't be in the group while it's borrowed for the comparisons, but the group's
+        // elements are all equal to it, so swapping it to the front keeps the group sorted.
+        group.push(first);
+        let last = group.len() - 1;
+        group.swap(0, last);
+        Some(group)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.iter.len();
+        (len.min(1), Some(len))
+    }
+}
+
+/// An iterator over the sorted elements of a lazy sorter in pages of a fixed length, created by
+/// [`sorted_chunks`](trait.LazySorted.html#method.sorted_chunks).
+#[derive(Debug, Clone)]
+pub struct SortedChunks<S, T> {
+    sorter: S,
+    n: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<S, T> SortedChunks<S, T> {
+    pub(crate) fn new(sorter: S, n: usize) -> SortedChunks<S, T> {
+        assert!(n > 0, "n must be positive");
+        SortedChunks {
+            sorter,
+            n,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S, T> Iterator for SortedChunks<S, T>
+    where S: LazySorted<T>,
+          T: Ord
+{
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let len = self.sorter.len();
+        if len == 0 {
+            return None;
+        }
+        let mut chunk = Vec::with_capacity(len.min(self.n));
+        self.sorter.next_chunk_into(self.n, &mut chunk);
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let chunks = self.sorter.len().div_ceil(self.n);
+        (chunks, Some(chunks))
+    }
+}
+
+impl<S, T> ExactSizeIterator for SortedChunks<S, T>
+    where S: LazySorted<T>,
+          T: Ord
+{
+}
+
+/// An iterator that yields each distinct element of a lazy sorter once.
+///
+/// Of each run of equal elements, the first one is yielded and the rest are dropped. A quicksort
+/// drops the elements it set aside with an equal pivot without comparing them again.
+#[derive(Debug, Clone)]
+pub struct SortedUnique<I: Iterator> {
+    iter: I,
+}
+
+impl<I: Iterator> SortedUnique<I> {
+    pub(crate) fn new(iter: I) -> SortedUnique<I> {
+        SortedUnique { iter }
+    }
+}
+
+impl<I, T> Iterator for SortedUnique<I>
+    where I: Iterator<Item = T> + LazySorted<T>,
+          T: Ord
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let first = self.iter.next()?;
+        self.iter.drain_equal(&first, drop);
+        Some(first)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.iter.len();
+        (len.min(1), Some(len))
+    }
+}
+
+/// An iterator that yields each distinct element of a lazy sorter along with the number of times
+/// it occurs.
+///
+/// A quicksort counts the elements it set aside with an equal pivot without comparing them again.
+#[derive(Debug, Clone)]
+pub struct DedupWithCounts<I: Iterator> {
+    iter: I,
+}
+
+impl<I: Iterator> DedupWithCounts<I> {
+    pub(crate) fn new(iter: I) -> DedupWithCounts<I> {
+        DedupWithCounts { iter }
+    }
+}
+
+impl<I, T> Iterator for DedupWithCounts<I>
+    where I: Iterator<Item = T> + LazySorted<T>,
+          T: Ord
+{
+    type Item = (T, usize);
+
+    fn next(&mut self) -> Option<(T, usize)> {
+        let first = self.iter.next()?;
+        let mut count = 1;
+        self.iter.drain_equal(&first, |_| count += 1);
+        Some((first, count))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.iter.len();
+        (len.min(1), Some(len))
+    }
+}
+
+/// How [`WithRanks`](struct.WithRanks.html) assigns ranks to equal elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankPolicy {
+    /// Equal elements share a rank, and the next distinct element gets the next rank
+    /// (`0, 1, 1, 2`).
+    Dense,
+    /// Equal elements share a rank, and the next distinct element's rank skips over them
+    /// (`0, 1, 1, 3`).
+    Competition,
+}
+
+/// An iterator that yields the elements of a sorted iterator along with their zero-based ranks.
+#[derive(Debug, Clone)]
+pub struct WithRanks<I: Iterator> {
+    iter: I,
+    policy: RankPolicy,
+    pending: Option<I::Item>,
+    rank: usize,
+    position: usize,
+}
+
+impl<I: Iterator> WithRanks<I> {
+    pub(crate) fn new(iter: I, policy: RankPolicy) -> WithRanks<I> {
+        WithRanks {
+            iter,
+            policy,
+            pending: None,
+            rank: 0,
+            position: 0,
+        }
+    }
+}
+
+impl<I> Iterator for WithRanks<I>
+    where I: Iterator,
+          I::Item: PartialEq
+{
+    type Item = (usize, I::Item);
+
+    fn next(&mut self) -> Option<(usize, I::Item)> {
+        let current = match self.pending.take() {
+            Some(el) => el,
+            None => self.iter.next()?,
+        };
+        let rank = self.rank;
+        self.position += 1;
+        // Look ahead so that the rank of the next element is known before it is yielded.
+        self.pending = self.iter.next();
+        if self.pending.as_ref().is_some_and(|next| *next != current) {
+            self.rank = match self.policy {
+                RankPolicy::Dense => rank + 1,
+                RankPolicy::Competition => self.position,
+            };
+        }
+        Some((rank, current))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let pending = self.pending.is_some() as usize;
+        let (lower, upper) = self.iter.size_hint();
+        (lower.saturating_add(pending),
+         upper.and_then(|upper| upper.checked_add(pending)))
+    }
+}
+
+/// An iterator that folds each run of adjacent elements with equal keys into a single value.
+#[derive(Debug, Clone)]
+pub struct GroupFold<I: Iterator, K, A, FK, FF> {
+    iter: I,
+    key_fn: FK,
+    init: A,
+    fold_fn: FF,
+    pending: Option<(K, I::Item)>,
+}
+
+impl<I: Iterator, K, A, FK, FF> GroupFold<I, K, A, FK, FF> {
+    pub(crate) fn new(iter: I, key_fn: FK, init: A, fold_fn: FF) -> GroupFold<I, K, A, FK, FF> {
+        GroupFold {
+            iter,
+            key_fn,
+            init,
+            fold_fn,
+            pending: None,
+        }
+    }
+}
+
+impl<I, K, A, FK, FF> Iterator for GroupFold<I, K, A, FK, FF>
+    where I: Iterator,
+          K: PartialEq,
+          A: Clone,
+          FK: FnMut(&I::Item) -> K,
+          FF: FnMut(A, I::Item) -> A
+{
+    type Item = (K, A);
+
+    fn next(&mut self) -> Option<(K, A)> {
+        let (key, first) = match self.pending.take() {
+            Some(pending) => pending,
+            None => {
+                let el = self.iter.next()?;
+                ((self.key_fn)(&el), el)
+            }
+        };
+        let mut acc = (self.fold_fn)(self.init.clone(), first);
+        for el in self.iter.by_ref() {
+            let el_key = (self.key_fn)(&el);
+            if el_key == key {
+                acc = (self.fold_fn)(acc, el);
+            } else {
+                self.pending = Some((el_key, el));
+                break;
+            }
+        }
+        Some((key, acc))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let pending = self.pending.is_some() as usize;
+        let (lower, upper) = self.iter.size_hint();
+        (if pending + lower > 0 { 1 } else { 0 },
+         upper.and_then(|upper| upper.checked_add(pending)))
+    }
+}
+
+/// A sorted iterator that remembers what it has yielded, so that it can be rewound and yield it
+/// again without sorting it again, created by
+/// [`rewindable`](trait.LazySorted.html#method.rewindable).
+#[derive(Debug, Clone)]
+pub struct Rewindable<I: Iterator> {
+    iter: I,
+    /// Every element yielded by `iter` so far.
+    emitted: Vec<I::Item>,
+    /// The position of the next element to yield, which is in `emitted` if it's in bounds.
+    pos: usize,
+}
+
+impl<I: Iterator> Rewindable<I> {
+    pub(crate) fn new(iter: I) -> Rewindable<I> {
+        Rewindable {
+            iter,
+            emitted: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Restarts iteration from the first element.
+    pub fn rewind(&mut self) {
+        self.pos = 0;
+    }
+
+    /// Returns the elements pulled from the sorter so far, in sorted order.
+    pub fn emitted(&self) -> &[I::Item] {
+        &self.emitted
+    }
+}
+
+impl<I> Iterator for Rewindable<I>
+    where I: Iterator,
+          I::Item: Clone
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.pos == self.emitted.len() {
+            self.emitted.push(self.iter.next()?);
+        }
+        self.pos += 1;
+        Some(self.emitted[self.pos - 1].clone())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let replayed = self.emitted.len() - self.pos;
+        let (lower, upper) = self.iter.size_hint();
+        (lower.saturating_add(replayed), upper.and_then(|upper| upper.checked_add(replayed)))
+    }
+}
+
+#[test]
+fn group_equal() {
+    use super::{LazySortBuilder, LazySortIterator, LazySorted};
+
+    let v = vec![2, 4, 2, 5, 8, 4, 3, 4, 6];
+    let groups: Vec<_> = v.iter().cloned().quick_sort().group_equal().collect();
+    assert_eq!(groups,
+               vec![vec![2, 2], vec![3], vec![4, 4, 4], vec![5], vec![6], vec![8]]);
+    let groups: Vec<_> = v.iter().cloned().heap_sort().group_equal().collect();
+    assert_eq!(groups.len(), 6);
+
+    // Grouping adds at most a binary search per group to the sort's comparisons, rather than a
+    // comparison per element.
+    let (grouped, comparisons) = counting_comparisons(|v| {
+        LazySortBuilder::new().perturb_pivots(false).sort(v).unwrap().group_equal().count()
+    });
+    let (_, sort_comparisons) = counting_comparisons(|v| {
+        LazySortBuilder::new().perturb_pivots(false).sort(v).unwrap().count()
+    });
+    assert_eq!(grouped, 4);
+    assert!(comparisons <= sort_comparisons + 4 * 16);
+}
+
+/// Runs `f` on 10,000 elements with four distinct values, returning its result and the number of
+/// comparisons it makes between them.
+#[cfg(test)]
+fn counting_comparisons<F, R>(f: F) -> (R, usize)
+    where F: FnOnce(Vec<Counted>) -> R
+{
+    let v = (0..10_000).map(|i| Counted(i % 4)).collect();
+    let before = COMPARISONS.with(std::cell::Cell::get);
+    let result = f(v);
+    (result, COMPARISONS.with(std::cell::Cell::get) - before)
+}
+
+#[cfg(test)]
+thread_local! {
+    static COMPARISONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Counted(u32);
+
+#[cfg(test)]
+impl PartialOrd for Counted {
+    fn partial_cmp(&self, other: &Counted) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+impl Ord for Counted {
+    fn cmp(&self, other: &Counted) -> std::cmp::Ordering {
+        COMPARISONS.with(|comparisons| comparisons.set(comparisons.get() + 1));
+        self.0.cmp(&other.0)
+    }
+}
+
+#[test]
+fn sorted_unique() {
+    use super::{LazySortBuilder, LazySortIterator, LazySorted};
+
+    let v = vec![2, 4, 2, 5, 8, 4, 3, 4, 6, 8];
+    let unique: Vec<_> = v.iter().cloned().quick_sort().sorted_unique().collect();
+    assert_eq!(unique, vec![2, 3, 4, 5, 6, 8]);
+    let unique: Vec<_> = v.iter().cloned().heap_sort().sorted_unique().collect();
+    assert_eq!(unique, vec![2, 3, 4, 5, 6, 8]);
+
+    let (unique, comparisons) = counting_comparisons(|v| {
+        LazySortBuilder::new().perturb_pivots(false).sort(v).unwrap().sorted_unique().count()
+    });
+    let (_, sort_comparisons) = counting_comparisons(|v| {
+        LazySortBuilder::new().perturb_pivots(false).sort(v).unwrap().count()
+    });
+    assert_eq!(unique, 4);
+    assert!(comparisons <= sort_comparisons + 4 * 16);
+}
+
+#[test]
+fn dedup_with_counts() {
+    use super::{LazySortBuilder, LazySortIterator, LazySorted};
+
+    let v = vec![2, 4, 2, 5, 8, 4, 3, 4, 6, 8];
+    let counts: Vec<_> = v.iter().cloned().quick_sort().dedup_with_counts().collect();
+    assert_eq!(counts, vec![(2, 2), (3, 1), (4, 3), (5, 1), (6, 1), (8, 2)]);
+    let counts: Vec<_> = v.iter().cloned().heap_sort().dedup_with_counts().collect();
+    assert_eq!(counts, vec![(2, 2), (3, 1), (4, 3), (5, 1), (6, 1), (8, 2)]);
+
+    let (counts, comparisons) = counting_comparisons(|v| {
+        let sorter = LazySortBuilder::new().perturb_pivots(false).sort(v).unwrap();
+        sorter.dedup_with_counts().map(|(el, count)| (el.0, count)).collect::<Vec<_>>()
+    });
+    let (_, sort_comparisons) = counting_comparisons(|v| {
+        LazySortBuilder::new().perturb_pivots(false).sort(v).unwrap().count()
+    });
+    assert_eq!(counts, [(0, 2500), (1, 2500), (2, 2500), (3, 2500)]);
+    assert!(comparisons <= sort_comparisons + 4 * 16);
+}
+
+#[test]
+fn with_ranks() {
+    use super::{LazySortIterator, LazySorted};
+
+    let v = [4, 2, 4, 1, 2, 2, 7];
+    let ranks: Vec<_> = v.iter().cloned().quick_sort().with_ranks(RankPolicy::Dense).collect();
+    assert_eq!(ranks, vec![(0, 1), (1, 2), (1, 2), (1, 2), (2, 4), (2, 4), (3, 7)]);
+    let ranks: Vec<_> = v.iter().cloned().heap_sort().with_ranks(RankPolicy::Competition).collect();
+    assert_eq!(ranks, vec![(0, 1), (1, 2), (1, 2), (1, 2), (4, 4), (4, 4), (6, 7)]);
+}
+
+#[test]
+fn group_fold() {
+    use super::{LazySortIterator, LazySorted};
+
+    let sales = [("pears", 3), ("apples", 2), ("pears", 1), ("figs", 7), ("apples", 5)];
+    let totals: Vec<_> = sales.iter()
+                              .cloned()
+                              .quick_sort()
+                              .group_fold(|sale| sale.0, 0, |total, sale| total + sale.1)
+                              .collect();
+    assert_eq!(totals, vec![("apples", 7), ("figs", 7), ("pears", 4)]);
+}
+
+#[test]
+fn rewindable() {
+    use super::{LazySortIterator, LazySorted};
+
+    let mut sorted = (0..100).rev().quick_sort().rewindable();
+    assert_eq!(sorted.by_ref().take(3).collect::<Vec<_>>(), [0, 1, 2]);
+    sorted.rewind();
+    assert_eq!(sorted.size_hint(), (100, Some(100)));
+    assert_eq!(sorted.by_ref().take(5).collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+    assert_eq!(sorted.emitted(), [0, 1, 2, 3, 4]);
+    sorted.rewind();
+    assert!(sorted.eq(0..100));
+}
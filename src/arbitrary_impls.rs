@@ -0,0 +1,78 @@
+//! `Arbitrary` impls that let fuzz targets start from partially consumed sorters.
+//!
+//! Each sorter is built from an arbitrary vec of elements, and then an arbitrary number of them,
+//! up to all of them, is taken from it.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use std::vec::Vec;
+use super::{Config, FixedHeapSort, HeapSort, LazySortIterator, PairingHeapSort, PivotStrategy,
+            QuickSort};
+use rng::XorShift;
+
+/// Takes an arbitrary number of elements from `sorter`.
+fn consume<I: Iterator>(u: &mut Unstructured, mut sorter: I, len: usize) -> Result<I> {
+    let consumed = u.int_in_range(0..=len)?;
+    sorter.by_ref().take(consumed).for_each(drop);
+    Ok(sorter)
+}
+
+impl<'a, T: Arbitrary<'a> + Ord> Arbitrary<'a> for QuickSort<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<QuickSort<T>> {
+        let v: Vec<T> = u.arbitrary()?;
+        let pivot = *u.choose(&[PivotStrategy::Middle,
+                                PivotStrategy::Random,
+                                PivotStrategy::MedianOfThree])?;
+        let config = Config {
+            pivot,
+            rng: XorShift::new(u.arbitrary()?),
+            ..Config::default()
+        };
+        let len = v.len();
+        consume(u, QuickSort::with_config(v, config), len)
+    }
+}
+
+impl<'a, T: Arbitrary<'a> + Ord> Arbitrary<'a> for HeapSort<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<HeapSort<T>> {
+        let v: Vec<T> = u.arbitrary()?;
+        let len = v.len();
+        consume(u, v.into_iter().heap_sort(), len)
+    }
+}
+
+impl<'a, T: Arbitrary<'a> + Ord> Arbitrary<'a> for PairingHeapSort<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<PairingHeapSort<T>> {
+        let v: Vec<T> = u.arbitrary()?;
+        let len = v.len();
+        consume(u, v.into_iter().pairing_heap_sort(), len)
+    }
+}
+
+impl<'a, T: Arbitrary<'a> + Ord, const N: usize> Arbitrary<'a> for FixedHeapSort<T, N> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<FixedHeapSort<T, N>> {
+        let v: Vec<T> = u.arbitrary()?;
+        let mut sorter = FixedHeapSort::new();
+        for el in v.into_iter().take(N) {
+            // Can't fail, since at most `N` elements are pushed.
+            let _ = sorter.try_push(el);
+        }
+        let len = sorter.len();
+        consume(u, sorter, len)
+    }
+}
+
+#[test]
+fn arbitrary_sorters() {
+    use super::LazySorted;
+
+    let bytes: Vec<u8> = (0..255).collect();
+    let mut u = Unstructured::new(&bytes);
+    let mut quick = QuickSort::<u8>::arbitrary(&mut u).unwrap();
+    let len = quick.len();
+    let sorted: Vec<_> = quick.by_ref().collect();
+    assert_eq!(sorted.len(), len);
+    assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+    let heap = HeapSort::<u8>::arbitrary(&mut u).unwrap().collect::<Vec<_>>();
+    assert!(heap.windows(2).all(|w| w[0] <= w[1]));
+    assert!(FixedHeapSort::<u8, 4>::arbitrary(&mut u).unwrap().len() <= 4);
+}
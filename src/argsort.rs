@@ -0,0 +1,389 @@
+//! Lazily sorting the indices of a slice rather than its elements.
+
+use std::cmp::Ordering;
+use std::iter::FusedIterator;
+use std::vec::Vec;
+use super::{LazySortIterator, QuickSort, SortKey};
+#[cfg(feature = "streaming-iterator")]
+use super::StreamingRefs;
+
+/// An extension trait that provides methods for lazily sorting slices.
+pub trait LazySortSlice<T: Ord> {
+    /// Lazily yields the indices of the slice's elements in ascending order of the elements.
+    /// The order of indices of equal elements is unspecified.
+    fn argsort(&self) -> ArgSort<'_, T>;
+
+    /// Lazily yields the indices of the slice's elements in ascending order of the elements.
+    /// Indices of equal elements are yielded in ascending order.
+    fn argsort_stable(&self) -> StableArgSort<'_, T>;
+
+    /// Like [`argsort`](#tymethod.argsort), but caches the first 8 bytes of each element's
+    /// [sort key](trait.SortKey.html) inline with its index, so that only comparisons of
+    /// elements whose keys share those bytes dereference the elements.
+    ///
+    /// This pays off for elements that are slow to compare or far apart in memory, such as
+    /// strings. Each element's key is encoded once, up front, reusing one buffer. The order of
+    /// the keys must agree with the elements' `Ord` impl, as it does for the provided impls of
+    /// `SortKey`.
+    ///
+    /// ```
+    /// use lazy_sort::LazySortSlice;
+    ///
+    /// let names = ["carol", "alice", "bob", "alicia"];
+    /// assert_eq!(names.argsort_by_prefix().collect::<Vec<_>>(), [1, 3, 2, 0]);
+    /// ```
+    fn argsort_by_prefix(&self) -> PrefixArgSort<'_, T>
+        where T: SortKey;
+
+    /// Lazily yields references to the slice's elements in ascending order, leaving the slice
+    /// untouched and cloning nothing.
+    fn quick_sort_refs(&self) -> QuickSort<&T>;
+
+    /// Like [`quick_sort_refs`](#tymethod.quick_sort_refs), but lends each element from the slice
+    /// through a `StreamingIterator`, so that passes over the elements see them as plain `&T`.
+    #[cfg(feature = "streaming-iterator")]
+    fn streaming_sort_refs(&self) -> StreamingRefs<'_, T>;
+}
+
+impl<T: Ord> LazySortSlice<T> for [T] {
+    fn argsort(&self) -> ArgSort<'_, T> {
+        ArgSort(self.iter().enumerate().map(|(index, value)| ByValue(value, index)).quick_sort())
+    }
+
+    fn argsort_stable(&self) -> StableArgSort<'_, T> {
+        // Ties on the element are broken by the index, which makes the order total.
+        StableArgSort(self.iter().enumerate().map(|(index, value)| (value, index)).quick_sort())
+    }
+
+    fn argsort_by_prefix(&self) -> PrefixArgSort<'_, T>
+        where T: SortKey
+    {
+        let mut key = Vec::new();
+        let prefixed = self.iter().enumerate().map(|(index, value)| {
+            key.clear();
+            value.write_sort_key(&mut key);
+            ByPrefix {
+                prefix: key_prefix(&key),
+                value,
+                index,
+            }
+        });
+        PrefixArgSort(prefixed.quick_sort())
+    }
+
+    fn quick_sort_refs(&self) -> QuickSort<&T> {
+        self.iter().quick_sort()
+    }
+
+    #[cfg(feature = "streaming-iterator")]
+    fn streaming_sort_refs(&self) -> StreamingRefs<'_, T> {
+        StreamingRefs::new(self.quick_sort_refs())
+    }
+}
+
+/// An iterator that lazily yields the indices of a slice in sorted order of its elements.
+#[derive(Debug, Clone)]
+pub struct ArgSort<'a, T: 'a>(QuickSort<ByValue<'a, T>>);
+
+impl<'a, T: Ord> Iterator for ArgSort<'a, T> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.0.next().map(|ByValue(_, index)| index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, T: Ord> ExactSizeIterator for ArgSort<'a, T> {}
+
+impl<'a, T: Ord> FusedIterator for ArgSort<'a, T> {}
+
+/// An iterator that lazily yields the indices of a slice in sorted order of its elements, with
+/// indices of equal elements in ascending order.
+#[derive(Debug, Clone)]
+pub struct StableArgSort<'a, T: 'a>(QuickSort<(&'a T, usize)>);
+
+impl<'a, T: Ord> Iterator for StableArgSort<'a, T> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.0.next().map(|(_, index)| index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, T: Ord> ExactSizeIterator for StableArgSort<'a, T> {}
+
+impl<'a, T: Ord> FusedIterator for StableArgSort<'a, T> {}
+
+/// An iterator that lazily yields the indices of a slice in sorted order of its elements,
+/// comparing cached key prefixes before the elements themselves.
+#[derive(Debug, Clone)]
+pub struct PrefixArgSort<'a, T: 'a>(QuickSort<ByPrefix<'a, T>>);
+
+impl<'a, T: Ord> Iterator for PrefixArgSort<'a, T> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.0.next().map(|prefixed| prefixed.index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, T: Ord> ExactSizeIterator for PrefixArgSort<'a, T> {}
+
+impl<'a, T: Ord> FusedIterator for PrefixArgSort<'a, T> {}
+
+/// Lazily yields the elements of `column` at each of `indices`, in order.
+///
+/// This applies an order computed by [`argsort`](trait.LazySortSlice.html#tymethod.argsort) to
+/// another column of a struct-of-arrays layout. To follow the order in several columns, collect
+/// the indices first and gather each column from them.
+///
+/// The returned iterator panics if an index is out of bounds.
+pub fn gather<I, U>(indices: I, column: &[U]) -> Gather<'_, I::IntoIter, U>
+    where I: IntoIterator<Item = usize>
+{
+    Gather {
+        indices: indices.into_iter(),
+        column,
+    }
+}
+
+/// An iterator over the elements of a slice at a sequence of indices.
+#[derive(Debug)]
+pub struct Gather<'a, I, U: 'a> {
+    indices: I,
+    column: &'a [U],
+}
+
+impl<'a, I: Clone, U> Clone for Gather<'a, I, U> {
+    fn clone(&self) -> Self {
+        Gather {
+            indices: self.indices.clone(),
+            column: self.column,
+        }
+    }
+}
+
+impl<'a, I, U> Iterator for Gather<'a, I, U>
+    where I: Iterator<Item = usize>
+{
+    type Item = &'a U;
+
+    fn next(&mut self) -> Option<&'a U> {
+        self.indices.next().map(|index| &self.column[index])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+/// Reorders `column` in place so that its `i`th element is the element previously at
+/// `permutation[i]`.
+///
+/// Panics if `permutation` is not a permutation of `0..column.len()`, which is checked before
+/// `column` is touched, so that a panic leaves it as it was.
+pub fn apply_permutation<T>(permutation: &[usize], column: &mut [T]) {
+    assert_eq!(permutation.len(), column.len(), "permutation and column lengths differ");
+    let mut visited = vec![false; permutation.len()];
+    for &index in permutation {
+        assert!(index < permutation.len(), "index {} is out of bounds in permutation", index);
+        assert!(!visited[index], "index {} appears twice in permutation", index);
+        visited[index] = true;
+    }
+    // Every index appears once, so the elements still to be moved are the ones left marked.
+    for start in 0..permutation.len() {
+        if !visited[start] {
+            continue;
+        }
+        // Follow the cycle containing `start`, pulling each element into place with a swap.
+        let mut i = start;
+        visited[i] = false;
+        loop {
+            let next = permutation[i];
+            if next == start {
+                break;
+            }
+            column.swap(i, next);
+            visited[next] = false;
+            i = next;
+        }
+    }
+}
+
+/// A reference to a slice element along with its index, ordered by the element alone.
+#[derive(Debug)]
+struct ByValue<'a, T: 'a>(&'a T, usize);
+
+impl<'a, T> Clone for ByValue<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for ByValue<'a, T> {}
+
+impl<'a, T: PartialEq> PartialEq for ByValue<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<'a, T: Eq> Eq for ByValue<'a, T> {}
+
+impl<'a, T: PartialOrd> PartialOrd for ByValue<'a, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.0.partial_cmp(other.0)
+    }
+}
+
+impl<'a, T: Ord> Ord for ByValue<'a, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(other.0)
+    }
+}
+
+/// Returns the first 8 bytes of `key` as a big-endian integer, padded with zeros.
+///
+/// Padding with zeros keeps the prefixes in the keys' order: a key that's a prefix of another
+/// sorts first, and so does its prefix, unless the two prefixes are equal.
+fn key_prefix(key: &[u8]) -> u64 {
+    let mut bytes = [0; 8];
+    let len = key.len().min(8);
+    bytes[..len].copy_from_slice(&key[..len]);
+    u64::from_be_bytes(bytes)
+}
+
+/// A reference to a slice element along with its index and the prefix of its sort key, ordered
+/// by the prefix and then by the element.
+#[derive(Debug)]
+struct ByPrefix<'a, T: 'a> {
+    prefix: u64,
+    value: &'a T,
+    index: usize,
+}
+
+impl<'a, T> Clone for ByPrefix<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for ByPrefix<'a, T> {}
+
+impl<'a, T: Ord> PartialEq for ByPrefix<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a, T: Ord> Eq for ByPrefix<'a, T> {}
+
+impl<'a, T: Ord> PartialOrd for ByPrefix<'a, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, T: Ord> Ord for ByPrefix<'a, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.prefix.cmp(&other.prefix).then_with(|| self.value.cmp(other.value))
+    }
+}
+
+#[test]
+fn argsort() {
+    let v = [30, 10, 50, 20, 40];
+    let indices: Vec<_> = v.argsort().collect();
+    assert_eq!(indices, vec![1, 3, 0, 4, 2]);
+    let mut argsort = v.argsort();
+    assert_eq!(argsort.nth(2), Some(0));
+    assert_eq!(argsort.len(), 2);
+    argsort.by_ref().for_each(drop);
+    assert_eq!(argsort.next(), None);
+    assert_eq!(v.argsort().take(2).map(|i| v[i]).collect::<Vec<_>>(), vec![10, 20]);
+}
+
+#[test]
+fn argsort_stable() {
+    let v: Vec<_> = (0..100).map(|i| (i * 7) % 5).collect();
+    let indices: Vec<_> = v.argsort_stable().collect();
+    let mut expected: Vec<_> = (0..v.len()).collect();
+    expected.sort_by_key(|&i| v[i]);
+    assert_eq!(indices, expected);
+    let mut argsort = v.argsort_stable();
+    assert_eq!(argsort.len(), v.len());
+    assert!(argsort.by_ref().take(10).eq(expected[..10].iter().cloned()));
+    assert_eq!(argsort.len(), v.len() - 10);
+}
+
+#[test]
+fn argsort_by_prefix() {
+    use std::string::String;
+
+    // Long shared prefixes, strings shorter than a prefix, and embedded zero bytes.
+    let v: Vec<String> = (0..500)
+        .map(|i| {
+            let tail: String = "ab\0c".chars().cycle().skip(i % 4).take(i % 7).collect();
+            if i % 3 == 0 { tail } else { format!("a shared prefix {}{}", i % 13, tail) }
+        })
+        .collect();
+    let mut expected: Vec<_> = (0..v.len()).collect();
+    expected.sort_by(|&a, &b| v[a].cmp(&v[b]));
+    let sorted: Vec<_> = v.argsort_by_prefix().map(|i| &v[i]).collect();
+    assert!(sorted.into_iter().eq(expected.iter().map(|&i| &v[i])));
+    assert!(key_prefix(b"ab") < key_prefix(b"ab\0\xff") && key_prefix(b"") == 0);
+}
+
+#[test]
+fn quick_sort_refs() {
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct NoClone(u32);
+
+    let v: Vec<_> = (0..100).map(|i| NoClone((i * 37) % 100)).collect();
+    let sorted: Vec<_> = v.quick_sort_refs().take(3).collect();
+    assert_eq!(sorted, [&NoClone(0), &NoClone(1), &NoClone(2)]);
+    assert!(std::ptr::eq(sorted[1], &v[73]));
+}
+
+#[test]
+fn gather_columns() {
+    let scores = [30, 10, 50, 20, 40];
+    let names = ["c", "a", "e", "b", "d"];
+    let top: Vec<_> = scores.argsort().take(3).collect();
+    assert_eq!(gather(top.iter().cloned(), &names).collect::<Vec<_>>(), vec![&"a", &"b", &"c"]);
+
+    let permutation: Vec<_> = scores.argsort().collect();
+    let mut names = names;
+    apply_permutation(&permutation, &mut names);
+    assert_eq!(names, ["a", "b", "c", "d", "e"]);
+}
+
+#[test]
+#[should_panic]
+fn apply_permutation_repeated_index() {
+    apply_permutation(&[1, 1, 0], &mut [1, 2, 3]);
+}
+
+#[test]
+fn apply_permutation_checks_first() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    let mut column = [1, 2, 3, 4];
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        apply_permutation(&[1, 0, 3, 3], &mut column);
+    }));
+    assert!(result.is_err());
+    assert_eq!(column, [1, 2, 3, 4]);
+}
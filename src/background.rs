@@ -0,0 +1,40 @@
+//! Sorting on a background thread, sending the sorted elements through a channel.
+
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use super::LazySortIterator;
+
+/// Lazily sorts `iter` with quicksort on a new thread, sending the elements in order through a
+/// channel that holds at most `bound` of them at once.
+///
+/// The sort runs up to `bound` elements ahead of the receiver, overlapping its work with the
+/// receiver's, and stops once the receiver is dropped, so taking only the first few elements
+/// doesn't sort the rest. If the sorting thread panics, the channel disconnects early.
+///
+/// ```
+/// let sorted = lazy_sort::spawn_sorted(vec![5, 1, 4, 2, 3], 2);
+/// assert_eq!(sorted.iter().collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+/// ```
+pub fn spawn_sorted<I>(iter: I, bound: usize) -> Receiver<I::Item>
+    where I: IntoIterator + Send + 'static,
+          I::Item: Ord + Send + 'static
+{
+    let (tx, rx) = mpsc::sync_channel(bound);
+    thread::spawn(move || {
+        // Sending fails once the receiver is dropped, which ends the sort.
+        let _ = iter.into_iter().quick_sort().try_for_each(|el| tx.send(el));
+    });
+    rx
+}
+
+#[test]
+fn background_sort() {
+    let v: Vec<u32> = (0..10_000).map(|i| (i * 7919) % 10_007).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    let sorted = spawn_sorted(v.clone(), 16);
+    assert!(sorted.iter().take(10).eq(expected[..10].iter().cloned()));
+    drop(sorted);
+    let sorted = spawn_sorted(v, 0);
+    assert_eq!(sorted.iter().collect::<Vec<_>>(), expected);
+}
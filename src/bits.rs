@@ -0,0 +1,147 @@
+//! Mapping floats and signed integers to unsigned integers in the same order.
+
+use std::iter::FusedIterator;
+use super::{LazySortIterator, LazySorted, QuickSort};
+
+/// A primitive that maps to an unsigned integer of the same width, such that comparing the
+/// integers orders the values.
+///
+/// Signed integers have their sign bit flipped. Floats are ordered like `total_cmp`: negative
+/// values have all their bits flipped and the rest just their sign bit, so `-0.0` comes before
+/// `0.0` and NaNs with the sign bit clear come after every other value. Sorting the integers
+/// is then a single integer comparison per step, and they suit radix sorts.
+///
+/// ```
+/// use lazy_sort::OrderedBits;
+///
+/// assert!((-1.5f64).to_ordered_bits() < 0.25f64.to_ordered_bits());
+/// assert!((-3i32).to_ordered_bits() < 2i32.to_ordered_bits());
+/// assert_eq!(f32::from_ordered_bits((-0.0f32).to_ordered_bits()).to_bits(), (-0.0f32).to_bits());
+/// ```
+pub trait OrderedBits: Copy {
+    /// The unsigned integer the values map to.
+    type Bits: Ord + Copy;
+
+    /// Maps the value to an integer that orders it among the others.
+    fn to_ordered_bits(self) -> Self::Bits;
+
+    /// Maps an integer returned by [`to_ordered_bits`](#tymethod.to_ordered_bits) back to its
+    /// value.
+    fn from_ordered_bits(bits: Self::Bits) -> Self;
+}
+
+macro_rules! unsigned_bits {
+    ($($t:ty),*) => {$(
+        impl OrderedBits for $t {
+            type Bits = $t;
+
+            fn to_ordered_bits(self) -> $t {
+                self
+            }
+
+            fn from_ordered_bits(bits: $t) -> $t {
+                bits
+            }
+        }
+    )*}
+}
+
+macro_rules! signed_bits {
+    ($($t:ty => $bits:ty),*) => {$(
+        impl OrderedBits for $t {
+            type Bits = $bits;
+
+            fn to_ordered_bits(self) -> $bits {
+                (self as $bits) ^ (1 << (<$bits>::BITS - 1))
+            }
+
+            fn from_ordered_bits(bits: $bits) -> $t {
+                (bits ^ (1 << (<$bits>::BITS - 1))) as $t
+            }
+        }
+    )*}
+}
+
+macro_rules! float_bits {
+    ($($t:ty => $bits:ty),*) => {$(
+        impl OrderedBits for $t {
+            type Bits = $bits;
+
+            fn to_ordered_bits(self) -> $bits {
+                let bits = self.to_bits();
+                let sign = 1 << (<$bits>::BITS - 1);
+                if bits & sign == 0 { bits | sign } else { !bits }
+            }
+
+            fn from_ordered_bits(bits: $bits) -> $t {
+                let sign = 1 << (<$bits>::BITS - 1);
+                <$t>::from_bits(if bits & sign == 0 { !bits } else { bits & !sign })
+            }
+        }
+    )*}
+}
+
+unsigned_bits!(u8, u16, u32, u64, u128, usize);
+signed_bits!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+float_bits!(f32 => u32, f64 => u64);
+
+/// Lazily sorts primitives by their [ordered bits](trait.OrderedBits.html), which sorts floats
+/// the way `total_cmp` orders them, comparing integers rather than floats.
+///
+/// ```
+/// use lazy_sort::quick_sort_bits;
+///
+/// let sorted: Vec<f64> = quick_sort_bits(vec![2.5, -1.0, 0.0, -0.0, 7.25]).take(4).collect();
+/// assert_eq!(sorted, [-1.0, -0.0, 0.0, 2.5]);
+/// ```
+pub fn quick_sort_bits<I>(iter: I) -> SortedBits<I::Item>
+    where I: IntoIterator,
+          I::Item: OrderedBits
+{
+    SortedBits(iter.into_iter().map(OrderedBits::to_ordered_bits).quick_sort())
+}
+
+/// Primitives lazily sorted by their ordered bits, created by
+/// [`quick_sort_bits`](fn.quick_sort_bits.html).
+#[derive(Debug, Clone)]
+pub struct SortedBits<T: OrderedBits>(QuickSort<T::Bits>);
+
+impl<T: OrderedBits> SortedBits<T> {
+    /// Returns the next element in sorted order without consuming it.
+    pub fn peek(&mut self) -> Option<T> {
+        self.0.peek().map(|&bits| T::from_ordered_bits(bits))
+    }
+}
+
+impl<T: OrderedBits> Iterator for SortedBits<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.next().map(T::from_ordered_bits)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T: OrderedBits> ExactSizeIterator for SortedBits<T> {}
+
+impl<T: OrderedBits> FusedIterator for SortedBits<T> {}
+
+#[test]
+fn ordered_bits() {
+    use std::vec::Vec;
+
+    let floats = [f64::NEG_INFINITY, -2.5, -0.0, 0.0, 1e-300, 3.0, f64::INFINITY, f64::NAN];
+    assert!(floats.windows(2).all(|w| w[0].to_ordered_bits() < w[1].to_ordered_bits()));
+    for &x in &floats {
+        assert_eq!(f64::from_ordered_bits(x.to_ordered_bits()).to_bits(), x.to_bits());
+    }
+    let ints: Vec<i16> = (-300..300).map(|i| i * 97 % 301).collect();
+    let mut sorted = ints.clone();
+    sorted.sort();
+    assert!(quick_sort_bits(ints).eq(sorted));
+    assert_eq!(quick_sort_bits(vec![-1i8, i8::MIN, i8::MAX, 0]).collect::<Vec<_>>(),
+               [i8::MIN, -1, 0, i8::MAX]);
+}
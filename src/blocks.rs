@@ -0,0 +1,96 @@
+//! Lending the sorted output of a quicksort in blocks.
+
+use std::vec::Vec;
+use super::{LazySorted, QuickSort};
+
+/// The sorted elements of a quicksort in blocks, created by
+/// [`QuickSort::sorted_blocks`](struct.QuickSort.html#method.sorted_blocks).
+///
+/// Each block is fully sorted, and follows every block before it in sorted order, so copying the
+/// blocks out one after another gives the sorted sequence. A block is made of the partitions
+/// finalized next: sorted partitions, partitions of at most `min_len` elements, which are sorted
+/// outright, and the pivots between them. Blocks are gathered until they hold at least `min_len`
+/// elements, so only the last block may be shorter.
+///
+/// `SortedBlocks` lends each block as a slice, so it isn't an `Iterator` itself; use
+/// [`next_block`](#method.next_block).
+///
+/// ```
+/// use lazy_sort::LazySortIterator;
+///
+/// let mut blocks = (0..1000).rev().quick_sort().sorted_blocks(64);
+/// let mut out = Vec::new();
+/// while let Some(block) = blocks.next_block() {
+///     assert!(block.len() >= 64 || out.len() + block.len() == 1000);
+///     out.extend_from_slice(block);
+/// }
+/// assert_eq!(out, (0..1000).collect::<Vec<_>>());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SortedBlocks<T> {
+    sorter: QuickSort<T>,
+    min_len: usize,
+    /// The block lent by the last call to `next_block`, whose buffer is reused for the next.
+    block: Vec<T>,
+}
+
+impl<T: Ord> SortedBlocks<T> {
+    pub(crate) fn new(sorter: QuickSort<T>, min_len: usize) -> SortedBlocks<T> {
+        SortedBlocks {
+            sorter,
+            min_len,
+            block: Vec::new(),
+        }
+    }
+
+    /// Returns the next block, or `None` once the elements run out.
+    pub fn next_block(&mut self) -> Option<&[T]> {
+        self.block.clear();
+        while self.block.len() < self.min_len.max(1) && !self.sorter.is_empty() {
+            self.sorter.next_block_into(self.min_len, &mut self.block);
+        }
+        if self.block.is_empty() { None } else { Some(&self.block) }
+    }
+
+    /// Returns the number of elements in the blocks not yet lent.
+    pub fn len(&self) -> usize {
+        self.sorter.len()
+    }
+
+    /// Returns true if every block has been lent.
+    pub fn is_empty(&self) -> bool {
+        self.sorter.is_empty()
+    }
+
+    /// Returns the sorter of the elements not yet lent.
+    pub fn into_inner(self) -> QuickSort<T> {
+        self.sorter
+    }
+}
+
+#[test]
+fn sorted_blocks() {
+    use super::LazySortIterator;
+
+    let v: Vec<u32> = (0..5000).map(|i| (i * 7919) % 2500).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    for &min_len in &[0, 1, 10, 100, 10_000] {
+        let mut blocks = v.iter().cloned().quick_sort().sorted_blocks(min_len);
+        let mut sorted = Vec::new();
+        while let Some(block) = blocks.next_block() {
+            assert!(block.windows(2).all(|w| w[0] <= w[1]));
+            let last = sorted.len() + block.len() == expected.len();
+            assert!(!block.is_empty() && (block.len() >= min_len || last));
+            sorted.extend_from_slice(block);
+        }
+        assert_eq!(sorted, expected);
+    }
+
+    let mut blocks = v.iter().cloned().quick_sort().sorted_blocks(100);
+    assert_eq!(blocks.next_block().map(|block| block[0]), Some(0));
+    let rest = blocks.into_inner();
+    let lent = 5000 - rest.len();
+    assert!(lent >= 100);
+    assert!(rest.eq(expected[lent..].iter().cloned()));
+}
@@ -0,0 +1,1031 @@
+//! Configuring how a lazy sort is performed.
+
+use std::error::Error;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "threads")]
+use std::thread;
+use std::vec::Vec;
+use super::{Config, HeapSort, LazySorted, QuickSort, ReverseOrder};
+use super::funnel::FunnelSort;
+#[cfg(feature = "std")]
+use super::adaptive::AdaptiveThreshold;
+#[cfg(feature = "std")]
+use super::DecisionLog;
+#[cfg(feature = "std")]
+use super::replay::Decisions;
+#[cfg(feature = "diagnostics")]
+use super::Diagnostics;
+#[cfg(feature = "instrument")]
+use super::Stats;
+#[cfg(feature = "threads")]
+use super::threads;
+use rng::{self, XorShift};
+
+/// The algorithm a [`LazySortBuilder`](struct.LazySortBuilder.html) sorts with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Lazy quicksort, as done by [`quick_sort`](trait.LazySortIterator.html#method.quick_sort).
+    Quick,
+    /// Lazy heapsort, as done by [`heap_sort`](trait.LazySortIterator.html#method.heap_sort).
+    Heap,
+    /// Lazy funnelsort, a cache-oblivious merge sort, for inputs large enough that cache misses
+    /// dominate quicksort's running time. Experimental: pivot settings, progress steps, and
+    /// comparison counts don't apply to it, and its first element costs more than quicksort's.
+    Funnel,
+}
+
+/// How quicksort chooses the pivot of each partition.
+///
+/// By default, each strategy's choices are perturbed at random; see
+/// [`LazySortBuilder::perturb_pivots`](struct.LazySortBuilder.html#method.perturb_pivots).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PivotStrategy {
+    /// The element in the middle of the partition.
+    Middle,
+    /// An element chosen uniformly at random, using the builder's seed.
+    Random,
+    /// The median of the first, middle, and last elements of the partition.
+    MedianOfThree,
+}
+
+/// An error returned when a lazy sort can't be constructed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SortError {
+    /// The input needed more than the configured memory cap, in bytes.
+    MemoryCapExceeded(usize),
+}
+
+impl fmt::Display for SortError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SortError::MemoryCapExceeded(cap) => {
+                write!(f, "input exceeded the memory cap of {} bytes", cap)
+            }
+        }
+    }
+}
+
+impl Error for SortError {}
+
+/// A handle for cancelling sorts from another thread.
+///
+/// A sort configured with
+/// [`LazySortBuilder::cancel_token`](struct.LazySortBuilder.html#method.cancel_token) stops
+/// yielding elements soon after the token is cancelled, abandoning any partitioning in progress.
+/// Its remaining elements can still be recovered with
+/// [`into_inner`](trait.LazySorted.html#tymethod.into_inner).
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Returns a token that hasn't been cancelled.
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Cancels every sort using this token. Cancellation can't be undone.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How far a sort has got, reported to the callback set with
+/// [`LazySortBuilder::progress`](struct.LazySortBuilder.html#method.progress).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of elements yielded so far.
+    pub emitted: usize,
+    /// The number of elements left to sort and yield.
+    pub remaining: usize,
+}
+
+#[derive(Clone)]
+struct ProgressFn(Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl fmt::Debug for ProgressFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ProgressFn")
+    }
+}
+
+/// A step of a quicksort, reported to the callback set with
+/// [`LazySortBuilder::on_step`](struct.LazySortBuilder.html#method.on_step).
+///
+/// The remaining elements are held in a chain of partitions, one at each depth, each nested in
+/// the one before it and holding elements no greater than its pivot. Indices are positions in the
+/// buffer of the partition at `depth`, whose pivot is its last element while it's being split.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// The elements at `a` and `b` were compared.
+    Compare {
+        /// The depth of the partition holding the elements.
+        depth: usize,
+        /// The index of one element.
+        a: usize,
+        /// The index of the other element.
+        b: usize,
+    },
+    /// The elements at `a` and `b` were swapped.
+    Swap {
+        /// The depth of the partition holding the elements.
+        depth: usize,
+        /// The index of one element.
+        a: usize,
+        /// The index of the other element.
+        b: usize,
+    },
+    /// The partition at `depth`, of `len` elements, was split around its pivot, and the `less`
+    /// elements after the pivot's new position were moved into a new partition at `depth + 1`.
+    Partition {
+        /// The depth of the split partition.
+        depth: usize,
+        /// The number of elements in the partition before the split.
+        len: usize,
+        /// The number of elements moved into the new partition.
+        less: usize,
+    },
+    /// The next element in sorted order was yielded.
+    Emit {
+        /// The number of elements left to yield.
+        remaining: usize,
+    },
+}
+
+#[derive(Clone)]
+pub(crate) struct StepFn(pub(crate) Arc<dyn Fn(Step) + Send + Sync>);
+
+impl fmt::Debug for StepFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("StepFn")
+    }
+}
+
+/// Configures and constructs a lazy sort.
+///
+/// ```
+/// use lazy_sort::{Algorithm, LazySortBuilder, PivotStrategy};
+///
+/// let sorted: Vec<_> = LazySortBuilder::new()
+///     .algorithm(Algorithm::Quick)
+///     .pivot(PivotStrategy::Random)
+///     .seed(7)
+///     .sort(vec![3, 1, 2])
+///     .unwrap()
+///     .collect();
+/// assert_eq!(sorted, [1, 2, 3]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LazySortBuilder {
+    algorithm: Algorithm,
+    pivot: PivotStrategy,
+    threshold: usize,
+    #[cfg(feature = "std")]
+    adaptive_threshold: bool,
+    seed: u64,
+    perturb: bool,
+    crossover: Option<f64>,
+    release: bool,
+    stable: bool,
+    memory_cap: Option<usize>,
+    #[cfg(feature = "threads")]
+    threads: Option<usize>,
+    #[cfg(feature = "prefetch")]
+    prefetch: bool,
+    progress: Option<ProgressFn>,
+    on_step: Option<StepFn>,
+    cancel: Option<CancelToken>,
+    #[cfg(feature = "std")]
+    decisions: Option<Decisions>,
+}
+
+impl Default for LazySortBuilder {
+    fn default() -> LazySortBuilder {
+        let config = Config::default();
+        LazySortBuilder {
+            algorithm: Algorithm::Quick,
+            pivot: config.pivot,
+            threshold: config.threshold,
+            #[cfg(feature = "std")]
+            adaptive_threshold: false,
+            seed: 0,
+            perturb: config.perturb,
+            crossover: config.crossover,
+            release: config.release,
+            stable: false,
+            memory_cap: None,
+            #[cfg(feature = "threads")]
+            threads: None,
+            #[cfg(feature = "prefetch")]
+            prefetch: config.prefetch,
+            progress: None,
+            on_step: None,
+            cancel: None,
+            #[cfg(feature = "std")]
+            decisions: None,
+        }
+    }
+}
+
+impl LazySortBuilder {
+    /// Returns a builder that sorts the same way as
+    /// [`quick_sort`](trait.LazySortIterator.html#method.quick_sort).
+    pub fn new() -> LazySortBuilder {
+        LazySortBuilder::default()
+    }
+
+    /// Sets the sorting algorithm. Defaults to `Algorithm::Quick`.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> LazySortBuilder {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets how quicksort chooses pivots. Defaults to `PivotStrategy::Middle`.
+    pub fn pivot(mut self, pivot: PivotStrategy) -> LazySortBuilder {
+        self.pivot = pivot;
+        self
+    }
+
+    /// Sets the size at or below which quicksort partitions are sorted eagerly with insertion
+    /// sort. Defaults to 32.
+    pub fn threshold(mut self, threshold: usize) -> LazySortBuilder {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets whether the threshold is chosen while sorting, from how long comparisons take in the
+    /// first few large partitions, rather than fixed: cheap comparisons get a larger threshold,
+    /// and expensive ones a smaller one. The [`threshold`](#method.threshold) applies until then.
+    /// Defaults to false.
+    ///
+    /// Partitions are timed with `std::time::Instant`, so on `wasm32-unknown-unknown`, which has
+    /// no clock, the threshold stays fixed.
+    #[cfg(feature = "std")]
+    pub fn adaptive_threshold(mut self, adaptive: bool) -> LazySortBuilder {
+        self.adaptive_threshold = adaptive;
+        self
+    }
+
+    /// Sets the seed used for random pivot selection. Unless pivots are
+    /// [unperturbed](#method.perturb_pivots), it's mixed with a seed chosen once per process.
+    pub fn seed(mut self, seed: u64) -> LazySortBuilder {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets whether pivot choices are perturbed with a seed chosen at random once per process, the
+    /// way `HashMap` seeds its hasher, so that input can't be crafted to drive quicksort towards
+    /// quadratic time. The middle and median-of-three strategies then choose from near their
+    /// usual positions, and random pivots depend on the process as well as the seed.
+    ///
+    /// Defaults to true with the `std` feature. Without it there's no source of randomness, so
+    /// this defaults to false, and turning it on only perturbs pivots with the seed. Turn it off
+    /// for pivots that are the same in every process, such as to reproduce a sort elsewhere.
+    pub fn perturb_pivots(mut self, perturb: bool) -> LazySortBuilder {
+        self.perturb = perturb;
+        self
+    }
+
+    /// Sets whether quicksort prefetches the elements ahead of its scans while partitioning
+    /// partitions too large for the cache. This helps with large elements, where partitioning
+    /// waits on memory, but the extra instructions can cost more than they save for small ones,
+    /// so it defaults to false.
+    #[cfg(feature = "prefetch")]
+    pub fn prefetch(mut self, prefetch: bool) -> LazySortBuilder {
+        self.prefetch = prefetch;
+        self
+    }
+
+    /// Sets the fraction of the input after which quicksort sorts the rest eagerly, with
+    /// `sort_unstable`, or `None` to keep it lazy to the end. By then the consumer is likely to
+    /// drain the rest, which costs less sorted in one go than partition by partition. Defaults to
+    /// half.
+    ///
+    /// Sorts with an [`on_step`](#method.on_step) callback or a
+    /// [cancellation token](#method.cancel_token) always stay lazy.
+    pub fn eager_crossover(mut self, fraction: Option<f64>) -> LazySortBuilder {
+        self.crossover = fraction;
+        self
+    }
+
+    /// Sets whether quicksort gives back the memory of its buffers as their elements are
+    /// consumed, so that a sorter that's iterated slowly doesn't hold on to its peak memory
+    /// until it's dropped. A buffer is shrunk once three quarters of it is unused, which copies
+    /// its remaining elements. Defaults to false.
+    pub fn release_memory(mut self, release: bool) -> LazySortBuilder {
+        self.release = release;
+        self
+    }
+
+    /// Sets whether equal elements are yielded in the order they were input. This costs an extra
+    /// `usize` per element. Defaults to false.
+    pub fn stable(mut self, stable: bool) -> LazySortBuilder {
+        self.stable = stable;
+        self
+    }
+
+    /// Sets the maximum number of bytes the sorter may use to buffer the input. Sorting fails with
+    /// `SortError::MemoryCapExceeded` if the input doesn't fit. Defaults to no cap.
+    pub fn memory_cap(mut self, bytes: usize) -> LazySortBuilder {
+        self.memory_cap = Some(bytes);
+        self
+    }
+
+    /// Sets the number of threads [`par_sort`](#method.par_sort) partitions with. Defaults to
+    /// the available parallelism.
+    #[cfg(feature = "threads")]
+    pub fn threads(mut self, threads: usize) -> LazySortBuilder {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Sets a callback that's called with the sort's progress after each element it yields.
+    pub fn progress<F>(mut self, callback: F) -> LazySortBuilder
+        where F: Fn(Progress) + Send + Sync + 'static
+    {
+        self.progress = Some(ProgressFn(Arc::new(callback)));
+        self
+    }
+
+    /// Sets a callback that's called with each comparison, swap, partition and yielded element
+    /// of a quicksort, for visualizing how it works. Partitioning done in parallel by
+    /// [`par_sort`](#method.par_sort) isn't reported, and heapsorts report nothing.
+    ///
+    /// Sorts with a callback partition one comparison at a time, which is slower.
+    pub fn on_step<F>(mut self, callback: F) -> LazySortBuilder
+        where F: Fn(Step) + Send + Sync + 'static
+    {
+        self.on_step = Some(StepFn(Arc::new(callback)));
+        self
+    }
+
+    /// Sets a token that stops the sort once it's cancelled. Quicksort checks it while
+    /// partitioning, and heapsort while building its heap, so even a long call to `next` or
+    /// `sort` returns soon after cancellation.
+    pub fn cancel_token(mut self, token: CancelToken) -> LazySortBuilder {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Sets a log that quicksort records each pivot it chooses in, so that the sort can be
+    /// reproduced with [`replay_decisions`](#method.replay_decisions).
+    #[cfg(feature = "std")]
+    pub fn record_decisions(mut self, log: DecisionLog) -> LazySortBuilder {
+        self.decisions = Some(Decisions::Record(log));
+        self
+    }
+
+    /// Sets a log of pivots that quicksort chooses in order, in place of its pivot strategy.
+    ///
+    /// Sorting panics if the log runs out, or one of its pivots is out of bounds, which is a sign
+    /// that the input isn't the one that was recorded.
+    #[cfg(feature = "std")]
+    pub fn replay_decisions(mut self, log: DecisionLog) -> LazySortBuilder {
+        self.decisions = Some(Decisions::Replay(log));
+        self
+    }
+
+    /// Collects `iter` into a lazy sorter configured by this builder.
+    pub fn sort<I>(self, iter: I) -> Result<LazySort<I::Item>, SortError>
+        where I: IntoIterator,
+              I::Item: Ord
+    {
+        self.sort_with(iter, QuickSort::with_config, QuickSort::with_config)
+    }
+
+    /// Like [`sort`](#method.sort), but quicksort's partitioning up to the first element is
+    /// split across several threads, which shortens the time to the first element of a large
+    /// input.
+    #[cfg(feature = "threads")]
+    pub fn par_sort<I>(self, iter: I) -> Result<LazySort<I::Item>, SortError>
+        where I: IntoIterator,
+              I::Item: Ord + Send + Sync
+    {
+        let threads = self.threads.unwrap_or_else(|| {
+            thread::available_parallelism().map_or(1, |threads| threads.get())
+        });
+        self.sort_with(iter,
+                       |v, config| threads::quick_sort(v, config, threads),
+                       |v, config| threads::quick_sort(v, config, threads))
+    }
+
+    fn sort_with<I, Q, S>(self, iter: I, quick_sort: Q, stable_quick_sort: S)
+        -> Result<LazySort<I::Item>, SortError>
+        where I: IntoIterator,
+              I::Item: Ord,
+              Q: FnOnce(Vec<I::Item>, Config) -> QuickSort<I::Item>,
+              S: FnOnce(Vec<(I::Item, usize)>, Config) -> QuickSort<(I::Item, usize)>
+    {
+        let sorter = if self.stable {
+            let v = collect_capped(iter.into_iter().enumerate().map(|(i, el)| (el, i)),
+                                   self.memory_cap)?;
+            match self.algorithm {
+                Algorithm::Quick => Sorter::StableQuick(stable_quick_sort(v, self.config())),
+                Algorithm::Heap => {
+                    self.cancellable_heap_sort(v, Sorter::StableHeap, Sorter::StableQuick)
+                }
+                Algorithm::Funnel => Sorter::StableFunnel(FunnelSort::new(v)),
+            }
+        } else {
+            let v = collect_capped(iter.into_iter(), self.memory_cap)?;
+            match self.algorithm {
+                Algorithm::Quick => Sorter::Quick(quick_sort(v, self.config())),
+                Algorithm::Heap => self.cancellable_heap_sort(v, Sorter::Heap, Sorter::Quick),
+                Algorithm::Funnel => Sorter::Funnel(FunnelSort::new(v)),
+            }
+        };
+        Ok(LazySort(sorter, Monitor {
+            progress: self.progress,
+            cancel: self.cancel,
+            emitted: 0,
+        }))
+    }
+
+    /// Heapsorts `v`, checking the cancellation token while the heap is built. If the sort is
+    /// cancelled first, the elements are left in a quicksort instead, which doesn't order any of
+    /// them until it's asked for one.
+    fn cancellable_heap_sort<T, U>(&self,
+                                   v: Vec<T>,
+                                   heap: fn(HeapSort<T>) -> Sorter<U>,
+                                   quick: fn(QuickSort<T>) -> Sorter<U>)
+        -> Sorter<U>
+        where T: Ord
+    {
+        let token = match self.cancel {
+            Some(ref token) => token,
+            None => return heap(heap_sort(v)),
+        };
+        let reversed = v.into_iter().map(ReverseOrder).collect();
+        match HeapSort::from_reversed_cancellable(reversed, token) {
+            Ok(s) => heap(s),
+            Err(v) => {
+                let v = v.into_iter().map(|ReverseOrder(el)| el).collect();
+                quick(QuickSort::with_config(v, self.config()))
+            }
+        }
+    }
+
+    fn config(&self) -> Config {
+        let seed = if self.perturb { self.seed ^ rng::process_seed() } else { self.seed };
+        Config {
+            threshold: self.threshold,
+            // There's no clock to time partitions with on this target.
+            #[cfg(feature = "std")]
+            adaptive: if self.adaptive_threshold &&
+                         !cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+                Some(AdaptiveThreshold::default())
+            } else {
+                None
+            },
+            pivot: self.pivot,
+            rng: XorShift::new(seed),
+            crossover: self.crossover,
+            eager_in: None,
+            release: self.release,
+            perturb: self.perturb,
+            cancel: self.cancel.clone(),
+            on_step: self.on_step.clone(),
+            #[cfg(feature = "prefetch")]
+            prefetch: self.prefetch,
+            #[cfg(feature = "std")]
+            decisions: self.decisions.clone(),
+            #[cfg(feature = "instrument")]
+            stats: Stats::default(),
+            #[cfg(feature = "diagnostics")]
+            diagnostics: Diagnostics::default(),
+            #[cfg(feature = "diagnostics")]
+            depth_limit: 0,
+        }
+    }
+}
+
+fn collect_capped<I: Iterator>(iter: I, cap: Option<usize>) -> Result<Vec<I::Item>, SortError> {
+    let cap = match cap {
+        Some(cap) => cap,
+        None => return Ok(iter.collect()),
+    };
+    let max_len = cap.checked_div(mem::size_of::<I::Item>()).unwrap_or(usize::MAX);
+    let mut v = Vec::with_capacity(iter.size_hint().0.min(max_len));
+    for el in iter {
+        if v.len() == max_len {
+            return Err(SortError::MemoryCapExceeded(cap));
+        }
+        v.push(el);
+    }
+    Ok(v)
+}
+
+fn heap_sort<T: Ord>(v: Vec<T>) -> HeapSort<T> {
+    HeapSort::from_reversed(v.into_iter().map(ReverseOrder).collect())
+}
+
+/// A lazy sorter constructed by a [`LazySortBuilder`](struct.LazySortBuilder.html).
+#[derive(Debug, Clone)]
+pub struct LazySort<T>(Sorter<T>, Monitor);
+
+/// Reports a sort's progress and checks whether it has been cancelled.
+#[derive(Debug, Clone)]
+struct Monitor {
+    progress: Option<ProgressFn>,
+    cancel: Option<CancelToken>,
+    emitted: usize,
+}
+
+impl Monitor {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Sorter<T> {
+    Quick(QuickSort<T>),
+    Heap(HeapSort<T>),
+    // Stable sorts break ties between equal elements by their position in the input.
+    StableQuick(QuickSort<(T, usize)>),
+    StableHeap(HeapSort<(T, usize)>),
+    Funnel(FunnelSort<T>),
+    StableFunnel(FunnelSort<(T, usize)>),
+}
+
+impl<T: Ord> LazySort<T> {
+    /// Returns counts of the work the sorter has done so far.
+    #[cfg(feature = "instrument")]
+    pub fn stats(&self) -> &Stats {
+        match self.0 {
+            Sorter::Quick(ref s) => s.stats(),
+            Sorter::Heap(ref s) => s.stats(),
+            Sorter::StableQuick(ref s) => s.stats(),
+            Sorter::StableHeap(ref s) => s.stats(),
+            Sorter::Funnel(ref s) => s.stats(),
+            Sorter::StableFunnel(ref s) => s.stats(),
+        }
+    }
+
+    /// Returns signs of pathological pivot choices in the sort so far, or `None` for heapsorts and
+    /// funnelsorts, whose running time doesn't depend on the input.
+    #[cfg(feature = "diagnostics")]
+    pub fn diagnostics(&self) -> Option<&Diagnostics> {
+        match self.0 {
+            Sorter::Quick(ref s) => Some(s.diagnostics()),
+            Sorter::StableQuick(ref s) => Some(s.diagnostics()),
+            Sorter::Heap(_) | Sorter::StableHeap(_) => None,
+            Sorter::Funnel(_) | Sorter::StableFunnel(_) => None,
+        }
+    }
+
+    /// Returns the number of bytes of heap memory held by the sorter's buffers, counting their
+    /// full capacity but not any memory owned by the elements.
+    pub fn memory_usage(&self) -> usize {
+        match self.0 {
+            Sorter::Quick(ref s) => s.memory_usage(),
+            Sorter::Heap(ref s) => s.memory_usage(),
+            Sorter::StableQuick(ref s) => s.memory_usage(),
+            Sorter::StableHeap(ref s) => s.memory_usage(),
+            Sorter::Funnel(ref s) => s.memory_usage(),
+            Sorter::StableFunnel(ref s) => s.memory_usage(),
+        }
+    }
+}
+
+impl<T: Ord> Iterator for LazySort<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.1.is_cancelled() {
+            return None;
+        }
+        let next = match self.0 {
+            Sorter::Quick(ref mut s) => s.next(),
+            Sorter::Heap(ref mut s) => s.next(),
+            Sorter::StableQuick(ref mut s) => s.next().map(|(el, _)| el),
+            Sorter::StableHeap(ref mut s) => s.next().map(|(el, _)| el),
+            Sorter::Funnel(ref mut s) => s.next(),
+            Sorter::StableFunnel(ref mut s) => s.next().map(|(el, _)| el),
+        };
+        if next.is_some() {
+            self.1.emitted += 1;
+            if let Some(ref progress) = self.1.progress {
+                (progress.0)(Progress {
+                    emitted: self.1.emitted,
+                    remaining: self.len(),
+                });
+            }
+        }
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<T: Ord> FusedIterator for LazySort<T> {}
+
+impl<T: Ord> LazySorted<T> for LazySort<T> {
+    fn peek(&mut self) -> Option<&T> {
+        if self.1.is_cancelled() {
+            return None;
+        }
+        match self.0 {
+            Sorter::Quick(ref mut s) => s.peek(),
+            Sorter::Heap(ref mut s) => s.peek(),
+            Sorter::StableQuick(ref mut s) => s.peek().map(|el| &el.0),
+            Sorter::StableHeap(ref mut s) => s.peek().map(|el| &el.0),
+            Sorter::Funnel(ref mut s) => s.peek(),
+            Sorter::StableFunnel(ref mut s) => s.peek().map(|el| &el.0),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self.0 {
+            Sorter::Quick(ref s) => s.len(),
+            Sorter::Heap(ref s) => s.len(),
+            Sorter::StableQuick(ref s) => s.len(),
+            Sorter::StableHeap(ref s) => s.len(),
+            Sorter::Funnel(ref s) => s.len(),
+            Sorter::StableFunnel(ref s) => s.len(),
+        }
+    }
+
+    fn into_inner(self) -> Vec<T> {
+        match self.0 {
+            Sorter::Quick(s) => s.into_inner(),
+            Sorter::Heap(s) => s.into_inner(),
+            Sorter::StableQuick(s) => s.into_inner().into_iter().map(|(el, _)| el).collect(),
+            Sorter::StableHeap(s) => s.into_inner().into_iter().map(|(el, _)| el).collect(),
+            Sorter::Funnel(s) => s.into_inner(),
+            Sorter::StableFunnel(s) => s.into_inner().into_iter().map(|(el, _)| el).collect(),
+        }
+    }
+
+    fn advance_to(&mut self, x: &T) {
+        match self.0 {
+            Sorter::Quick(ref mut s) => s.advance_to(x),
+            Sorter::Heap(ref mut s) => s.advance_to(x),
+            _ => {
+                while self.peek().is_some_and(|el| el < x) {
+                    self.next();
+                }
+            }
+        }
+    }
+
+    fn next_chunk_into(&mut self, n: usize, out: &mut Vec<T>) {
+        match self.0 {
+            Sorter::Quick(ref mut s) if self.1.progress.is_none() && !self.1.is_cancelled() => {
+                let len = out.len();
+                s.next_chunk_into(n, out);
+                self.1.emitted += out.len() - len;
+            }
+            _ => out.extend(self.by_ref().take(n)),
+        }
+    }
+
+    fn drain_equal<F>(&mut self, x: &T, mut f: F)
+        where F: FnMut(T)
+    {
+        match self.0 {
+            Sorter::Quick(ref mut s) if self.1.progress.is_none() && !self.1.is_cancelled() => {
+                let emitted = &mut self.1.emitted;
+                s.drain_equal(x, |el| {
+                    *emitted += 1;
+                    f(el)
+                });
+            }
+            _ => {
+                while self.peek().is_some_and(|el| el == x) {
+                    f(self.next().unwrap());
+                }
+            }
+        }
+    }
+
+    fn split_at_rank(self, k: usize) -> (LazySort<T>, LazySort<T>) {
+        let (smallest, rest) = match self.0 {
+            Sorter::Quick(s) => {
+                let (smallest, rest) = s.split_at_rank(k);
+                (Sorter::Quick(smallest), Sorter::Quick(rest))
+            }
+            Sorter::Heap(s) => {
+                let (smallest, rest) = s.split_at_rank(k);
+                (Sorter::Heap(smallest), Sorter::Heap(rest))
+            }
+            Sorter::StableQuick(s) => {
+                let (smallest, rest) = s.split_at_rank(k);
+                (Sorter::StableQuick(smallest), Sorter::StableQuick(rest))
+            }
+            Sorter::StableHeap(s) => {
+                let (smallest, rest) = s.split_at_rank(k);
+                (Sorter::StableHeap(smallest), Sorter::StableHeap(rest))
+            }
+            Sorter::Funnel(s) => {
+                let (smallest, rest) = s.split_at_rank(k);
+                (Sorter::Funnel(smallest), Sorter::Funnel(rest))
+            }
+            Sorter::StableFunnel(s) => {
+                let (smallest, rest) = s.split_at_rank(k);
+                (Sorter::StableFunnel(smallest), Sorter::StableFunnel(rest))
+            }
+        };
+        (LazySort(smallest, self.1.clone()), LazySort(rest, self.1))
+    }
+
+    fn modify_remaining<F>(&mut self, f: F)
+        where F: FnOnce(&mut [T])
+    {
+        match self.0 {
+            Sorter::Quick(ref mut s) => s.modify_remaining(f),
+            Sorter::Heap(ref mut s) => s.modify_remaining(f),
+            Sorter::StableQuick(ref mut s) => {
+                let config = s.config.clone();
+                let empty = QuickSort::with_config(Vec::new(), config.clone());
+                let v = mem::replace(s, empty).into_inner();
+                *s = QuickSort::with_config(modify_stable(v, f), config);
+            }
+            Sorter::StableHeap(ref mut s) => {
+                let v = mem::replace(s, heap_sort(Vec::new())).into_inner();
+                *s = heap_sort(modify_stable(v, f));
+            }
+            Sorter::Funnel(ref mut s) => s.modify_remaining(f),
+            Sorter::StableFunnel(ref mut s) => {
+                let v = mem::replace(s, FunnelSort::new(Vec::new())).into_inner();
+                *s = FunnelSort::new(modify_stable(v, f));
+            }
+        }
+    }
+}
+
+/// Calls `f` with the elements of `v`, leaving them paired with their original input positions.
+fn modify_stable<T, F>(v: Vec<(T, usize)>, f: F) -> Vec<(T, usize)>
+    where F: FnOnce(&mut [T])
+{
+    let (mut elements, positions): (Vec<T>, Vec<usize>) = v.into_iter().unzip();
+    f(&mut elements);
+    elements.into_iter().zip(positions).collect()
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, Copy)]
+struct Keyed(u32, usize);
+
+#[cfg(test)]
+impl PartialEq for Keyed {
+    fn eq(&self, other: &Keyed) -> bool {
+        self.0 == other.0
+    }
+}
+
+#[cfg(test)]
+impl Eq for Keyed {}
+
+#[cfg(test)]
+impl PartialOrd for Keyed {
+    fn partial_cmp(&self, other: &Keyed) -> Option<::std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+impl Ord for Keyed {
+    fn cmp(&self, other: &Keyed) -> ::std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+#[test]
+fn builder_configurations() {
+    let v: Vec<u32> = (0..500).map(|i| (i * 7919) % 503).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    let pivots = [PivotStrategy::Middle, PivotStrategy::Random, PivotStrategy::MedianOfThree];
+    for &algorithm in &[Algorithm::Quick, Algorithm::Heap, Algorithm::Funnel] {
+        for &pivot in &pivots {
+            for &threshold in &[0, 1, 32, 1000] {
+                let sorted: Vec<_> = LazySortBuilder::new()
+                    .algorithm(algorithm)
+                    .pivot(pivot)
+                    .threshold(threshold)
+                    .seed(42)
+                    .sort(v.iter().cloned())
+                    .unwrap()
+                    .collect();
+                assert_eq!(sorted, expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn builder_perturb_pivots() {
+    use super::DecisionLog;
+
+    let first_pivot = |builder: LazySortBuilder| {
+        let log = DecisionLog::new();
+        let mut sorter = builder.record_decisions(log.clone()).sort(0..1000u32).unwrap();
+        assert_eq!(sorter.next(), Some(0));
+        log.pivots()[0]
+    };
+    assert_eq!(first_pivot(LazySortBuilder::new().perturb_pivots(false)), 500);
+    for _ in 0..10 {
+        let pivot = first_pivot(LazySortBuilder::new());
+        assert!((250..750).contains(&pivot));
+    }
+    assert_eq!(rng::process_seed(), rng::process_seed());
+}
+
+#[test]
+fn builder_stable() {
+    let v: Vec<_> = (0..300).map(|i| Keyed((i as u32 * 31) % 7, i)).collect();
+    let mut expected = v.clone();
+    expected.sort_by_key(|el| el.0);
+    for &algorithm in &[Algorithm::Quick, Algorithm::Heap, Algorithm::Funnel] {
+        let sorted: Vec<_> = LazySortBuilder::new()
+            .algorithm(algorithm)
+            .stable(true)
+            .sort(v.iter().cloned())
+            .unwrap()
+            .map(|el| el.1)
+            .collect();
+        assert_eq!(sorted, expected.iter().map(|el| el.1).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn builder_stable_modify_remaining() {
+    let v: Vec<_> = (0..100).map(|i| Keyed(i as u32 % 3, i)).collect();
+    let mut sorter = LazySortBuilder::new().stable(true).sort(v).unwrap();
+    sorter.modify_remaining(|rest| for el in rest.iter_mut() {
+        el.0 = 2 - el.0;
+    });
+    let sorted: Vec<_> = sorter.collect();
+    assert!(sorted.windows(2).all(|w| w[0].0 < w[1].0 || (w[0].0 == w[1].0 && w[0].1 < w[1].1)));
+}
+
+#[test]
+fn builder_memory_cap() {
+    let cap = 10 * mem::size_of::<u64>();
+    assert!(LazySortBuilder::new().memory_cap(cap).sort(0..10u64).is_ok());
+    assert_eq!(LazySortBuilder::new().memory_cap(cap).sort(0..11u64).unwrap_err(),
+               SortError::MemoryCapExceeded(cap));
+}
+
+#[test]
+fn builder_release_memory() {
+    let v: Vec<u64> = (0..100_000).map(|i| (i * 7919) % 100_000).collect();
+    // Unperturbed pivots fix the partitions, and with them the buffers left after the prefix.
+    let usage = |release, crossover| {
+        let mut sorter = LazySortBuilder::new().perturb_pivots(false)
+                                               .release_memory(release)
+                                               .eager_crossover(crossover)
+                                               .sort(v.clone())
+                                               .unwrap();
+        let full = sorter.memory_usage();
+        assert!(sorter.by_ref().take(90_000).eq(0..90_000));
+        (full, sorter.memory_usage())
+    };
+    // A lazy sort holds on to the whole input buffer unless it's released.
+    let (full, kept) = usage(false, None);
+    assert!(kept >= full);
+    // Buffers are shrunk once three quarters of them are unused, so the rest of the sort takes
+    // at most four times the room of the elements left in it.
+    let remaining = (v.len() - 90_000) * mem::size_of::<u64>();
+    for &crossover in &[None, Some(0.5)] {
+        let (full, released) = usage(true, crossover);
+        assert!(released <= 4 * remaining, "{} of {} bytes are still held", released, full);
+    }
+}
+
+#[cfg(feature = "threads")]
+#[test]
+fn builder_par_sort() {
+    let v: Vec<_> = (0..50_000).map(|i| Keyed((i as u32 * 7919) % 1000, i)).collect();
+    let mut expected = v.clone();
+    expected.sort_by_key(|el| el.0);
+    let sorted: Vec<_> = LazySortBuilder::new().stable(true)
+                                               .threads(4)
+                                               .par_sort(v)
+                                               .unwrap()
+                                               .map(|el| el.1)
+                                               .collect();
+    assert_eq!(sorted, expected.iter().map(|el| el.1).collect::<Vec<_>>());
+}
+
+#[cfg(all(feature = "threads", feature = "instrument"))]
+#[test]
+fn builder_par_sort_crossover() {
+    let mut rng = XorShift::new(9);
+    let v: Vec<u64> = (0..200_000).map(|_| rng.next_u64()).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    let partitions = |crossover| {
+        let mut sorter = LazySortBuilder::new().threads(4)
+                                               .eager_crossover(crossover)
+                                               .par_sort(v.clone())
+                                               .unwrap();
+        assert!(sorter.by_ref().eq(expected.iter().cloned()));
+        sorter.stats().partition_sizes.len()
+    };
+    // Sorting the last nine tenths eagerly skips most of the splits a lazy sort makes.
+    let (eager, lazy) = (partitions(Some(0.1)), partitions(None));
+    assert!(eager * 2 < lazy, "{} splits with the crossover and {} without", eager, lazy);
+}
+
+#[cfg(all(feature = "threads", feature = "diagnostics"))]
+#[test]
+fn builder_par_sort_diagnostics() {
+    let mut rng = XorShift::new(10);
+    let v: Vec<u64> = (0..200_000).map(|_| rng.next_u64()).collect();
+    let mut sorter = LazySortBuilder::new().threads(4).par_sort(v).unwrap();
+    assert!(sorter.by_ref().take(1000).is_sorted());
+    let report = *sorter.diagnostics().unwrap();
+    // The partitions split on other threads count towards the depth of those nested in them.
+    assert!(report.max_depth > 0 && report.max_depth <= Diagnostics::depth_limit(200_000));
+    assert_eq!(report.fallback_triggers, 0);
+    assert!(report.worst_split.is_some());
+}
+
+#[test]
+fn builder_progress_and_cancel() {
+    use std::sync::Mutex;
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let token = CancelToken::new();
+    let mut sorter = {
+        let reports = reports.clone();
+        LazySortBuilder::new().progress(move |progress| reports.lock().unwrap().push(progress))
+                              .cancel_token(token.clone())
+                              .sort(vec![3, 1, 2])
+                              .unwrap()
+    };
+    assert_eq!(sorter.next(), Some(1));
+    assert_eq!(*reports.lock().unwrap(), [Progress { emitted: 1, remaining: 2 }]);
+    token.cancel();
+    assert_eq!(sorter.next(), None);
+    assert_eq!(sorter.into_inner().len(), 2);
+
+    // A cancelled sort abandons partitioning instead of finishing it.
+    let mut sorter = LazySortBuilder::new().cancel_token(token.clone()).sort(0..10_000).unwrap();
+    if let Sorter::Quick(ref mut s) = sorter.0 {
+        assert_eq!(s.next(), None);
+    }
+    assert_eq!(sorter.len(), 10_000);
+
+    // So does one cancelled while its heap is being built.
+    let v: Vec<u32> = (0..10_000).map(|i| (i * 7919) % 10_007).collect();
+    let heap = |token| LazySortBuilder::new().algorithm(Algorithm::Heap).cancel_token(token);
+    let mut sorter = heap(token.clone()).sort(v.clone()).unwrap();
+    assert_eq!(sorter.next(), None);
+    #[cfg(feature = "instrument")]
+    assert_eq!(sorter.stats().comparisons, 0);
+    assert_eq!(sorter.into_inner().len(), v.len());
+    let mut expected = v.clone();
+    expected.sort();
+    assert_eq!(heap(CancelToken::new()).sort(v).unwrap().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn builder_on_step() {
+    use std::sync::Mutex;
+
+    let steps = Arc::new(Mutex::new(Vec::new()));
+    let v: Vec<u32> = (0..100).map(|i| (i * 37) % 101).collect();
+    let sorted: Vec<_> = {
+        let steps = steps.clone();
+        LazySortBuilder::new().threshold(4)
+                              .pivot(PivotStrategy::MedianOfThree)
+                              .perturb_pivots(false)
+                              .on_step(move |step| steps.lock().unwrap().push(step))
+                              .sort(v.clone())
+                              .unwrap()
+                              .take(3)
+                              .collect()
+    };
+    assert_eq!(sorted, [0, 1, 2]);
+    let steps = steps.lock().unwrap();
+    let emitted: Vec<_> = steps.iter().filter_map(|step| match *step {
+        Step::Emit { remaining } => Some(remaining),
+        _ => None,
+    }).collect();
+    assert_eq!(emitted, [99, 98, 97]);
+    // The first split is of the whole input, and each split is nested in the one before it.
+    let splits: Vec<_> = steps.iter().filter_map(|step| match *step {
+        Step::Partition { depth, len, less } => Some((depth, len, less)),
+        _ => None,
+    }).collect();
+    assert_eq!((splits[0].0, splits[0].1), (0, 100));
+    assert!(splits.windows(2).all(|w| w[1].0 == w[0].0 + 1 && w[1].1 == w[0].2));
+    assert!(steps.iter().any(|step| match *step {
+        Step::Compare { depth, a, b } => depth == 0 && a < 100 && b < 100,
+        _ => false,
+    }));
+}
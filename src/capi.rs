@@ -0,0 +1,167 @@
+//! A C interface for lazily selecting the smallest elements of buffers of primitives.
+//!
+//! Enabled by the `capi` feature. `cargo build --release -p lazy-sort-capi` builds the shared
+//! library, `liblazy_sort_capi.so` or its equivalent on the target, and `include/lazy_sort.h`
+//! declares the functions.
+//!
+//! For each element type there's a one-shot function, such as `lazy_sort_smallest_u32`, that
+//! copies the `k` smallest elements of a buffer into another in ascending order, and an opaque
+//! handle, such as [`LazySortU32`](struct.LazySortU32.html), that copies a buffer once and yields
+//! its elements in ascending order on demand. Floats are sorted by their
+//! [ordered bits](../trait.OrderedBits.html), in the order of `total_cmp`, so NaNs with the sign
+//! bit clear sort after every other value.
+
+use std::boxed::Box;
+use std::slice;
+use super::{LazySortIterator, LazySorted, OrderedBits, QuickSort};
+
+/// Returns the `len` elements at `data`, which may be null if `len` is zero.
+unsafe fn input<'a, T>(data: *const T, len: usize) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+/// Writes the elements of `iter` to `out`, returning how many were written.
+unsafe fn output<T, I: Iterator<Item = T>>(iter: I, out: *mut T) -> usize {
+    let mut written = 0;
+    for el in iter {
+        out.add(written).write(el);
+        written += 1;
+    }
+    written
+}
+
+macro_rules! capi {
+    ($t:ty, $key:ty, $wrap:expr, $unwrap:expr, $handle:ident, $smallest:ident, $new:ident,
+     $next:ident, $next_n:ident, $remaining:ident, $free:ident) => {
+        #[doc = concat!("Copies the `k` smallest of the `len` `", stringify!($t),
+                        "`s at `data` to `out` in ascending order, returning how many were \
+                        copied.")]
+        ///
+        /// # Safety
+        ///
+        /// `data` must point to `len` readable elements, and `out` to space for `min(k, len)`
+        /// elements. Either may be null if nothing is read from or written to it.
+        #[no_mangle]
+        pub unsafe extern "C" fn $smallest(data: *const $t, len: usize, k: usize, out: *mut $t)
+            -> usize
+        {
+            let sorted = input(data, len).iter().map(|&el| $wrap(el)).quick_sort();
+            output(sorted.take(k).map($unwrap), out)
+        }
+
+        #[doc = concat!("A lazy sort of `", stringify!($t), "`s, created by [`", stringify!($new),
+                        "`](fn.", stringify!($new), ".html).")]
+        pub struct $handle(QuickSort<$key>);
+
+        #[doc = concat!("Copies the `len` `", stringify!($t), "`s at `data` into a new \
+                        handle that yields them in ascending order. Free it with [`",
+                        stringify!($free), "`](fn.", stringify!($free), ".html).")]
+        ///
+        /// # Safety
+        ///
+        /// `data` must point to `len` readable elements, and may be null if `len` is zero.
+        #[no_mangle]
+        pub unsafe extern "C" fn $new(data: *const $t, len: usize) -> *mut $handle {
+            let sorted = input(data, len).iter().map(|&el| $wrap(el)).quick_sort();
+            Box::into_raw(Box::new($handle(sorted)))
+        }
+
+        /// Writes the next smallest element to `out`, returning false if there are none left.
+        ///
+        /// # Safety
+        ///
+        /// `handle` must be a live handle, and `out` must point to space for an element.
+        #[no_mangle]
+        pub unsafe extern "C" fn $next(handle: *mut $handle, out: *mut $t) -> bool {
+            output((*handle).0.next().map($unwrap).into_iter(), out) == 1
+        }
+
+        /// Writes up to `n` of the next smallest elements to `out` in ascending order, returning
+        /// how many were written.
+        ///
+        /// # Safety
+        ///
+        /// `handle` must be a live handle, and `out` must point to space for `n` elements, or be
+        /// null if `n` is zero.
+        #[no_mangle]
+        pub unsafe extern "C" fn $next_n(handle: *mut $handle, out: *mut $t, n: usize)
+            -> usize
+        {
+            output((*handle).0.by_ref().take(n).map($unwrap), out)
+        }
+
+        /// Returns the number of elements the handle has yet to yield.
+        ///
+        /// # Safety
+        ///
+        /// `handle` must be a live handle.
+        #[no_mangle]
+        pub unsafe extern "C" fn $remaining(handle: *const $handle) -> usize {
+            (*handle).0.len()
+        }
+
+        /// Frees a handle. Does nothing if `handle` is null.
+        ///
+        /// # Safety
+        ///
+        /// `handle` must be null or a live handle, which isn't used again.
+        #[no_mangle]
+        pub unsafe extern "C" fn $free(handle: *mut $handle) {
+            if !handle.is_null() {
+                drop(Box::from_raw(handle));
+            }
+        }
+    }
+}
+
+capi!(i32, i32, |el| el, |el| el, LazySortI32, lazy_sort_smallest_i32, lazy_sort_i32_new,
+      lazy_sort_i32_next, lazy_sort_i32_next_n, lazy_sort_i32_remaining, lazy_sort_i32_free);
+capi!(u32, u32, |el| el, |el| el, LazySortU32, lazy_sort_smallest_u32, lazy_sort_u32_new,
+      lazy_sort_u32_next, lazy_sort_u32_next_n, lazy_sort_u32_remaining, lazy_sort_u32_free);
+capi!(i64, i64, |el| el, |el| el, LazySortI64, lazy_sort_smallest_i64, lazy_sort_i64_new,
+      lazy_sort_i64_next, lazy_sort_i64_next_n, lazy_sort_i64_remaining, lazy_sort_i64_free);
+capi!(u64, u64, |el| el, |el| el, LazySortU64, lazy_sort_smallest_u64, lazy_sort_u64_new,
+      lazy_sort_u64_next, lazy_sort_u64_next_n, lazy_sort_u64_remaining, lazy_sort_u64_free);
+capi!(f32, u32, f32::to_ordered_bits, f32::from_ordered_bits, LazySortF32,
+      lazy_sort_smallest_f32, lazy_sort_f32_new, lazy_sort_f32_next, lazy_sort_f32_next_n,
+      lazy_sort_f32_remaining, lazy_sort_f32_free);
+capi!(f64, u64, f64::to_ordered_bits, f64::from_ordered_bits, LazySortF64,
+      lazy_sort_smallest_f64, lazy_sort_f64_new, lazy_sort_f64_next, lazy_sort_f64_next_n,
+      lazy_sort_f64_remaining, lazy_sort_f64_free);
+
+#[test]
+fn capi() {
+    use std::ptr;
+
+    let data = [5u32, 3, 9, 1, 7];
+    let mut out = [0; 3];
+    unsafe {
+        assert_eq!(lazy_sort_smallest_u32(data.as_ptr(), data.len(), 3, out.as_mut_ptr()), 3);
+        assert_eq!(out, [1, 3, 5]);
+        assert_eq!(lazy_sort_smallest_u32(ptr::null(), 0, 3, out.as_mut_ptr()), 0);
+
+        let handle = lazy_sort_u32_new(data.as_ptr(), data.len());
+        let mut el = 0;
+        assert!(lazy_sort_u32_next(handle, &mut el));
+        assert_eq!(el, 1);
+        assert_eq!(lazy_sort_u32_next_n(handle, out.as_mut_ptr(), 3), 3);
+        assert_eq!(out, [3, 5, 7]);
+        assert_eq!(lazy_sort_u32_remaining(handle), 1);
+        assert_eq!(lazy_sort_u32_next_n(handle, out.as_mut_ptr(), 3), 1);
+        assert!(!lazy_sort_u32_next(handle, &mut el));
+        lazy_sort_u32_free(handle);
+        lazy_sort_u32_free(ptr::null_mut());
+    }
+
+    let floats = [2.5f64, f64::NAN, -1.0, 0.0];
+    let mut out = [0.0; 4];
+    unsafe {
+        assert_eq!(lazy_sort_smallest_f64(floats.as_ptr(), floats.len(), 4, out.as_mut_ptr()), 4);
+    }
+    assert_eq!(out[..3], [-1.0, 0.0, 2.5]);
+    assert!(out[3].is_nan());
+}
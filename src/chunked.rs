@@ -0,0 +1,133 @@
+//! Sorting in fixed-size chunks.
+
+use std::collections::BinaryHeap;
+#[cfg(feature = "external")]
+use std::io;
+use std::iter::{Fuse, FusedIterator};
+use std::mem;
+use super::ReverseOrder;
+#[cfg(feature = "external")]
+use super::{ExternalSort, ExternalSortBuilder, SpillCodec};
+
+#[cfg(feature = "external")]
+impl ExternalSortBuilder {
+    /// Like [`sort_with_codec`](#method.sort_with_codec), but the input is consumed in chunks of
+    /// `chunk_size` elements rather than by the memory budget. Each chunk is sorted and spilled
+    /// as a run, except the last, which stays in memory, and the runs are merged lazily as the
+    /// output is read.
+    ///
+    /// With a [`fan_in`](#method.fan_in) set, no more than that many runs are merged at once, so
+    /// at most `chunk_size × fan_in` elements are ever held in memory, however long the input
+    /// is.
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn chunked_sort_with_codec<I, C>(mut self, iter: I, chunk_size: usize, codec: C)
+        -> io::Result<ExternalSort<I::Item, C>>
+        where I: IntoIterator,
+              I::Item: Ord,
+              C: SpillCodec<I::Item> + Clone
+    {
+        assert!(chunk_size > 0, "chunk_size must be positive");
+        // Every element weighs one, so the budget counts elements.
+        self.memory_budget = chunk_size;
+        self.sort_weighed(iter, codec, |_| 1)
+    }
+}
+
+/// An iterator that sorts each consecutive chunk of its input independently, created by
+/// [`sorted_within_chunks`](trait.LazySortIterator.html#method.sorted_within_chunks).
+///
+/// The elements of each chunk of at most `chunk_size` elements are yielded in sorted order before
+/// the next chunk is read, with no ordering between chunks. Each chunk is heapified in `O(n)` and
+/// yields each element in `O(log n)`, in a buffer that's reused for every chunk.
+#[derive(Debug, Clone)]
+pub struct SortedWithinChunks<I: Iterator> {
+    iter: Fuse<I>,
+    chunk_size: usize,
+    heap: BinaryHeap<ReverseOrder<I::Item>>,
+}
+
+impl<I> SortedWithinChunks<I>
+    where I: Iterator,
+          I::Item: Ord
+{
+    pub(crate) fn new(iter: I, chunk_size: usize) -> SortedWithinChunks<I> {
+        assert!(chunk_size > 0, "chunk_size must be positive");
+        SortedWithinChunks {
+            iter: iter.fuse(),
+            chunk_size,
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// Returns the number of elements of the current chunk that haven't been yielded yet.
+    pub fn remaining_in_chunk(&self) -> usize {
+        self.heap.len()
+    }
+}
+
+impl<I> Iterator for SortedWithinChunks<I>
+    where I: Iterator,
+          I::Item: Ord
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.heap.is_empty() {
+            let mut buffer = mem::take(&mut self.heap).into_vec();
+            buffer.extend(self.iter.by_ref().take(self.chunk_size).map(ReverseOrder));
+            self.heap = BinaryHeap::from(buffer);
+        }
+        self.heap.pop().map(|ReverseOrder(el)| el)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        let buffered = self.heap.len();
+        (lower.saturating_add(buffered), upper.and_then(|upper| upper.checked_add(buffered)))
+    }
+}
+
+impl<I> FusedIterator for SortedWithinChunks<I>
+    where I: Iterator,
+          I::Item: Ord
+{
+}
+
+#[test]
+fn sorted_within_chunks() {
+    use std::vec::Vec;
+    use super::LazySortIterator;
+
+    let v = [5, 1, 9, 3, 3, 7, 0, 8, 2, 6, 4];
+    let mut sorted = v.iter().cloned().sorted_within_chunks(4);
+    assert_eq!(sorted.next(), Some(1));
+    assert_eq!(sorted.remaining_in_chunk(), 3);
+    assert_eq!(sorted.size_hint(), (10, Some(10)));
+    assert_eq!(sorted.collect::<Vec<_>>(), [3, 5, 9, 0, 3, 7, 8, 2, 4, 6]);
+    assert!(v.iter().cloned().sorted_within_chunks(1).eq(v.iter().cloned()));
+}
+
+#[cfg(feature = "external")]
+#[test]
+fn chunked_sort() {
+    use std::vec::Vec;
+    use super::external::FixedWidth;
+
+    let v: Vec<u64> = vec![5, 1, 9, 3, 3, 7, 0, 8, 2, 6, 4];
+    let mut expected = v.clone();
+    expected.sort();
+    for chunk_size in 1..13 {
+        for &fan_in in &[2, 3, 16] {
+            let sorted = ExternalSortBuilder::new().fan_in(fan_in)
+                                                   .chunked_sort_with_codec(v.clone(),
+                                                                            chunk_size,
+                                                                            FixedWidth)
+                                                   .unwrap();
+            assert_eq!(sorted.size_hint().1, Some(v.len()));
+            assert_eq!(sorted.collect::<Vec<_>>(), expected);
+        }
+    }
+    let empty = ExternalSortBuilder::new().chunked_sort_with_codec(Vec::new(), 4, FixedWidth);
+    assert_eq!(empty.unwrap().next(), None);
+}
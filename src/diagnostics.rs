@@ -0,0 +1,103 @@
+//! Detecting the pivot choices that push quicksort towards quadratic time.
+
+/// Signs of pathological pivot choices in a quicksort so far, returned by its `diagnostics`
+/// method.
+///
+/// A well-behaved sort of `n` elements nests partitions about `log2(n)` deep and splits each
+/// roughly in half. Input that defeats the pivot strategy shows up as a `max_depth` far beyond
+/// that, near-zero `worst_split` ratios, and a growing count of `fallback_triggers`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    /// The depth of the most deeply nested partition created so far, where the partition holding
+    /// the whole input is at depth zero.
+    pub max_depth: usize,
+    /// The number of splits that left fewer than an eighth of a partition on one side of its
+    /// pivot.
+    pub unbalanced_splits: usize,
+    /// The most unbalanced split so far, as the length of the partition that was split and the
+    /// number of elements on the smaller side of its pivot.
+    pub worst_split: Option<(usize, usize)>,
+    /// The number of partitions created deeper than `2 log2(n)`, where an introsort would have
+    /// fallen back to heapsort. Lazy quicksort redoes the splits that nest most of a partition
+    /// around its median instead, so these are rare.
+    pub fallback_triggers: usize,
+}
+
+impl Diagnostics {
+    /// Returns the depth beyond which partitions of an input of `len` elements count as
+    /// fallback triggers.
+    pub(crate) fn depth_limit(len: usize) -> usize {
+        2 * len.checked_ilog2().unwrap_or(0) as usize
+    }
+
+    /// Records a split of a partition of `len` elements at `depth`, pivot included, that left
+    /// `greater` elements after the pivot and `less` before it.
+    pub(crate) fn record_split(&mut self,
+                               depth_limit: usize,
+                               depth: usize,
+                               len: usize,
+                               greater: usize,
+                               less: usize) {
+        let smaller = greater.min(less);
+        if smaller * 8 < len {
+            self.unbalanced_splits += 1;
+        }
+        let worse = self.worst_split.is_none_or(|(worst_len, worst_smaller)| {
+            smaller * worst_len < worst_smaller * len
+        });
+        if worse {
+            self.worst_split = Some((len, smaller));
+        }
+        if less > 0 {
+            self.max_depth = self.max_depth.max(depth + 1);
+            if depth + 1 > depth_limit {
+                self.fallback_triggers += 1;
+            }
+        }
+    }
+}
+
+#[test]
+fn diagnostics() {
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+    use super::{LazySortBuilder, LazySortIterator, Step};
+
+    let mut balanced = (0..10_000).quick_sort();
+    balanced.by_ref().for_each(drop);
+    let report = *balanced.diagnostics();
+    assert!(report.max_depth <= Diagnostics::depth_limit(10_000));
+    assert_eq!(report.fallback_triggers, 0);
+
+    // Elements equal to the pivot are set aside with it, leaving nothing to nest, even in
+    // observed sorts.
+    let mut sorter = LazySortBuilder::new().threshold(0)
+                                           .on_step(|_| {})
+                                           .sort(vec![7u8; 200])
+                                           .unwrap();
+    assert_eq!(sorter.next(), Some(7));
+    let report = *sorter.diagnostics().unwrap();
+    assert_eq!(report.max_depth, 0);
+    assert_eq!(report.unbalanced_splits, 1);
+    assert_eq!(report.worst_split, Some((200, 0)));
+
+    // A split around a pivot greater than everything else is redone around the median, which
+    // observed sorts report as they go.
+    let splits = Arc::new(Mutex::new(Vec::new()));
+    let mut v: Vec<u32> = (0..200).collect();
+    v[100] = 200;
+    let mut sorter = {
+        let splits = splits.clone();
+        LazySortBuilder::new().threshold(0)
+                              .perturb_pivots(false)
+                              .on_step(move |step| if let Step::Partition { len, less, .. } = step {
+                                  splits.lock().unwrap().push((len, less));
+                              })
+                              .sort(v)
+                              .unwrap()
+    };
+    assert_eq!(sorter.next(), Some(0));
+    assert_eq!(splits.lock().unwrap()[0], (200, 99));
+    let report = *sorter.diagnostics().unwrap();
+    assert!(report.max_depth <= Diagnostics::depth_limit(200));
+}
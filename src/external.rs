@@ -0,0 +1,976 @@
+//! Sorting inputs that don't fit in memory by spilling sorted runs to disk.
+
+#[cfg(feature = "bincode")]
+use bincode;
+#[cfg(feature = "lz4")]
+use lz4_flex;
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+#[cfg(feature = "bincode")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "bincode")]
+use serde::Serialize;
+use std::borrow::Borrow;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::iter::{FusedIterator, Peekable};
+use std::marker::PhantomData;
+use std::mem;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "threads")]
+use std::{iter, thread};
+use tempfile;
+#[cfg(feature = "zstd")]
+use zstd;
+use super::{kmerge, Config, KMerge, QuickSort};
+
+/// Converts elements to and from the bytes of a spilled run.
+///
+/// Each run is written by encoding its elements one after another, and read back by decoding the
+/// same number of elements, so an encoding needs no framing beyond what `decode` needs to find the
+/// end of one element.
+pub trait SpillCodec<T> {
+    /// Writes `el` to `writer`.
+    fn encode<W: Write>(&self, el: &T, writer: &mut W) -> io::Result<()>;
+
+    /// Reads the next element from `reader`.
+    fn decode<R: Read>(&self, reader: &mut R) -> io::Result<T>;
+}
+
+/// A codec that encodes serde-serializable elements with bincode.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl<T: Serialize + DeserializeOwned> SpillCodec<T> for BincodeCodec {
+    fn encode<W: Write>(&self, el: &T, writer: &mut W) -> io::Result<()> {
+        bincode::serialize_into(writer, el).map_err(|err| into_io_error(*err))
+    }
+
+    fn decode<R: Read>(&self, reader: &mut R) -> io::Result<T> {
+        bincode::deserialize_from(reader).map_err(|err| into_io_error(*err))
+    }
+}
+
+#[cfg(feature = "bincode")]
+fn into_io_error(err: bincode::ErrorKind) -> io::Error {
+    match err {
+        bincode::ErrorKind::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+/// Configures and constructs an external sort.
+///
+/// ```
+/// # #[cfg(feature = "bincode")]
+/// # fn main() {
+/// use lazy_sort::ExternalSortBuilder;
+///
+/// let sorted: Vec<u32> = ExternalSortBuilder::new()
+///     .memory_budget(64)
+///     .sort((0..100).rev())
+///     .unwrap()
+///     .collect();
+/// assert_eq!(sorted, (0..100).collect::<Vec<_>>());
+/// # }
+/// # #[cfg(not(feature = "bincode"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExternalSortBuilder {
+    pub(crate) memory_budget: usize,
+    temp_dir: Option<PathBuf>,
+    compression: Compression,
+    backend: SpillBackend,
+    checkpoint_dir: Option<PathBuf>,
+    fan_in: Option<usize>,
+    #[cfg(feature = "threads")]
+    threads: Option<usize>,
+}
+
+/// How an external sorter reads its spilled runs back when merging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpillBackend {
+    /// Read runs through a buffer.
+    Buffered,
+    /// Memory-map runs, leaving the caching to the OS page cache.
+    #[cfg(feature = "mmap")]
+    Mmap,
+}
+
+/// How an external sorter compresses the runs it spills to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Write runs uncompressed.
+    None,
+    /// Compress runs with LZ4, which is fast enough to rarely be the bottleneck.
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Compress runs with zstd at the given level, trading CPU for smaller spills.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+impl Default for ExternalSortBuilder {
+    fn default() -> ExternalSortBuilder {
+        ExternalSortBuilder {
+            memory_budget: 64 << 20,
+            temp_dir: None,
+            compression: Compression::None,
+            backend: SpillBackend::Buffered,
+            checkpoint_dir: None,
+            fan_in: None,
+            #[cfg(feature = "threads")]
+            threads: None,
+        }
+    }
+}
+
+impl ExternalSortBuilder {
+    /// Returns a builder with a 64 MiB memory budget that spills to the system temp directory.
+    pub fn new() -> ExternalSortBuilder {
+        ExternalSortBuilder::default()
+    }
+
+    /// Sets the number of bytes of elements to buffer before spilling a sorted run to disk.
+    pub fn memory_budget(mut self, bytes: usize) -> ExternalSortBuilder {
+        self.memory_budget = bytes;
+        self
+    }
+
+    /// Sets the directory to create spill files in.
+    pub fn temp_dir<P: Into<PathBuf>>(mut self, dir: P) -> ExternalSortBuilder {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets how spilled runs are compressed. Defaults to `Compression::None`.
+    pub fn compression(mut self, compression: Compression) -> ExternalSortBuilder {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets how spilled runs are read back. Defaults to `SpillBackend::Buffered`.
+    pub fn backend(mut self, backend: SpillBackend) -> ExternalSortBuilder {
+        self.backend = backend;
+        self
+    }
+
+    /// Makes the sort resumable by keeping its runs in `dir`, along with a manifest of how much of
+    /// the input they cover and, after each call to
+    /// [`ExternalSort::checkpoint`](struct.ExternalSort.html#method.checkpoint), how much of them
+    /// has been merged. Sorting again with the same `dir`, settings, codec, and input picks up
+    /// where the manifest left off. All of the input is spilled, nothing else may modify `dir`
+    /// while the sort is running, and `dir` is left for the caller to remove once it's done.
+    pub fn checkpoint_dir<P: Into<PathBuf>>(mut self, dir: P) -> ExternalSortBuilder {
+        self.checkpoint_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the most runs that are merged at once, which must be at least two. Each run being
+    /// merged holds a decoded element and a read buffer, so this bounds the memory the merge
+    /// takes however long the input is. If more runs than that are spilled, they're merged into
+    /// longer runs on disk, `runs` at a time, before the sort returns. Checkpointed sorts merge
+    /// all of their runs at once. Defaults to no limit.
+    ///
+    /// Panics if `runs` is less than two.
+    pub fn fan_in(mut self, runs: usize) -> ExternalSortBuilder {
+        assert!(runs >= 2, "fan_in must be at least two");
+        self.fan_in = Some(runs);
+        self
+    }
+
+    /// Sets the number of threads [`par_sort_with_codec`](#method.par_sort_with_codec) merges
+    /// with. Defaults to the available parallelism.
+    #[cfg(feature = "threads")]
+    pub fn threads(mut self, threads: usize) -> ExternalSortBuilder {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Consumes `iter`, spilling a sorted run each time the memory budget fills, and returns an
+    /// iterator that lazily merges the runs. Nothing is written to disk if the input fits.
+    #[cfg(feature = "bincode")]
+    pub fn sort<I>(self, iter: I) -> io::Result<ExternalSort<I::Item, BincodeCodec>>
+        where I: IntoIterator,
+              I::Item: Ord + Serialize + DeserializeOwned
+    {
+        self.sort_with_codec(iter, BincodeCodec)
+    }
+
+    /// Like [`sort`](#method.sort), but encodes spilled runs with `codec`.
+    pub fn sort_with_codec<I, C>(self, iter: I, codec: C) -> io::Result<ExternalSort<I::Item, C>>
+        where I: IntoIterator,
+              I::Item: Ord,
+              C: SpillCodec<I::Item> + Clone
+    {
+        self.sort_weighed(iter, codec, |_| mem::size_of::<I::Item>())
+    }
+
+    /// Like [`sort_with_codec`](#method.sort_with_codec), but merges the spilled runs on several
+    /// threads, for when merging on one can't keep up with the disk.
+    ///
+    /// The first run's elements are sampled for bounds that split the input into one key range
+    /// per thread, and every run is spilled as a file per range. Each thread then merges its
+    /// range's files into one, and the sorted output reads the merged ranges back in turn. Unlike
+    /// `sort_with_codec`, the whole merge is done before this returns, and the last run is
+    /// spilled too, unless all of the input fits in memory. Checkpointed sorts merge on one
+    /// thread.
+    #[cfg(feature = "threads")]
+    pub fn par_sort_with_codec<I, C>(self, iter: I, codec: C)
+        -> io::Result<ExternalSort<I::Item, C>>
+        where I: IntoIterator,
+              I::Item: Ord + Clone,
+              C: SpillCodec<I::Item> + Clone + Send
+    {
+        let threads = self.threads.unwrap_or_else(|| {
+            thread::available_parallelism().map_or(1, |threads| threads.get())
+        });
+        if threads <= 1 || self.checkpoint_dir.is_some() {
+            return self.sort_with_codec(iter, codec);
+        }
+        let weigh = |_: &I::Item| mem::size_of::<I::Item>();
+        let mut iter = iter.into_iter().fuse().peekable();
+        let mut chunk = self.next_chunk(&mut iter, &weigh);
+        if iter.peek().is_none() {
+            let sorter = QuickSort::with_config(chunk, Config::default());
+            return Ok(ExternalSort {
+                merge: kmerge(vec![Source::Memory(Box::new(sorter))]),
+                checkpoint_dir: None,
+            });
+        }
+        chunk.sort_unstable();
+        // A range ends at each bound, inclusive, and the last one after the largest.
+        let mut bounds: Vec<_> = (1..threads).map(|i| chunk[i * chunk.len() / threads].clone())
+                                             .collect();
+        bounds.dedup();
+        let mut ranges: Vec<Vec<Run<C>>> = (0..=bounds.len()).map(|_| Vec::new()).collect();
+        loop {
+            let mut start = 0;
+            let ends = bounds.iter().map(Some).chain(iter::once(None));
+            for (runs, bound) in ranges.iter_mut().zip(ends) {
+                let end = bound.map_or(chunk.len(), |bound| {
+                    start + chunk[start..].partition_point(|el| el <= bound)
+                });
+                if end > start {
+                    runs.push(self.spill(&chunk[start..end], codec.clone())?);
+                }
+                start = end;
+            }
+            if iter.peek().is_none() {
+                break;
+            }
+            chunk = self.next_chunk(&mut iter, &weigh);
+            chunk.sort_unstable();
+        }
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(target: "lazy_sort::external", ranges = ranges.len(),
+                          "merging runs in parallel");
+        let builder = &self;
+        let merged = thread::scope(|scope| {
+            let handles: Vec<_> = ranges.into_iter().map(|runs| {
+                let codec = codec.clone();
+                scope.spawn(move || builder.merge_range::<I::Item, C>(runs, codec))
+            }).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect::<io::Result<Vec<_>>>()
+        })?;
+        // The ranges are disjoint, so merging them just yields each in turn.
+        Ok(ExternalSort {
+            merge: kmerge(merged.into_iter().map(Source::Run)),
+            checkpoint_dir: None,
+        })
+    }
+
+    /// Merges the runs of one key range into a single run.
+    #[cfg(feature = "threads")]
+    fn merge_range<T, C>(&self, runs: Vec<Run<C>>, codec: C) -> io::Result<Run<C>>
+        where T: Ord,
+              C: SpillCodec<T> + Clone
+    {
+        let runs = self.merge_down::<T, C>(runs, &codec, self.fan_in.unwrap_or(usize::MAX))?;
+        self.merge_into_run::<T, C>(runs, codec)
+    }
+
+    /// Merges `runs` into longer runs, at most `fan_in` of them at a time, until no more than
+    /// `max` are left.
+    fn merge_down<T, C>(&self, mut runs: Vec<Run<C>>, codec: &C, max: usize)
+        -> io::Result<Vec<Run<C>>>
+        where T: Ord,
+              C: SpillCodec<T> + Clone
+    {
+        let fan_in = match self.fan_in {
+            Some(fan_in) => fan_in,
+            None => return Ok(runs),
+        };
+        while runs.len() > max {
+            #[cfg(feature = "tracing")]
+            ::tracing::debug!(target: "lazy_sort::external", runs = runs.len(), fan_in,
+                              "merging runs on disk");
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(fan_in));
+            let mut rest = runs.into_iter();
+            loop {
+                let group: Vec<_> = rest.by_ref().take(fan_in).collect();
+                match group.len() {
+                    0 => break,
+                    1 => merged.extend(group),
+                    _ => merged.push(self.merge_into_run::<T, C>(group, codec.clone())?),
+                }
+            }
+            runs = merged;
+        }
+        Ok(runs)
+    }
+
+    /// Merges `runs` into a single run.
+    fn merge_into_run<T, C>(&self, runs: Vec<Run<C>>, codec: C) -> io::Result<Run<C>>
+        where T: Ord,
+              C: SpillCodec<T>
+    {
+        let mut merge = kmerge(runs.into_iter().map(Source::Run::<T, C>));
+        let mut file = self.temp_file()?;
+        let len = self.write_run(&mut file, merge.by_ref(), &codec)?;
+        let error = merge.into_sources().into_iter().find_map(|source| match source {
+            Source::Run(run) => run.error,
+            Source::Memory(_) => None,
+        });
+        if let Some(err) = error {
+            return Err(err);
+        }
+        file.seek(SeekFrom::Start(0))?;
+        self.open_run(file, len, codec)
+    }
+
+    /// Sorts with the memory budget applied to the sizes that `weigh` reports for the elements.
+    pub(crate) fn sort_weighed<I, C, W>(self, iter: I, codec: C, weigh: W)
+        -> io::Result<ExternalSort<I::Item, C>>
+        where I: IntoIterator,
+              I::Item: Ord,
+              C: SpillCodec<I::Item> + Clone,
+              W: Fn(&I::Item) -> usize
+    {
+        let mut iter = iter.into_iter().fuse().peekable();
+        if let Some(ref dir) = self.checkpoint_dir {
+            return self.sort_checkpointed(iter, codec, weigh, dir);
+        }
+        let mut runs = Vec::new();
+        let last = loop {
+            let mut chunk = self.next_chunk(&mut iter, &weigh);
+            if iter.peek().is_none() {
+                // The last chunk stays in memory.
+                break chunk;
+            }
+            chunk.sort_unstable();
+            runs.push(self.spill(&chunk, codec.clone())?);
+        };
+        // Leave room in the merge for the last chunk.
+        let max = self.fan_in.map_or(usize::MAX, |fan_in| fan_in - 1);
+        let mut sources: Vec<_> = self.merge_down::<I::Item, C>(runs, &codec, max)?
+                                      .into_iter()
+                                      .map(Source::Run)
+                                      .collect();
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(target: "lazy_sort::external", runs = sources.len(),
+                          memory_len = last.len(), "merging runs");
+        let sorter = QuickSort::with_config(last, Config::default());
+        sources.push(Source::Memory(Box::new(sorter)));
+        Ok(ExternalSort {
+            merge: kmerge(sources),
+            checkpoint_dir: None,
+        })
+    }
+
+    /// Takes elements until the next one would exceed the memory budget, but at least one.
+    fn next_chunk<I, W>(&self, iter: &mut Peekable<I>, weigh: &W) -> Vec<I::Item>
+        where I: Iterator,
+              W: Fn(&I::Item) -> usize
+    {
+        let mut chunk = Vec::new();
+        let mut bytes = 0usize;
+        while let Some(size) = iter.peek().map(weigh) {
+            bytes = bytes.saturating_add(size);
+            if bytes > self.memory_budget && !chunk.is_empty() {
+                break;
+            }
+            chunk.extend(iter.next());
+        }
+        chunk
+    }
+
+    fn sort_checkpointed<I, T, C, W>(&self, mut iter: Peekable<I>, codec: C, weigh: W, dir: &Path)
+        -> io::Result<ExternalSort<T, C>>
+        where I: Iterator<Item = T>,
+              T: Ord,
+              C: SpillCodec<T> + Clone,
+              W: Fn(&T) -> usize
+    {
+        fs::create_dir_all(dir)?;
+        let mut manifest = Manifest::read(dir)?.unwrap_or_default();
+        if manifest.consumed > 0 {
+            iter.nth(manifest.consumed - 1);
+        }
+        while !manifest.complete {
+            let mut chunk = self.next_chunk(&mut iter, &weigh);
+            manifest.consumed += chunk.len();
+            manifest.complete = iter.peek().is_none();
+            if !chunk.is_empty() {
+                chunk.sort_unstable();
+                let mut file = File::create(run_path(dir, manifest.runs.len()))?;
+                self.write_run(&mut file, &chunk, &codec)?;
+                file.sync_all()?;
+                manifest.runs.push((chunk.len(), 0));
+            }
+            manifest.write(dir)?;
+        }
+        let mut sources = Vec::with_capacity(manifest.runs.len());
+        for (i, &(len, merged)) in manifest.runs.iter().enumerate() {
+            let mut run = self.open_run(File::open(run_path(dir, i))?, len, codec.clone())?;
+            for _ in 0..merged {
+                run.next::<T>();
+            }
+            if let Some(err) = run.take_error() {
+                return Err(err);
+            }
+            sources.push(Source::Run(run));
+        }
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(target: "lazy_sort::external", runs = sources.len(), memory_len = 0,
+                          "merging runs");
+        Ok(ExternalSort {
+            merge: kmerge(sources),
+            checkpoint_dir: Some(dir.to_owned()),
+        })
+    }
+
+    pub(crate) fn spill<T, C: SpillCodec<T>>(&self, run: &[T], codec: C) -> io::Result<Run<C>> {
+        let mut file = self.temp_file()?;
+        self.write_run(&mut file, run, &codec)?;
+        file.seek(SeekFrom::Start(0))?;
+        self.open_run(file, run.len(), codec)
+    }
+
+    fn temp_file(&self) -> io::Result<File> {
+        match self.temp_dir {
+            Some(ref dir) => tempfile::tempfile_in(dir),
+            None => tempfile::tempfile(),
+        }
+    }
+
+    /// Writes the elements of `run` to `file`, returning how many there were.
+    fn write_run<T, I, C>(&self, file: &mut File, run: I, codec: &C) -> io::Result<usize>
+        where I: IntoIterator,
+              I::Item: Borrow<T>,
+              C: SpillCodec<T>
+    {
+        let mut writer = BufWriter::new(file);
+        let len = match self.compression {
+            Compression::None => write_elements(&mut writer, run, codec)?,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(&mut writer);
+                let len = write_elements(&mut encoder, run, codec)?;
+                encoder.finish().map_err(io::Error::from)?;
+                len
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => {
+                let mut encoder = zstd::Encoder::new(&mut writer, level)?;
+                let len = write_elements(&mut encoder, run, codec)?;
+                encoder.finish()?;
+                len
+            }
+        };
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(target: "lazy_sort::external", len, "spilled run");
+        writer.flush()?;
+        Ok(len)
+    }
+
+    fn open_run<C>(&self, file: File, len: usize, codec: C) -> io::Result<Run<C>> {
+        let reader: Box<dyn Read + Send> = match self.backend {
+            SpillBackend::Buffered => Box::new(BufReader::new(file)),
+            // Safe as long as nothing else modifies the run while it's mapped, which holds for
+            // anonymous temporaries and is documented for checkpoint directories.
+            #[cfg(feature = "mmap")]
+            SpillBackend::Mmap => Box::new(io::Cursor::new(unsafe { Mmap::map(&file)? })),
+        };
+        let reader: Box<dyn Read + Send> = match self.compression {
+            Compression::None => reader,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Box::new(lz4_flex::frame::FrameDecoder::new(reader)),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(_) => Box::new(zstd::Decoder::new(reader)?),
+        };
+        Ok(Run {
+            reader,
+            codec,
+            len,
+            remaining: len,
+            error: None,
+        })
+    }
+}
+
+fn write_elements<W, T, I, C>(writer: &mut W, run: I, codec: &C) -> io::Result<usize>
+    where W: Write,
+          I: IntoIterator,
+          I::Item: Borrow<T>,
+          C: SpillCodec<T>
+{
+    let mut len = 0;
+    for el in run {
+        codec.encode(el.borrow(), writer)?;
+        len += 1;
+    }
+    Ok(len)
+}
+
+fn run_path(dir: &Path, i: usize) -> PathBuf {
+    dir.join(format!("run-{}", i))
+}
+
+/// The progress of a checkpointed sort, kept in the checkpoint directory.
+#[derive(Debug, Default)]
+struct Manifest {
+    /// The number of input elements that have been spilled.
+    consumed: usize,
+    /// Whether the input has been exhausted.
+    complete: bool,
+    /// The length of each run, and the number of its elements that have been merged.
+    runs: Vec<(usize, usize)>,
+}
+
+impl Manifest {
+    fn read(dir: &Path) -> io::Result<Option<Manifest>> {
+        let text = match fs::read_to_string(dir.join("manifest")) {
+            Ok(text) => text,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let mut manifest = Manifest::default();
+        for line in text.lines() {
+            match *line.split_whitespace().collect::<Vec<_>>() {
+                ["consumed", consumed] => manifest.consumed = parse_count(consumed)?,
+                ["complete"] => manifest.complete = true,
+                ["run", len, merged] => {
+                    manifest.runs.push((parse_count(len)?, parse_count(merged)?))
+                }
+                _ => return Err(invalid_manifest()),
+            }
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Replaces the manifest in `dir`, atomically so that a crash leaves either version intact.
+    fn write(&self, dir: &Path) -> io::Result<()> {
+        let mut text = format!("consumed {}\n", self.consumed);
+        if self.complete {
+            text.push_str("complete\n");
+        }
+        for &(len, merged) in &self.runs {
+            text.push_str(&format!("run {} {}\n", len, merged));
+        }
+        let tmp = dir.join("manifest.tmp");
+        {
+            let mut file = File::create(&tmp)?;
+            file.write_all(text.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(tmp, dir.join("manifest"))
+    }
+}
+
+fn parse_count(s: &str) -> io::Result<usize> {
+    s.parse().map_err(|_| invalid_manifest())
+}
+
+fn invalid_manifest() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed external sort manifest")
+}
+
+/// A sorted iterator over elements that may have been spilled to disk by an
+/// [`ExternalSortBuilder`](struct.ExternalSortBuilder.html).
+///
+/// If reading a spilled run fails, iteration stops early and the error is available from
+/// [`error`](#method.error).
+#[derive(Debug)]
+pub struct ExternalSort<T: Ord, C: SpillCodec<T>> {
+    merge: KMerge<Source<T, C>>,
+    checkpoint_dir: Option<PathBuf>,
+}
+
+impl<T: Ord, C: SpillCodec<T>> ExternalSort<T, C> {
+    /// Records how much of each run has been merged in the checkpoint directory's manifest, so
+    /// that a resumed sort continues from here. Does nothing if no checkpoint directory was set.
+    pub fn checkpoint(&self) -> io::Result<()> {
+        let dir = match self.checkpoint_dir {
+            Some(ref dir) => dir,
+            None => return Ok(()),
+        };
+        let heads = self.merge.heads();
+        let runs: Vec<_> = self.merge.sources().iter().enumerate().map(|(i, source)| {
+            match *source {
+                Source::Run(ref run) => {
+                    let buffered = heads.get(i).is_some_and(Option::is_some) as usize;
+                    (run.len, run.len - run.remaining - buffered)
+                }
+                Source::Memory(_) => unreachable!("checkpointed sorts spill all of their input"),
+            }
+        }).collect();
+        Manifest {
+            consumed: runs.iter().map(|&(len, _)| len).sum(),
+            complete: true,
+            runs,
+        }.write(dir)
+    }
+
+    /// Returns the error that ended iteration early, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.merge.sources().iter().filter_map(|source| match *source {
+            Source::Run(ref run) => run.error.as_ref(),
+            Source::Memory(_) => None,
+        }).next()
+    }
+}
+
+impl<T: Ord, C: SpillCodec<T>> Iterator for ExternalSort<T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.error().is_some() {
+            return None;
+        }
+        let next = self.merge.next();
+        if self.error().is_some() {
+            return None;
+        }
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.merge.size_hint()
+    }
+}
+
+impl<T: Ord, C: SpillCodec<T>> FusedIterator for ExternalSort<T, C> { }
+
+/// Either the in-memory remainder of the input or a run spilled to disk.
+#[derive(Debug)]
+enum Source<T, C> {
+    Memory(Box<QuickSort<T>>),
+    Run(Run<C>),
+}
+
+impl<T: Ord, C: SpillCodec<T>> Iterator for Source<T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match *self {
+            Source::Memory(ref mut sorter) => sorter.next(),
+            Source::Run(ref mut run) => run.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match *self {
+            Source::Memory(ref sorter) => sorter.size_hint(),
+            Source::Run(ref run) => (0, Some(run.remaining)),
+        }
+    }
+}
+
+/// A sorted run read back from an anonymous temporary file, which is deleted when it's dropped.
+pub(crate) struct Run<C> {
+    reader: Box<dyn Read + Send>,
+    codec: C,
+    len: usize,
+    remaining: usize,
+    error: Option<io::Error>,
+}
+
+impl<C: fmt::Debug> fmt::Debug for Run<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Run")
+         .field("codec", &self.codec)
+         .field("len", &self.len)
+         .field("remaining", &self.remaining)
+         .field("error", &self.error)
+         .finish()
+    }
+}
+
+impl<C> Run<C> {
+    /// Returns the number of elements left to read.
+    pub(crate) fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Returns the error that stopped reading early, if any.
+    pub(crate) fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    pub(crate) fn next<T>(&mut self) -> Option<T>
+        where C: SpillCodec<T>
+    {
+        if self.remaining == 0 || self.error.is_some() {
+            return None;
+        }
+        match self.codec.decode(&mut self.reader) {
+            Ok(el) => {
+                self.remaining -= 1;
+                Some(el)
+            }
+            Err(err) => {
+                self.error = Some(err);
+                None
+            }
+        }
+    }
+}
+
+/// Lazily merges runs that were sorted and encoded with `codec` elsewhere, reading each from one
+/// of `readers` until it runs out.
+///
+/// This is the merge phase of an [`ExternalSort`](struct.ExternalSort.html) on its own. Each run
+/// must end exactly where an element does; wrap the readers in decoders first if the runs are
+/// compressed. If reading or decoding a run fails, iteration stops early and the error is
+/// available from [`error`](struct.MergeRuns.html#method.error).
+pub fn merge_runs<I, T, C>(readers: I, codec: C) -> MergeRuns<I::Item, T, C>
+    where I: IntoIterator,
+          I::Item: Read,
+          T: Ord,
+          C: SpillCodec<T> + Clone
+{
+    let runs = readers.into_iter().map(|reader| {
+        ReaderRun {
+            reader: BufReader::new(reader),
+            codec: codec.clone(),
+            error: None,
+            _marker: PhantomData,
+        }
+    });
+    MergeRuns(kmerge(runs))
+}
+
+/// A sorted iterator over the elements of pre-sorted runs, created by
+/// [`merge_runs`](fn.merge_runs.html).
+#[derive(Debug)]
+pub struct MergeRuns<R: Read, T: Ord, C: SpillCodec<T>>(KMerge<ReaderRun<R, T, C>>);
+
+impl<R: Read, T: Ord, C: SpillCodec<T>> MergeRuns<R, T, C> {
+    /// Returns the error that ended iteration early, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.0.sources().iter().filter_map(|run| run.error.as_ref()).next()
+    }
+}
+
+impl<R: Read, T: Ord, C: SpillCodec<T>> Iterator for MergeRuns<R, T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.error().is_some() {
+            return None;
+        }
+        let next = self.0.next();
+        if self.error().is_some() {
+            return None;
+        }
+        next
+    }
+}
+
+impl<R: Read, T: Ord, C: SpillCodec<T>> FusedIterator for MergeRuns<R, T, C> { }
+
+/// A sorted run of unknown length, read until its reader runs out.
+struct ReaderRun<R, T, C> {
+    reader: BufReader<R>,
+    codec: C,
+    error: Option<io::Error>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T, C: fmt::Debug> fmt::Debug for ReaderRun<R, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReaderRun")
+         .field("codec", &self.codec)
+         .field("error", &self.error)
+         .finish()
+    }
+}
+
+impl<R: Read, T, C: SpillCodec<T>> Iterator for ReaderRun<R, T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.error.is_some() {
+            return None;
+        }
+        // The run ends cleanly only between elements.
+        let decoded = match self.reader.fill_buf() {
+            Ok(&[]) => return None,
+            Ok(_) => self.codec.decode(&mut self.reader),
+            Err(err) => Err(err),
+        };
+        decoded.map_err(|err| self.error = Some(err)).ok()
+    }
+}
+
+/// Encodes `u64`s as eight little-endian bytes.
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub(crate) struct FixedWidth;
+
+#[cfg(test)]
+impl SpillCodec<u64> for FixedWidth {
+    fn encode<W: Write>(&self, el: &u64, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&el.to_le_bytes())
+    }
+
+    fn decode<R: Read>(&self, reader: &mut R) -> io::Result<u64> {
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+#[test]
+fn external_sort() {
+    use super::rng::XorShift;
+
+    let mut rng = XorShift::new(1);
+    let v: Vec<u64> = (0..1000).map(|_| rng.next_u64() % 100).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    for &budget in &[1, 8, 800, 1 << 20] {
+        let sorted = ExternalSortBuilder::new().memory_budget(budget)
+                                               .sort_with_codec(v.clone(), FixedWidth)
+                                               .unwrap();
+        assert_eq!(sorted.size_hint().1, Some(v.len()));
+        assert_eq!(sorted.collect::<Vec<_>>(), expected);
+    }
+    let compressions = [
+        Compression::None,
+        #[cfg(feature = "lz4")]
+        Compression::Lz4,
+        #[cfg(feature = "zstd")]
+        Compression::Zstd(3),
+    ];
+    let backends = [
+        SpillBackend::Buffered,
+        #[cfg(feature = "mmap")]
+        SpillBackend::Mmap,
+    ];
+    for &compression in &compressions {
+        for &backend in &backends {
+            let sorted = ExternalSortBuilder::new().memory_budget(64)
+                                                   .compression(compression)
+                                                   .backend(backend)
+                                                   .sort_with_codec(v.clone(), FixedWidth)
+                                                   .unwrap();
+            assert_eq!(sorted.collect::<Vec<_>>(), expected);
+        }
+    }
+    let mut empty = ExternalSortBuilder::new().sort_with_codec(vec![], FixedWidth).unwrap();
+    assert_eq!(empty.next(), None);
+    assert!(empty.error().is_none());
+}
+
+#[cfg(feature = "threads")]
+#[test]
+fn par_external_sort() {
+    use super::rng::XorShift;
+
+    let mut rng = XorShift::new(2);
+    let v: Vec<u64> = (0..5000).map(|_| rng.next_u64() % 700).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    for &(budget, threads) in &[(8, 4), (800, 3), (800, 1), (1 << 20, 4)] {
+        let sorted = ExternalSortBuilder::new().memory_budget(budget)
+                                               .threads(threads)
+                                               .par_sort_with_codec(v.clone(), FixedWidth)
+                                               .unwrap();
+        assert_eq!(sorted.size_hint().1, Some(v.len()));
+        assert_eq!(sorted.collect::<Vec<_>>(), expected);
+    }
+    // Every bound sampled from equal elements is the same, leaving a single range.
+    let sorted = ExternalSortBuilder::new().memory_budget(80)
+                                           .threads(4)
+                                           .par_sort_with_codec(vec![7; 100], FixedWidth)
+                                           .unwrap();
+    assert_eq!(sorted.collect::<Vec<_>>(), [7; 100]);
+}
+
+#[test]
+fn external_sort_fan_in() {
+    use super::rng::XorShift;
+
+    let mut rng = XorShift::new(3);
+    let v: Vec<u64> = (0..1000).map(|_| rng.next_u64() % 100).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    for &fan_in in &[2, 3, 10, 200] {
+        // A run of eight elements per spill makes 125 runs to merge down.
+        let sorted = ExternalSortBuilder::new().memory_budget(64)
+                                               .fan_in(fan_in)
+                                               .sort_with_codec(v.clone(), FixedWidth)
+                                               .unwrap();
+        assert!(sorted.merge.sources().len() <= fan_in);
+        assert_eq!(sorted.size_hint().1, Some(v.len()));
+        assert_eq!(sorted.collect::<Vec<_>>(), expected);
+        #[cfg(feature = "threads")]
+        {
+            let sorted = ExternalSortBuilder::new().memory_budget(64)
+                                                   .fan_in(fan_in)
+                                                   .threads(3)
+                                                   .par_sort_with_codec(v.clone(), FixedWidth)
+                                                   .unwrap();
+            assert_eq!(sorted.collect::<Vec<_>>(), expected);
+        }
+    }
+}
+
+#[test]
+fn external_sort_checkpoint() {
+    let dir = tempfile::tempdir().unwrap();
+    let v: Vec<u64> = (0..500).map(|i| i * 7919 % 500).collect();
+    let builder = ExternalSortBuilder::new().memory_budget(128).checkpoint_dir(dir.path());
+    let mut sorted = builder.clone().sort_with_codec(v.clone(), FixedWidth).unwrap();
+    assert_eq!(sorted.by_ref().take(200).collect::<Vec<_>>(), (0..200).collect::<Vec<_>>());
+    sorted.checkpoint().unwrap();
+    assert_eq!(sorted.nth(100), Some(300));
+    drop(sorted);
+
+    // Resuming neither re-reads the input nor repeats anything merged before the checkpoint.
+    let resumed = builder.sort_with_codec(vec![], FixedWidth).unwrap();
+    assert_eq!(resumed.collect::<Vec<_>>(), (200..500).collect::<Vec<_>>());
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn external_sort_bincode() {
+    let words = ["pear", "fig", "apple", "kiwi", "date", "plum", "lime"];
+    let sorted: Vec<String> = ExternalSortBuilder::new()
+        .memory_budget(2 * mem::size_of::<String>())
+        .sort(words.iter().map(|word| word.to_string()))
+        .unwrap()
+        .collect();
+    assert_eq!(sorted, ["apple", "date", "fig", "kiwi", "lime", "pear", "plum"]);
+}
+
+#[test]
+fn merge_sorted_runs() {
+    let mut runs = vec![Vec::new(); 3];
+    for i in 0..300u64 {
+        FixedWidth.encode(&i, &mut runs[(i * 7 % 3) as usize]).unwrap();
+    }
+    let merged: Vec<u64> = merge_runs(runs.iter().map(|run| &run[..]), FixedWidth).collect();
+    assert_eq!(merged, (0..300).collect::<Vec<_>>());
+
+    let truncated = [&runs[0][..], &runs[1][..runs[1].len() - 1]];
+    let mut merged = merge_runs(truncated.iter().cloned(), FixedWidth);
+    assert!(merged.by_ref().count() < 200);
+    assert_eq!(merged.error().map(io::Error::kind), Some(io::ErrorKind::UnexpectedEof));
+}
@@ -0,0 +1,239 @@
+//! Lazy sorting without heap allocation, for inputs of bounded size.
+
+use std::array;
+use std::error::Error;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::mem::MaybeUninit;
+use std::slice;
+use super::heap;
+use super::ReverseOrder;
+
+/// The error returned when a fixed-capacity sorter is full. It holds the element that didn't fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError<T>(pub T);
+
+impl<T> fmt::Display for CapacityError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the fixed-capacity sorter is full")
+    }
+}
+
+impl<T: fmt::Debug> Error for CapacityError<T> {}
+
+/// A lazy heapsort that stores up to `N` elements inline, never allocating.
+///
+/// Like [`HeapSort`](struct.HeapSort.html), the heap is built up front in `O(n)` and each element
+/// taken costs `O(log n)`, but the heap is an array held by the sorter itself, so it can live on
+/// the stack or in a static.
+pub struct FixedHeapSort<T, const N: usize> {
+    /// A min-heap in its first `len` slots, which are initialized.
+    heap: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl<T: Ord, const N: usize> Default for FixedHeapSort<T, N> {
+    fn default() -> FixedHeapSort<T, N> {
+        FixedHeapSort::new()
+    }
+}
+
+impl<T: Ord, const N: usize> FixedHeapSort<T, N> {
+    /// Returns an empty sorter.
+    pub fn new() -> FixedHeapSort<T, N> {
+        FixedHeapSort {
+            heap: array::from_fn(|_| MaybeUninit::uninit()),
+            len: 0,
+        }
+    }
+
+    /// Collects `iter` into a sorter, failing with the first element that doesn't fit.
+    pub fn try_from_iter<I>(iter: I) -> Result<FixedHeapSort<T, N>, CapacityError<T>>
+        where I: IntoIterator<Item = T>
+    {
+        let mut sorter = FixedHeapSort::new();
+        for el in iter {
+            if sorter.len == N {
+                return Err(CapacityError(el));
+            }
+            sorter.heap[sorter.len] = MaybeUninit::new(el);
+            sorter.len += 1;
+        }
+        for i in (0..sorter.len / 2).rev() {
+            sorter.sift_down(i);
+        }
+        Ok(sorter)
+    }
+
+    /// Adds an element, or returns it in an error if the sorter already holds `N` elements.
+    pub fn try_push(&mut self, el: T) -> Result<(), CapacityError<T>> {
+        if self.len == N {
+            return Err(CapacityError(el));
+        }
+        self.heap[self.len] = MaybeUninit::new(el);
+        self.len += 1;
+        self.sift_up(self.len - 1);
+        Ok(())
+    }
+
+    /// Returns the smallest element without removing it.
+    pub fn peek(&self) -> Option<&T> {
+        self.elements().first()
+    }
+
+    /// Returns the number of elements in the sorter.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the sorter is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the most elements the sorter can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Replaces the smallest element with `el`, which the sorter must not be empty for.
+    fn replace_min(&mut self, el: T) {
+        // Safety: the first slot is initialized, since the sorter isn't empty.
+        *unsafe { self.heap[0].assume_init_mut() } = el;
+        self.sift_down(0);
+    }
+
+    fn sift_up(&mut self, mut pos: usize) {
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if self.elements()[parent] <= self.elements()[pos] {
+                return;
+            }
+            self.heap.swap(parent, pos);
+            pos = parent;
+        }
+    }
+
+    fn sift_down(&mut self, pos: usize) {
+        heap::sift_down_by(self.elements_mut(), pos, |a, b| a < b);
+    }
+}
+
+impl<T, const N: usize> FixedHeapSort<T, N> {
+    /// Returns the initialized elements, in heap order.
+    fn elements(&self) -> &[T] {
+        // Safety: the first `len` slots are initialized.
+        unsafe { slice::from_raw_parts(self.heap.as_ptr() as *const T, self.len) }
+    }
+
+    /// Returns the initialized elements mutably, in heap order.
+    fn elements_mut(&mut self) -> &mut [T] {
+        // Safety: the first `len` slots are initialized.
+        unsafe { slice::from_raw_parts_mut(self.heap.as_mut_ptr() as *mut T, self.len) }
+    }
+}
+
+/// Returns the `N` smallest elements of `iter` in ascending order, followed by `None`s if there
+/// are fewer than `N`.
+pub(crate) fn smallest_array<T, I, const N: usize>(iter: I) -> [Option<T>; N]
+    where T: Ord,
+          I: Iterator<Item = T>
+{
+    // A max-heap of the smallest elements seen so far.
+    let mut largest = FixedHeapSort::<ReverseOrder<T>, N>::new();
+    for el in iter {
+        match largest.try_push(ReverseOrder(el)) {
+            Ok(()) => {}
+            Err(CapacityError(el)) => if largest.peek().is_some_and(|max| el > *max) {
+                largest.replace_min(el);
+            },
+        }
+    }
+    let mut smallest = array::from_fn(|_| None);
+    for i in (0..largest.len()).rev() {
+        smallest[i] = largest.next().map(|ReverseOrder(el)| el);
+    }
+    smallest
+}
+
+impl<T, const N: usize> Drop for FixedHeapSort<T, N> {
+    fn drop(&mut self) {
+        for el in &mut self.heap[..self.len] {
+            // Safety: the first `len` slots are initialized, and are dropped only here.
+            unsafe { el.assume_init_drop() };
+        }
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for FixedHeapSort<T, N> {
+    fn clone(&self) -> FixedHeapSort<T, N> {
+        let mut heap: [MaybeUninit<T>; N] = array::from_fn(|_| MaybeUninit::uninit());
+        for (slot, el) in heap.iter_mut().zip(self.elements()) {
+            *slot = MaybeUninit::new(el.clone());
+        }
+        FixedHeapSort {
+            heap,
+            len: self.len,
+        }
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for FixedHeapSort<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FixedHeapSort").field("heap", &self.elements()).finish()
+    }
+}
+
+impl<T: Ord, const N: usize> Iterator for FixedHeapSort<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        self.heap.swap(0, self.len);
+        // Safety: slot `len` was initialized, and is now outside the initialized prefix.
+        let min = unsafe { self.heap[self.len].assume_init_read() };
+        self.sift_down(0);
+        Some(min)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T: Ord, const N: usize> FusedIterator for FixedHeapSort<T, N> {}
+
+#[test]
+fn fixed_heap_sort() {
+    use super::LazySortIterator;
+
+    let v = [2, 4, 2, 5, 8, 4, 3, 4, 6];
+    let mut sorted = v.iter().cloned().fixed_heap_sort::<9>().unwrap();
+    assert_eq!(sorted.peek(), Some(&2));
+    assert_eq!(sorted.try_push(1), Err(CapacityError(1)));
+    assert_eq!(sorted.next(), Some(2));
+    sorted.try_push(1).unwrap();
+    assert_eq!(sorted.clone().collect::<Vec<_>>(), [1, 2, 3, 4, 4, 4, 5, 6, 8]);
+    assert_eq!(sorted.len(), 9);
+    assert_eq!(v.iter().cloned().fixed_heap_sort::<8>().unwrap_err(), CapacityError(6));
+
+    let mut strings = FixedHeapSort::<String, 4>::new();
+    for s in &["pear", "fig", "apple"] {
+        strings.try_push(s.to_string()).unwrap();
+    }
+    assert_eq!(strings.next().as_deref(), Some("apple"));
+    drop(strings);
+}
+
+#[test]
+fn fixed_smallest_array() {
+    use super::LazySortIterator;
+
+    let v = [2, 4, 2, 5, 8, 4, 3, 4, 6];
+    assert_eq!(v.iter().cloned().smallest_array::<4>(), [2, 2, 3, 4]);
+    assert_eq!(v.iter().cloned().smallest_array::<0>(), []);
+    assert_eq!(v[..2].iter().cloned().smallest_array_padded::<3>(), [Some(2), Some(4), None]);
+}
@@ -0,0 +1,122 @@
+//! Sorting a little at a time, e.g. once per frame of a game loop.
+
+use std::mem;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+use super::{Config, LazySorted, QuickSort};
+
+/// How many comparisons are made between checks of the clock.
+const COMPARISONS_PER_CHECK: usize = 256;
+
+/// A quicksort that's advanced in time slices, keeping its progress between them.
+///
+/// Each call to [`advance`](#method.advance) partitions and emits elements until the slice is
+/// used up, appending them to [`sorted`](#method.sorted). Calling [`reset`](#method.reset) starts
+/// over with new input, reusing the buffers of the previous sort, so sorting every frame doesn't
+/// allocate once the buffers have grown to fit.
+#[derive(Debug, Clone)]
+pub struct FrameSort<T> {
+    sorter: QuickSort<T>,
+    sorted: Vec<T>,
+}
+
+impl<T: Ord> Default for FrameSort<T> {
+    fn default() -> FrameSort<T> {
+        FrameSort::new()
+    }
+}
+
+impl<T: Ord> FrameSort<T> {
+    /// Returns a sorter with nothing to sort.
+    pub fn new() -> FrameSort<T> {
+        FrameSort {
+            sorter: QuickSort::with_config(Vec::new(), Config::default()),
+            sorted: Vec::new(),
+        }
+    }
+
+    /// Discards the current sort, whether or not it's finished, and starts sorting `iter`.
+    pub fn reset<I>(&mut self, iter: I)
+        where I: IntoIterator<Item = T>
+    {
+        let sorter = mem::replace(&mut self.sorter,
+                                  QuickSort::with_config(Vec::new(), Config::default()));
+        let config = sorter.config.clone();
+        let mut buf = sorter.into_inner();
+        buf.clear();
+        buf.extend(iter);
+        self.sorted.clear();
+        self.sorter = QuickSort::with_config(buf, config);
+    }
+
+    /// Sorts for roughly `slice`, returning true once every element has been emitted.
+    ///
+    /// The clock is checked every few hundred comparisons, so a call can overrun `slice` by
+    /// about that much work.
+    pub fn advance(&mut self, slice: Duration) -> bool {
+        let start = Instant::now();
+        self.advance_while(|| start.elapsed() < slice)
+    }
+
+    /// Sorts until `more` returns false, returning true once every element has been emitted.
+    ///
+    /// `more` is called every few hundred comparisons. This lets the time slice be measured with
+    /// a clock other than `Instant`, which panics on `wasm32-unknown-unknown`; in a browser,
+    /// `more` can compare `performance.now()` against a deadline.
+    pub fn advance_while<F>(&mut self, mut more: F) -> bool
+        where F: FnMut() -> bool
+    {
+        loop {
+            match self.sorter.try_next(COMPARISONS_PER_CHECK) {
+                ControlFlow::Break(Some(el)) => self.sorted.push(el),
+                ControlFlow::Break(None) => return true,
+                ControlFlow::Continue(()) => {}
+            }
+            if !more() {
+                return self.is_done();
+            }
+        }
+    }
+
+    /// Returns the smallest elements, in order, that have been emitted so far.
+    pub fn sorted(&self) -> &[T] {
+        &self.sorted
+    }
+
+    /// Returns the number of elements not yet emitted.
+    pub fn remaining(&self) -> usize {
+        self.sorter.len()
+    }
+
+    /// Returns true if every element has been emitted.
+    pub fn is_done(&self) -> bool {
+        self.sorter.len() == 0
+    }
+}
+
+#[test]
+fn frame_sort() {
+    let mut sorter = FrameSort::new();
+    assert!(sorter.advance(Duration::from_micros(10)));
+    for round in 0..2u32 {
+        sorter.reset((0..20_000u32).map(|i| (i * 7919 + round) % 20_000));
+        let mut frames = 0;
+        while !sorter.advance(Duration::from_micros(50)) {
+            assert!(sorter.sorted().windows(2).all(|w| w[0] <= w[1]));
+            frames += 1;
+            assert!(frames < 1_000_000);
+        }
+        assert_eq!(sorter.remaining(), 0);
+        assert_eq!(sorter.sorted(), &(0..20_000).collect::<Vec<_>>()[..]);
+    }
+
+    sorter.reset((0..1000).rev());
+    let mut checks = 0;
+    assert!(!sorter.advance_while(|| {
+        checks += 1;
+        checks < 2
+    }));
+    assert_eq!(checks, 2);
+    assert!(sorter.advance_while(|| true));
+    assert_eq!(sorter.sorted(), &(0..1000).collect::<Vec<_>>()[..]);
+}
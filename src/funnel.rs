@@ -0,0 +1,243 @@
+//! Lazy funnelsort, a cache-oblivious merge sort.
+//!
+//! The input is split into about `n^(1/3)` segments, each sorted the same way, and merged by a
+//! `k`-funnel: the segments are merged in groups of about `sqrt(k)`, each group into a buffer of
+//! `k^(3/2)` elements, and the group buffers are merged in turn. Each level of the funnel works
+//! on a range of memory that fits in some level of cache, whatever the cache sizes are, whereas
+//! quicksort's partitions stream through the whole input at the top levels.
+
+use std::boxed::Box;
+use std::collections::VecDeque;
+use std::mem;
+use std::vec::{self, Vec};
+#[cfg(feature = "instrument")]
+use super::Stats;
+use super::{split_at_rank, LazySorted};
+
+/// Segments of at most this many elements are sorted outright.
+const BASE_LEN: usize = 256;
+
+/// A sorter that lazily sorts its input with funnelsort, selected with
+/// `Algorithm::Funnel`.
+///
+/// The segments at the bottom of the funnel are sorted when it's built, so the first element
+/// costs `O(n log BASE_LEN)` comparisons, and each one after it `O(log n)`.
+#[derive(Debug, Clone)]
+pub(crate) struct FunnelSort<T> {
+    merge: Input<T>,
+    /// The next element, if it has been peeked at.
+    peeked: Option<T>,
+    #[cfg(feature = "instrument")]
+    stats: Stats,
+}
+
+impl<T: Ord> FunnelSort<T> {
+    pub(crate) fn new(v: Vec<T>) -> FunnelSort<T> {
+        #[cfg(feature = "instrument")]
+        let len = v.len();
+        let input = segment(v);
+        FunnelSort {
+            #[cfg(feature = "instrument")]
+            stats: Stats {
+                allocations: len.div_ceil(BASE_LEN) as u64,
+                ..Stats::default()
+            },
+            merge: input,
+            peeked: None,
+        }
+    }
+
+    /// Returns counts of the work the sorter has done so far. Only the buffers of the segments
+    /// are counted.
+    #[cfg(feature = "instrument")]
+    pub(crate) fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Returns about the number of bytes of heap memory held by the remaining elements, not
+    /// counting spare capacity or the funnel's bookkeeping.
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.len() * mem::size_of::<T>()
+    }
+}
+
+impl<T: Ord> Iterator for FunnelSort<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.peeked.take().or_else(|| self.merge.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.merge.size_hint();
+        let peeked = self.peeked.is_some() as usize;
+        (lower + peeked, upper.map(|upper| upper + peeked))
+    }
+}
+
+impl<T: Ord> LazySorted<T> for FunnelSort<T> {
+    fn peek(&mut self) -> Option<&T> {
+        if self.peeked.is_none() {
+            self.peeked = self.merge.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn len(&self) -> usize {
+        self.size_hint().0
+    }
+
+    fn into_inner(self) -> Vec<T> {
+        self.collect()
+    }
+
+    fn split_at_rank(self, k: usize) -> (FunnelSort<T>, FunnelSort<T>) {
+        let (smallest, rest) = split_at_rank(self.into_inner(), k);
+        (FunnelSort::new(smallest), FunnelSort::new(rest))
+    }
+
+    fn modify_remaining<F>(&mut self, f: F)
+        where F: FnOnce(&mut [T])
+    {
+        let mut v = mem::replace(self, FunnelSort::new(Vec::new())).into_inner();
+        f(&mut v);
+        *self = FunnelSort::new(v);
+    }
+}
+
+/// A sorted input to a funnel, or the output of one.
+#[derive(Debug, Clone)]
+enum Input<T> {
+    /// A segment that was sorted outright.
+    Sorted(vec::IntoIter<T>),
+    /// A node of a funnel, merging two inputs.
+    Merge(Box<Merge<T>>),
+    /// The output of a funnel over some of the inputs of the one above it.
+    Buffered(Box<Buffered<T>>),
+}
+
+#[derive(Debug, Clone)]
+struct Merge<T> {
+    inputs: [Input<T>; 2],
+    /// The next element of each input, filled the first time the merge is pulled from.
+    heads: Option<[Option<T>; 2]>,
+}
+
+#[derive(Debug, Clone)]
+struct Buffered<T> {
+    input: Input<T>,
+    buffer: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T: Ord> Iterator for Input<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match *self {
+            Input::Sorted(ref mut run) => run.next(),
+            Input::Merge(ref mut merge) => {
+                let Merge { ref mut inputs, ref mut heads } = **merge;
+                let heads = heads.get_or_insert_with(|| [inputs[0].next(), inputs[1].next()]);
+                let i = match *heads {
+                    [Some(ref a), Some(ref b)] => (b < a) as usize,
+                    [Some(_), None] => 0,
+                    [None, Some(_)] => 1,
+                    [None, None] => return None,
+                };
+                mem::replace(&mut heads[i], inputs[i].next())
+            }
+            Input::Buffered(ref mut buffered) => {
+                if buffered.buffer.is_empty() {
+                    // Filling the whole buffer at once keeps the funnel below working in cache.
+                    let Buffered { ref mut input, ref mut buffer, capacity } = **buffered;
+                    buffer.extend(input.by_ref().take(capacity));
+                }
+                buffered.buffer.pop_front()
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (input_hint, held) = match *self {
+            Input::Sorted(ref run) => return run.size_hint(),
+            Input::Merge(ref merge) => {
+                let (a, b) = (merge.inputs[0].size_hint(), merge.inputs[1].size_hint());
+                let held = merge.heads.as_ref().map_or(0, |heads| heads.iter().flatten().count());
+                ((a.0 + b.0, a.1.and_then(|a| Some(a + b.1?))), held)
+            }
+            Input::Buffered(ref buffered) => (buffered.input.size_hint(), buffered.buffer.len()),
+        };
+        (input_hint.0 + held, input_hint.1.map(|upper| upper + held))
+    }
+}
+
+/// Splits `v` into segments of about `len^(2/3)` elements, and returns a funnel over them.
+fn segment<T: Ord>(mut v: Vec<T>) -> Input<T> {
+    if v.len() <= BASE_LEN {
+        v.sort_unstable();
+        return Input::Sorted(v.into_iter());
+    }
+    let segment_len = v.len().div_ceil(ceil_root(v.len(), 3));
+    let mut segments = Vec::new();
+    while v.len() > segment_len {
+        let rest = v.split_off(v.len() - segment_len);
+        segments.push(segment(rest));
+    }
+    segments.push(segment(v));
+    funnel(segments)
+}
+
+/// Returns a `k`-funnel over `inputs`: a funnel over each group of about `sqrt(k)` of them
+/// writes to a buffer of `k^(3/2)` elements, and a funnel over the buffers merges them.
+fn funnel<T: Ord>(mut inputs: Vec<Input<T>>) -> Input<T> {
+    let k = inputs.len();
+    if k <= 2 {
+        let b = inputs.pop().unwrap_or(Input::Sorted(Vec::new().into_iter()));
+        return match inputs.pop() {
+            Some(a) => Input::Merge(Box::new(Merge { inputs: [a, b], heads: None })),
+            None => b,
+        };
+    }
+    let group_len = ceil_root(k, 2);
+    let capacity = k * group_len;
+    let mut inputs = inputs.into_iter();
+    let mut groups = Vec::with_capacity(k.div_ceil(group_len));
+    loop {
+        let group: Vec<_> = inputs.by_ref().take(group_len).collect();
+        if group.is_empty() {
+            break;
+        }
+        groups.push(Input::Buffered(Box::new(Buffered {
+            input: funnel(group),
+            buffer: VecDeque::new(),
+            capacity,
+        })));
+    }
+    funnel(groups)
+}
+
+/// Returns the smallest `r` such that `r^p >= n`.
+fn ceil_root(n: usize, p: u32) -> usize {
+    let mut r = 1usize;
+    while r.checked_pow(p).is_some_and(|power| power < n) {
+        r += 1;
+    }
+    r
+}
+
+#[test]
+fn funnel_sort() {
+    let v: Vec<u32> = (0..100_000).map(|i| (i * 7919) % 100_003 / 2).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    let mut sorter = FunnelSort::new(v);
+    assert_eq!(sorter.len(), 100_000);
+    assert_eq!(sorter.peek(), Some(&0));
+    assert!(sorter.by_ref().take(1000).eq(expected[..1000].iter().cloned()));
+    let (smallest, rest) = sorter.split_at_rank(500);
+    assert!(smallest.chain(rest).eq(expected[1000..].iter().cloned()));
+    assert_eq!(FunnelSort::new(vec![3, 1, 2]).collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(ceil_root(27, 3), 3);
+    assert_eq!(ceil_root(28, 3), 4);
+}
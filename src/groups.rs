@@ -0,0 +1,90 @@
+//! Lazily sorting within each group of a pre-grouped stream.
+
+use std::iter::FusedIterator;
+use super::{Config, QuickSort};
+
+/// Pairs each key in `groups` with a lazy quicksort of its group, for paginating within groups
+/// without sorting them all up front.
+///
+/// A group is only collected when it's yielded, and only sorted as far as its elements are taken,
+/// so groups that are skipped or left untouched cost no comparisons. Groups that are already
+/// `Vec`s are sorted in place.
+///
+/// ```
+/// use lazy_sort::sort_within_groups;
+///
+/// let categories = vec![("fruit", vec![7, 3, 9, 1]), ("veg", vec![4, 8, 2])];
+/// let firsts: Vec<_> = sort_within_groups(categories)
+///     .map(|(category, sorted)| (category, sorted.take(2).collect::<Vec<_>>()))
+///     .collect();
+/// assert_eq!(firsts, [("fruit", vec![1, 3]), ("veg", vec![2, 4])]);
+/// ```
+pub fn sort_within_groups<I, K, G>(groups: I) -> SortedGroups<I::IntoIter>
+    where I: IntoIterator<Item = (K, G)>,
+          G: IntoIterator,
+          G::Item: Ord
+{
+    SortedGroups(groups.into_iter())
+}
+
+/// An iterator over the groups of a pre-grouped stream, each with a lazy quicksort of its
+/// elements, created by [`sort_within_groups`](fn.sort_within_groups.html).
+#[derive(Debug, Clone)]
+pub struct SortedGroups<I>(I);
+
+impl<I, K, G> Iterator for SortedGroups<I>
+    where I: Iterator<Item = (K, G)>,
+          G: IntoIterator,
+          G::Item: Ord
+{
+    type Item = (K, QuickSort<G::Item>);
+
+    fn next(&mut self) -> Option<(K, QuickSort<G::Item>)> {
+        self.0.next().map(|(key, group)| {
+            (key, QuickSort::with_config(group.into_iter().collect(), Config::default()))
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(K, QuickSort<G::Item>)> {
+        // Skip the groups without collecting them.
+        if n > 0 {
+            self.0.nth(n - 1)?;
+        }
+        self.next()
+    }
+}
+
+impl<I, K, G> ExactSizeIterator for SortedGroups<I>
+    where I: ExactSizeIterator<Item = (K, G)>,
+          G: IntoIterator,
+          G::Item: Ord
+{
+}
+
+impl<I, K, G> FusedIterator for SortedGroups<I>
+    where I: FusedIterator<Item = (K, G)>,
+          G: IntoIterator,
+          G::Item: Ord
+{
+}
+
+#[test]
+fn sorted_groups() {
+    use std::vec::Vec;
+    use super::LazySorted;
+
+    let groups = (0..5u32).map(|g| (g, (0..10u32).map(move |i| (i * 7 + g) % 10)));
+    let mut sorted = sort_within_groups(groups);
+    assert_eq!(sorted.len(), 5);
+    let (key, mut group) = sorted.nth(2).unwrap();
+    assert_eq!(key, 2);
+    assert_eq!(group.peek(), Some(&0));
+    assert_eq!(group.by_ref().skip(3).take(3).collect::<Vec<_>>(), [3, 4, 5]);
+    assert_eq!(group.len(), 4);
+    let rest: Vec<_> = sorted.map(|(key, group)| (key, group.collect::<Vec<_>>())).collect();
+    assert_eq!(rest, [(3, (0..10).collect::<Vec<_>>()), (4, (0..10).collect())]);
+}
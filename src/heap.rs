@@ -0,0 +1,207 @@
+//! A binary min-heap ordered by a comparator.
+
+use std::cmp::Ordering::{self, Less};
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ptr;
+use std::vec::Vec;
+
+/// A binary heap that pops its smallest element first, as decided by a comparator rather than by
+/// `Ord`.
+///
+/// `compare` must be a total order. Pushing and popping take `O(log n)` comparisons, and building
+/// a heap from a vec with [`from_vec`](#method.from_vec) takes `O(n)`.
+///
+/// ```
+/// use lazy_sort::MinHeapBy;
+///
+/// let mut heap = MinHeapBy::new(|a: &&str, b: &&str| a.len().cmp(&b.len()));
+/// heap.push("banana");
+/// heap.push("fig");
+/// heap.push("cherry");
+/// assert_eq!(heap.peek(), Some(&"fig"));
+/// assert_eq!(heap.into_sorted_iter().skip(1).map(str::len).collect::<Vec<_>>(), [6, 6]);
+/// ```
+#[derive(Clone)]
+pub struct MinHeapBy<T, F> {
+    data: Vec<T>,
+    compare: F,
+}
+
+impl<T, F> MinHeapBy<T, F>
+    where F: Fn(&T, &T) -> Ordering
+{
+    /// Returns an empty heap ordered by `compare`.
+    pub fn new(compare: F) -> MinHeapBy<T, F> {
+        MinHeapBy::from_vec(Vec::new(), compare)
+    }
+
+    /// Returns an empty heap ordered by `compare`, with room for `capacity` elements.
+    pub fn with_capacity(capacity: usize, compare: F) -> MinHeapBy<T, F> {
+        MinHeapBy::from_vec(Vec::with_capacity(capacity), compare)
+    }
+
+    /// Heapifies `v` in `O(n)`.
+    pub fn from_vec(v: Vec<T>, compare: F) -> MinHeapBy<T, F> {
+        let mut heap = MinHeapBy { data: v, compare };
+        for i in (0..heap.data.len() / 2).rev() {
+            heap.sift_down(i);
+        }
+        heap
+    }
+
+    /// Adds an element.
+    pub fn push(&mut self, el: T) {
+        self.data.push(el);
+        let mut i = self.data.len() - 1;
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if (self.compare)(&self.data[i], &self.data[parent]) != Less {
+                break;
+            }
+            self.data.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    /// Removes and returns the smallest element, or `None` if the heap is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        let last = self.data.len().checked_sub(1)?;
+        self.data.swap(0, last);
+        let min = self.data.pop();
+        self.sift_down(0);
+        min
+    }
+
+    /// Returns the smallest element without removing it.
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if the heap has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the elements in no particular order.
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+
+    /// Returns an iterator that pops the elements in ascending order.
+    pub fn into_sorted_iter(self) -> IntoSortedIter<T, F> {
+        IntoSortedIter(self)
+    }
+
+    /// Moves the element at `i` down until it's no greater than its children.
+    fn sift_down(&mut self, i: usize) {
+        let compare = &self.compare;
+        sift_down_by(&mut self.data, i, |a, b| compare(a, b) == Less);
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for MinHeapBy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MinHeapBy").field("data", &self.data).finish()
+    }
+}
+
+impl<T, F> Extend<T> for MinHeapBy<T, F>
+    where F: Fn(&T, &T) -> Ordering
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for el in iter {
+            self.push(el);
+        }
+    }
+}
+
+/// An iterator over the elements of a [`MinHeapBy`](struct.MinHeapBy.html) in ascending order,
+/// created by [`into_sorted_iter`](struct.MinHeapBy.html#method.into_sorted_iter).
+#[derive(Debug, Clone)]
+pub struct IntoSortedIter<T, F>(MinHeapBy<T, F>);
+
+impl<T, F> IntoSortedIter<T, F>
+    where F: Fn(&T, &T) -> Ordering
+{
+    /// Returns the next element in sorted order without consuming it.
+    pub fn peek(&self) -> Option<&T> {
+        self.0.peek()
+    }
+}
+
+impl<T, F> Iterator for IntoSortedIter<T, F>
+    where F: Fn(&T, &T) -> Ordering
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len(), Some(self.0.len()))
+    }
+}
+
+impl<T, F> ExactSizeIterator for IntoSortedIter<T, F> where F: Fn(&T, &T) -> Ordering { }
+
+impl<T, F> FusedIterator for IntoSortedIter<T, F> where F: Fn(&T, &T) -> Ordering { }
+
+/// Moves the element at `pos` down the heap `v` to where it belongs, assuming that the subtrees of
+/// its children are already heaps. `above(a, b)` is true if `a` belongs above `b`.
+pub(crate) fn sift_down_by<T, F>(v: &mut [T], pos: usize, above: F)
+    where F: FnMut(&T, &T) -> bool
+{
+    // Safety: `pos` and its descendants are within `v`, which is borrowed mutably.
+    unsafe { sift_down_ptr(v.as_mut_ptr(), v.len(), pos, above) }
+}
+
+/// Like `sift_down_by`, on the heap of `len` elements at `v`.
+///
+/// # Safety
+///
+/// `v` must point to `len` initialized elements, and nothing else may access `pos` or its
+/// descendants until this returns.
+pub(crate) unsafe fn sift_down_ptr<T, F>(v: *mut T, len: usize, mut pos: usize, mut above: F)
+    where F: FnMut(&T, &T) -> bool
+{
+    loop {
+        let mut child = 2 * pos + 1;
+        if child >= len {
+            return;
+        }
+        if child + 1 < len && above(&*v.add(child + 1), &*v.add(child)) {
+            child += 1;
+        }
+        if !above(&*v.add(child), &*v.add(pos)) {
+            return;
+        }
+        ptr::swap(v.add(pos), v.add(child));
+        pos = child;
+    }
+}
+
+#[test]
+fn min_heap_by() {
+    let v: Vec<u32> = (0..500).map(|i| (i * 7919) % 500).collect();
+    let descending = |a: &u32, b: &u32| b.cmp(a);
+    let mut heap = MinHeapBy::from_vec(v.clone(), descending);
+    assert_eq!(heap.len(), 500);
+    assert_eq!(heap.peek(), Some(&499));
+    assert_eq!(heap.pop(), Some(499));
+    heap.extend(vec![1000, 0]);
+    assert_eq!(heap.pop(), Some(1000));
+    let sorted: Vec<_> = heap.into_sorted_iter().collect();
+    assert_eq!(sorted, (0..499).rev().chain(Some(0)).collect::<Vec<_>>());
+
+    let mut heap = MinHeapBy::with_capacity(4, |a: &(u8, char), b: &(u8, char)| a.0.cmp(&b.0));
+    heap.extend(vec![(3, 'c'), (1, 'a'), (2, 'b')]);
+    assert_eq!(heap.into_sorted_iter().map(|p| p.1).collect::<String>(), "abc");
+    assert_eq!(MinHeapBy::new(descending).pop(), None);
+}
@@ -0,0 +1,129 @@
+//! Counting elements into buckets without sorting them.
+
+use std::iter;
+use std::vec::Vec;
+
+/// How [`histogram`](trait.LazySortIterator.html#method.histogram) chooses the boundaries of
+/// its buckets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BucketSpec<T> {
+    /// Buckets split at these boundaries, which may be given in any order. Duplicates are ignored.
+    Boundaries(Vec<T>),
+    /// This many buckets holding about equal numbers of elements, split at the elements whose
+    /// ranks are multiples of `len / n`. Elements equal to a boundary all fall in the same
+    /// bucket, so runs of equal elements make the counts uneven.
+    Quantiles(usize),
+}
+
+macro_rules! equal_width {
+    ($($t:ty)*) => {$(
+        impl BucketSpec<$t> {
+            /// Returns boundaries that split `min..=max` into `buckets` buckets of equal width,
+            /// rounding the boundaries down. Elements below `min` or above `max` fall in the
+            /// first or last bucket.
+            ///
+            /// Panics if `buckets` is zero or `min > max`.
+            pub fn equal_width(min: $t, max: $t, buckets: usize) -> BucketSpec<$t> {
+                assert!(buckets > 0, "buckets must be positive");
+                assert!(min <= max, "min must not be greater than max");
+                let width = max as i128 - min as i128;
+                BucketSpec::Boundaries((1..buckets).map(|i| {
+                    (min as i128 + width * i as i128 / buckets as i128) as $t
+                }).collect())
+            }
+        }
+    )*}
+}
+
+equal_width!(u8 u16 u32 u64 usize i8 i16 i32 i64 isize);
+
+/// A bucket of a histogram, holding the elements that are at least `lower` and less than `upper`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bucket<T> {
+    /// The inclusive lower bound, or `None` for the first bucket.
+    pub lower: Option<T>,
+    /// The exclusive upper bound, or `None` for the last bucket.
+    pub upper: Option<T>,
+    /// The number of elements in the bucket.
+    pub count: usize,
+}
+
+pub(crate) fn histogram<I>(mut iter: I, spec: BucketSpec<I::Item>) -> Vec<Bucket<I::Item>>
+    where I: Iterator,
+          I::Item: Ord + Clone
+{
+    let (elements, mut boundaries) = match spec {
+        BucketSpec::Boundaries(boundaries) => (None, boundaries),
+        BucketSpec::Quantiles(n) => {
+            assert!(n > 0, "the number of buckets must be positive");
+            let mut v: Vec<_> = iter.by_ref().collect();
+            // With more buckets than elements, several boundaries fall on the same rank.
+            let mut ranks: Vec<_> = (1..n).map(|i| i * v.len() / n)
+                                          .filter(|&r| r > 0 && r < v.len())
+                                          .collect();
+            ranks.dedup();
+            select_ranks(&mut v, 0, &ranks);
+            let boundaries = ranks.iter().map(|&r| v[r].clone()).collect();
+            (Some(v), boundaries)
+        }
+    };
+    boundaries.sort();
+    boundaries.dedup();
+
+    let mut counts = vec![0; boundaries.len() + 1];
+    let mut count = |el: &I::Item| counts[boundaries.partition_point(|b| b <= el)] += 1;
+    match elements {
+        Some(v) => v.iter().for_each(count),
+        None => iter.for_each(|el| count(&el)),
+    }
+
+    let lowers = iter::once(None).chain(boundaries.iter().cloned().map(Some));
+    let uppers = boundaries.iter().cloned().map(Some).chain(iter::once(None));
+    lowers.zip(uppers)
+          .zip(counts)
+          .map(|((lower, upper), count)| Bucket { lower, upper, count })
+          .collect()
+}
+
+/// Reorders `v`, which starts at position `offset` of the whole input, so that the element at
+/// each of `ranks`, which are sorted, is the one that would be there if the input were sorted.
+///
+/// Selecting the middle rank first and recursing on either side takes expected
+/// `O(n log ranks.len())` time.
+fn select_ranks<T: Ord>(v: &mut [T], offset: usize, ranks: &[usize]) {
+    if ranks.is_empty() {
+        return;
+    }
+    let mid = ranks.len() / 2;
+    let rank = ranks[mid];
+    let (less, _, greater) = v.select_nth_unstable(rank - offset);
+    select_ranks(less, offset, &ranks[..mid]);
+    select_ranks(greater, rank + 1, &ranks[mid + 1..]);
+}
+
+#[test]
+fn histograms() {
+    use super::LazySortIterator;
+
+    let values = (0..1000u32).map(|i| (i * 7919) % 1000);
+    let counts = |buckets: Vec<Bucket<u32>>| buckets.iter().map(|b| b.count).collect::<Vec<_>>();
+    let quartiles = values.clone().histogram(BucketSpec::Quantiles(4));
+    assert_eq!(quartiles[1], Bucket { lower: Some(250), upper: Some(500), count: 250 });
+    assert_eq!(counts(quartiles), [250; 4]);
+
+    let widths = values.clone().histogram(BucketSpec::<u32>::equal_width(0, 500, 5));
+    assert_eq!(widths[4].lower, Some(400));
+    assert_eq!(counts(widths), [100, 100, 100, 100, 600]);
+    let boundaries = BucketSpec::Boundaries(vec![900, 100, 100]);
+    assert_eq!(counts(values.histogram(boundaries)), [100, 800, 100]);
+
+    let runs = (0..100u32).map(|i| i / 40).histogram(BucketSpec::Quantiles(5));
+    assert_eq!(counts(runs), [0, 40, 40, 20]);
+    for &n in &[5, 8] {
+        let small = (0..5u32).histogram(BucketSpec::Quantiles(n));
+        assert_eq!(small[4], Bucket { lower: Some(4), upper: None, count: 1 });
+        assert_eq!(counts(small), [1; 5]);
+    }
+    let empty = None::<u32>.into_iter().histogram(BucketSpec::Quantiles(3));
+    assert_eq!(empty, [Bucket { lower: None, upper: None, count: 0 }]);
+}
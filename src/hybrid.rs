@@ -0,0 +1,220 @@
+//! Sorting inputs that don't fit in memory by partitioning them by key, keeping the partition at
+//! the front of the output in memory and spilling the others until they're reached.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::iter::{self, FusedIterator};
+use std::mem;
+use super::external::Run;
+use super::{Config, ExternalSortBuilder, LazySorted, QuickSort, SpillCodec};
+
+impl ExternalSortBuilder {
+    /// Like [`sort_with_codec`](#method.sort_with_codec), but partitions the input by key
+    /// instead of spilling sorted runs to merge, so that the front of the output is sorted from
+    /// memory, as fast as an in-memory lazy sort.
+    ///
+    /// The input is read into a partition of the smallest elements, held in memory. Whenever the
+    /// memory budget fills, either that partition is split at its median and its upper half
+    /// spilled as a partition of its own, or the elements buffered for one of the spilled
+    /// partitions are added to it. Each spilled partition is read back once the output reaches
+    /// it, and partitioned again the same way if it doesn't fit, so no more than the memory
+    /// budget of elements is ever held at once.
+    pub fn hybrid_sort_with_codec<I, C>(self, iter: I, codec: C)
+        -> io::Result<HybridSort<I::Item, C>>
+        where I: IntoIterator,
+              I::Item: Ord + Clone,
+              C: SpillCodec<I::Item> + Clone
+    {
+        let capacity = (self.memory_budget / mem::size_of::<I::Item>().max(1)).max(2);
+        let (front, partitions) = distribute(&self, iter, &codec, capacity)?;
+        Ok(HybridSort {
+            front: QuickSort::with_config(front, Config::default()),
+            partitions: partitions.into(),
+            builder: self,
+            codec,
+            capacity,
+            error: None,
+        })
+    }
+}
+
+/// The smallest elements of an input that doesn't fit in memory, lazily sorted in memory, and
+/// the rest partitioned by key on disk, created by `ExternalSortBuilder`'s
+/// [`hybrid_sort_with_codec`](struct.ExternalSortBuilder.html#method.hybrid_sort_with_codec).
+///
+/// If spilling a partition or reading one back fails, iteration stops early and the error is
+/// available from [`error`](#method.error).
+#[derive(Debug)]
+pub struct HybridSort<T, C> {
+    front: QuickSort<T>,
+    /// The spilled partitions, in order.
+    partitions: VecDeque<Partition<T, C>>,
+    builder: ExternalSortBuilder,
+    codec: C,
+    /// The number of elements that fit in the memory budget.
+    capacity: usize,
+    error: Option<io::Error>,
+}
+
+impl<T, C> HybridSort<T, C> {
+    /// Returns the error that ended iteration early, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+}
+
+impl<T: Ord + Clone, C: SpillCodec<T> + Clone> HybridSort<T, C> {
+    /// Reads the next partition into memory, partitioning it further if it doesn't fit.
+    fn load(&mut self, partition: Partition<T, C>) -> io::Result<()> {
+        self.front = QuickSort::with_config(Vec::new(), Config::default());
+        let mut runs = partition.runs;
+        let elements = runs.iter_mut()
+                           .flat_map(|run| iter::from_fn(move || run.next()))
+                           .chain(partition.buffer);
+        let (front, partitions) = distribute(&self.builder, elements, &self.codec, self.capacity)?;
+        if let Some(err) = runs.iter_mut().find_map(Run::take_error) {
+            return Err(err);
+        }
+        self.front = QuickSort::with_config(front, Config::default());
+        for partition in partitions.into_iter().rev() {
+            self.partitions.push_front(partition);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Ord + Clone, C: SpillCodec<T> + Clone> Iterator for HybridSort<T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if let Some(next) = self.front.next() {
+                return Some(next);
+            }
+            if self.error.is_some() {
+                return None;
+            }
+            let partition = self.partitions.pop_front()?;
+            if let Err(err) = self.load(partition) {
+                self.error = Some(err);
+                return None;
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let front = self.front.len();
+        (front, Some(front + self.partitions.iter().map(Partition::len).sum::<usize>()))
+    }
+}
+
+impl<T: Ord + Clone, C: SpillCodec<T> + Clone> FusedIterator for HybridSort<T, C> { }
+
+/// A key range of the input: runs spilled to disk in no particular order, and the elements
+/// buffered for it since the last one.
+struct Partition<T, C> {
+    runs: Vec<Run<C>>,
+    buffer: Vec<T>,
+}
+
+impl<T, C> Partition<T, C> {
+    fn len(&self) -> usize {
+        self.runs.iter().map(Run::remaining).sum::<usize>() + self.buffer.len()
+    }
+}
+
+impl<T, C: fmt::Debug> fmt::Debug for Partition<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Partition")
+         .field("runs", &self.runs)
+         .field("buffered", &self.buffer.len())
+         .finish()
+    }
+}
+
+/// A spilled partition still being filled, along with the largest element it may hold, or `None`
+/// for the last one.
+struct Filling<T, C> {
+    bound: Option<T>,
+    partition: Partition<T, C>,
+}
+
+/// The partition of the smallest elements, and the spilled partitions of the rest in order.
+type Partitioned<T, C> = (Vec<T>, Vec<Partition<T, C>>);
+
+/// Partitions `iter`, holding at most `capacity` elements in memory at once.
+fn distribute<I, T, C>(builder: &ExternalSortBuilder, iter: I, codec: &C, capacity: usize)
+    -> io::Result<Partitioned<T, C>>
+    where I: IntoIterator<Item = T>,
+          T: Ord + Clone,
+          C: SpillCodec<T> + Clone
+{
+    // Elements equal to a bound may be on either side of it, which still leaves each partition no
+    // greater than the ones after it.
+    let (mut front, mut front_bound) = (Vec::new(), None::<T>);
+    let mut spilled: Vec<Filling<T, C>> = Vec::new();
+    let mut buffered = 0;
+    for el in iter {
+        if front_bound.as_ref().is_none_or(|bound| el <= *bound) {
+            front.push(el);
+        } else {
+            let i = spilled.partition_point(|f| f.bound.as_ref().is_some_and(|bound| *bound < el));
+            spilled[i].partition.buffer.push(el);
+        }
+        buffered += 1;
+        if buffered < capacity {
+            continue;
+        }
+        if front.len() * 2 >= buffered {
+            let mid = front.len() / 2;
+            front.select_nth_unstable(mid);
+            let run = builder.spill(&front[mid..], codec.clone())?;
+            let bound = front_bound.replace(front[mid].clone());
+            buffered -= front.len() - mid;
+            front.truncate(mid);
+            spilled.insert(0, Filling {
+                bound,
+                partition: Partition { runs: vec![run], buffer: Vec::new() },
+            });
+        } else {
+            // Most of what's buffered belongs to the spilled partitions.
+            let fullest = spilled.iter_mut()
+                                 .map(|f| &mut f.partition)
+                                 .max_by_key(|partition| partition.buffer.len())
+                                 .unwrap();
+            let buffer = mem::take(&mut fullest.buffer);
+            buffered -= buffer.len();
+            fullest.runs.push(builder.spill(&buffer, codec.clone())?);
+        }
+    }
+    Ok((front, spilled.into_iter().map(|f| f.partition).collect()))
+}
+
+#[test]
+fn hybrid_sort() {
+    use super::external::FixedWidth;
+    use super::rng::XorShift;
+
+    let mut rng = XorShift::new(6);
+    let v: Vec<u64> = (0..1000).map(|_| rng.next_u64() % 100).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    for &budget in &[8, 64, 800, 1 << 20] {
+        let mut sorted = ExternalSortBuilder::new().memory_budget(budget)
+                                                   .hybrid_sort_with_codec(v.clone(), FixedWidth)
+                                                   .unwrap();
+        let capacity = sorted.capacity;
+        let buffered = sorted.partitions.iter().map(|p| p.buffer.len()).sum::<usize>();
+        assert!(sorted.front.len() + buffered <= capacity);
+        assert_eq!(sorted.partitions.is_empty(), budget >= 8 * v.len());
+        assert_eq!(sorted.size_hint().1, Some(v.len()));
+        assert!(sorted.by_ref().take(10).eq(expected[..10].iter().cloned()));
+        assert!(sorted.front.len() <= capacity);
+        assert_eq!(sorted.collect::<Vec<_>>(), &expected[10..]);
+    }
+    let equal = ExternalSortBuilder::new().memory_budget(64)
+                                          .hybrid_sort_with_codec(vec![7; 500], FixedWidth)
+                                          .unwrap();
+    assert_eq!(equal.collect::<Vec<_>>(), [7; 500]);
+}
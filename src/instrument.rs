@@ -0,0 +1,66 @@
+//! Counting the work the sorters do, to compare them on real data.
+
+use std::cell::Cell;
+use std::vec::Vec;
+
+/// Counts of the work a sorter has done so far, returned by its `stats` method.
+///
+/// Only work done on the thread calling into the sorter is counted, so the parallel partitioning
+/// of `par_sort` and `par_quick_sort` isn't.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of comparisons between elements.
+    pub comparisons: u64,
+    /// The number of times an element was moved within the sorter's buffers, where a swap moves
+    /// two. `HeapSort` doesn't count these, since its moves happen inside `BinaryHeap`.
+    pub moves: u64,
+    /// The number of buffers allocated.
+    pub allocations: u64,
+    /// The length of each partition quicksort has split, pivot included, in the order they were
+    /// split.
+    pub partition_sizes: Vec<usize>,
+}
+
+thread_local! {
+    /// The comparisons `ReverseOrder` has made on this thread, which is how the comparisons made
+    /// inside `BinaryHeap` are counted.
+    static HEAP_COMPARISONS: Cell<u64> = const { Cell::new(0) };
+}
+
+pub(crate) fn count_heap_comparison() {
+    HEAP_COMPARISONS.with(|count| count.set(count.get() + 1));
+}
+
+/// Runs `f`, adding the comparisons of `ReverseOrder`s that it makes to `stats`.
+pub(crate) fn count_heap_comparisons<R, F>(stats: &mut Stats, f: F) -> R
+    where F: FnOnce() -> R
+{
+    let before = HEAP_COMPARISONS.with(Cell::get);
+    let result = f();
+    stats.comparisons += HEAP_COMPARISONS.with(Cell::get) - before;
+    result
+}
+
+#[test]
+fn instrument_stats() {
+    use super::{LazySortBuilder, LazySorted, LazySortIterator};
+
+    // Unperturbed pivots fix the number of partitions, which the allocations are checked against.
+    let mut quick = LazySortBuilder::new().perturb_pivots(false).sort((0..100).rev()).unwrap();
+    assert_eq!(quick.next(), Some(0));
+    let stats = quick.stats().clone();
+    assert!(stats.comparisons >= 99);
+    assert!(stats.moves > 0);
+    assert_eq!(stats.partition_sizes[0], 100);
+    assert_eq!(stats.allocations as usize, 2 * stats.partition_sizes.len());
+    quick.by_ref().for_each(drop);
+    assert!(quick.stats().comparisons > stats.comparisons);
+
+    let mut heap = (0..100).rev().heap_sort();
+    let built = heap.stats().comparisons;
+    assert!(built >= 99);
+    heap.advance_to(&50);
+    assert_eq!(heap.next(), Some(50));
+    assert!(heap.stats().comparisons > built);
+    assert_eq!(heap.stats().moves, 0);
+}
@@ -0,0 +1,47 @@
+//! Compatibility with the `itertools` crate.
+//!
+//! The sorters are ordinary fused iterators, so they can be passed straight to
+//! `itertools::kmerge`. [`KSmallest`](trait.KSmallest.html) offers a drop-in replacement for
+//! `Itertools::k_smallest` that sorts lazily: swap the import, and the call sites stay the same.
+//! Importing both traits makes the method ambiguous, so call sites that also use other
+//! `Itertools` methods should name the trait explicitly.
+
+use std::iter::Take;
+use super::{LazySortIterator, QuickSort};
+
+/// An iterator extension trait providing a lazy equivalent of `Itertools::k_smallest`.
+pub trait KSmallest: Iterator
+    where Self: Sized,
+          Self::Item: Ord
+{
+    /// Returns an iterator over the `k` smallest elements in ascending order, like
+    /// `Itertools::k_smallest`. The elements are sorted lazily, so taking fewer than `k` of them
+    /// does less work.
+    fn k_smallest(self, k: usize) -> Take<QuickSort<Self::Item>> {
+        self.quick_sort().take(k)
+    }
+}
+
+impl<T> KSmallest for T
+    where T: Iterator,
+          T::Item: Ord { }
+
+#[test]
+fn k_smallest_matches_itertools() {
+    let v: Vec<u32> = (0..300).map(|i| (i * 7919) % 257).collect();
+    let ours: Vec<_> = KSmallest::k_smallest(v.iter().cloned(), 10).collect();
+    let theirs: Vec<_> = ::itertools::Itertools::k_smallest(v.iter().cloned(), 10).collect();
+    assert_eq!(ours, theirs);
+}
+
+#[test]
+fn itertools_kmerge() {
+    use super::LazySortIterator;
+
+    let merged: Vec<_> = ::itertools::kmerge(vec![(0..50).rev().quick_sort(),
+                                                  (25..75).rev().quick_sort()])
+                             .collect();
+    let mut expected: Vec<_> = (0..50).chain(25..75).collect();
+    expected.sort();
+    assert_eq!(merged, expected);
+}
@@ -0,0 +1,271 @@
+//! Sort-merge joins that lazily sort both of their inputs by key.
+
+use std::cmp::Ordering;
+use std::vec::Vec;
+use merge::Peeked;
+use super::{LazySortIterator, LazySorted, QuickSort};
+
+/// Lazily joins the elements of `left` and `right` whose keys are equal, yielding every matching
+/// pair in ascending order of key.
+pub fn inner_join<L, R, K, FL, FR>(left: L,
+                                   right: R,
+                                   left_key: FL,
+                                   right_key: FR)
+                                   -> InnerJoin<K, L::Item, R::Item>
+    where L: IntoIterator,
+          R: IntoIterator,
+          K: Ord,
+          FL: FnMut(&L::Item) -> K,
+          FR: FnMut(&R::Item) -> K
+{
+    InnerJoin {
+        core: JoinCore::new(left, right, left_key, right_key),
+        current: None,
+    }
+}
+
+/// Lazily joins each element of `left` with every element of `right` with an equal key, yielding
+/// `None` for left elements without a match, in ascending order of key.
+pub fn left_join<L, R, K, FL, FR>(left: L,
+                                  right: R,
+                                  left_key: FL,
+                                  right_key: FR)
+                                  -> LeftJoin<K, L::Item, R::Item>
+    where L: IntoIterator,
+          R: IntoIterator,
+          K: Ord,
+          FL: FnMut(&L::Item) -> K,
+          FR: FnMut(&R::Item) -> K
+{
+    LeftJoin {
+        core: JoinCore::new(left, right, left_key, right_key),
+        current: None,
+    }
+}
+
+/// Lazily yields the elements of `left` whose key matches some element of `right`, in ascending
+/// order of key.
+pub fn semi_join<L, R, K, FL, FR>(left: L,
+                                  right: R,
+                                  left_key: FL,
+                                  right_key: FR)
+                                  -> SemiJoin<K, L::Item, R::Item>
+    where L: IntoIterator,
+          R: IntoIterator,
+          K: Ord,
+          FL: FnMut(&L::Item) -> K,
+          FR: FnMut(&R::Item) -> K
+{
+    SemiJoin { core: JoinCore::new(left, right, left_key, right_key) }
+}
+
+/// Lazily yields the elements of `left` whose key matches no element of `right`, in ascending
+/// order of key.
+pub fn anti_join<L, R, K, FL, FR>(left: L,
+                                  right: R,
+                                  left_key: FL,
+                                  right_key: FR)
+                                  -> AntiJoin<K, L::Item, R::Item>
+    where L: IntoIterator,
+          R: IntoIterator,
+          K: Ord,
+          FL: FnMut(&L::Item) -> K,
+          FR: FnMut(&R::Item) -> K
+{
+    AntiJoin { core: JoinCore::new(left, right, left_key, right_key) }
+}
+
+/// An iterator over the pairs of an inner join.
+#[derive(Debug, Clone)]
+pub struct InnerJoin<K, L, R> {
+    core: JoinCore<K, L, R>,
+    /// The left element being joined with the current group, and the index of the next right
+    /// element in the group to pair it with.
+    current: Option<(L, usize)>,
+}
+
+impl<K: Ord, L: Clone, R: Clone> Iterator for InnerJoin<K, L, R> {
+    type Item = (L, R);
+
+    fn next(&mut self) -> Option<(L, R)> {
+        loop {
+            if let Some((ref l, ref mut i)) = self.current {
+                if let Some(r) = self.core.group.get(*i) {
+                    *i += 1;
+                    return Some((l.clone(), r.clone()));
+                }
+            }
+            let l = self.core.next_left()?;
+            self.current = Some((l, 0));
+        }
+    }
+}
+
+/// An iterator over the pairs of a left join.
+#[derive(Debug, Clone)]
+pub struct LeftJoin<K, L, R> {
+    core: JoinCore<K, L, R>,
+    current: Option<(L, usize)>,
+}
+
+impl<K: Ord, L: Clone, R: Clone> Iterator for LeftJoin<K, L, R> {
+    type Item = (L, Option<R>);
+
+    fn next(&mut self) -> Option<(L, Option<R>)> {
+        loop {
+            if let Some((ref l, ref mut i)) = self.current {
+                if let Some(r) = self.core.group.get(*i) {
+                    *i += 1;
+                    return Some((l.clone(), Some(r.clone())));
+                }
+            }
+            let l = self.core.next_left()?;
+            if self.core.group.is_empty() {
+                self.current = None;
+                return Some((l, None));
+            }
+            self.current = Some((l, 0));
+        }
+    }
+}
+
+/// An iterator over the left elements of a semi-join.
+#[derive(Debug, Clone)]
+pub struct SemiJoin<K, L, R> {
+    core: JoinCore<K, L, R>,
+}
+
+impl<K: Ord, L, R> Iterator for SemiJoin<K, L, R> {
+    type Item = L;
+
+    fn next(&mut self) -> Option<L> {
+        loop {
+            let l = self.core.next_left()?;
+            if !self.core.group.is_empty() {
+                return Some(l);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.core.left.len()))
+    }
+}
+
+/// An iterator over the left elements of an anti-join.
+#[derive(Debug, Clone)]
+pub struct AntiJoin<K, L, R> {
+    core: JoinCore<K, L, R>,
+}
+
+impl<K: Ord, L, R> Iterator for AntiJoin<K, L, R> {
+    type Item = L;
+
+    fn next(&mut self) -> Option<L> {
+        loop {
+            let l = self.core.next_left()?;
+            if self.core.group.is_empty() {
+                return Some(l);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.core.left.len()))
+    }
+}
+
+/// The state shared by the joins: both sides sorted by key, and the right elements whose key
+/// equals that of the most recent left element.
+#[derive(Debug, Clone)]
+struct JoinCore<K, L, R> {
+    left: QuickSort<ByKey<K, L>>,
+    right: Peeked<QuickSort<ByKey<K, R>>, ByKey<K, R>>,
+    group_key: Option<K>,
+    group: Vec<R>,
+}
+
+impl<K: Ord, L, R> JoinCore<K, L, R> {
+    fn new<I, J, FL, FR>(left: I, right: J, mut left_key: FL, mut right_key: FR) -> Self
+        where I: IntoIterator<Item = L>,
+              J: IntoIterator<Item = R>,
+              FL: FnMut(&L) -> K,
+              FR: FnMut(&R) -> K
+    {
+        JoinCore {
+            left: left.into_iter().map(|l| ByKey(left_key(&l), l)).quick_sort(),
+            right: Peeked::new(right.into_iter().map(|r| ByKey(right_key(&r), r)).quick_sort()),
+            group_key: None,
+            group: Vec::new(),
+        }
+    }
+
+    /// Returns the next left element, leaving the right elements that match it in `group`.
+    fn next_left(&mut self) -> Option<L> {
+        let ByKey(key, l) = self.left.next()?;
+        if self.group_key.as_ref() != Some(&key) {
+            self.group.clear();
+            while self.right.peek().is_some_and(|r| r.0 < key) {
+                self.right.next();
+            }
+            while self.right.peek().is_some_and(|r| r.0 == key) {
+                self.group.extend(self.right.next().map(|ByKey(_, r)| r));
+            }
+            self.group_key = Some(key);
+        }
+        Some(l)
+    }
+}
+
+/// An element paired with its key, ordered by the key alone.
+#[derive(Debug, Clone)]
+struct ByKey<K, T>(K, T);
+
+impl<K: PartialEq, T> PartialEq for ByKey<K, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<K: Eq, T> Eq for ByKey<K, T> {}
+
+impl<K: Ord, T> PartialOrd for ByKey<K, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, T> Ord for ByKey<K, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+#[test]
+fn joins() {
+    let users = vec![(3, "carol"), (1, "alice"), (2, "bob"), (4, "dave")];
+    let orders = vec![("pen", 2), ("ink", 1), ("cup", 2), ("hat", 5)];
+    let user_id = |u: &(u32, &str)| u.0;
+    let order_user = |o: &(&str, u32)| o.1;
+
+    let mut inner: Vec<_> = inner_join(users.clone(), orders.clone(), user_id, order_user)
+                                .map(|(u, o)| (u.1, o.0))
+                                .collect();
+    inner[1..].sort();
+    assert_eq!(inner, vec![("alice", "ink"), ("bob", "cup"), ("bob", "pen")]);
+
+    let left: Vec<_> = left_join(users.clone(), orders.clone(), user_id, order_user)
+                           .map(|(u, o)| (u.1, o.map(|o| o.0)))
+                           .collect();
+    assert_eq!(left.len(), 5);
+    assert_eq!(left[0], ("alice", Some("ink")));
+    assert_eq!(&left[3..], &[("carol", None), ("dave", None)]);
+
+    let semi: Vec<_> = semi_join(users.clone(), orders.clone(), user_id, order_user)
+                           .map(|u| u.1)
+                           .collect();
+    assert_eq!(semi, vec!["alice", "bob"]);
+
+    let anti: Vec<_> = anti_join(users, orders, user_id, order_user).map(|u| u.1).collect();
+    assert_eq!(anti, vec!["carol", "dave"]);
+}
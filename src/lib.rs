@@ -10,16 +10,211 @@
 //! better.
 
 #![deny(missing_docs)]
-#![feature(slice_splits, core)]
-#![cfg_attr(test, feature(test))]
-extern crate core;
-extern crate itertools;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(all(test, feature = "unstable"), feature(test))]
+#[cfg(not(any(feature = "std", test)))]
+#[macro_use]
+extern crate alloc;
+#[cfg(all(test, feature = "unstable"))]
 extern crate rand;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "bincode")]
+extern crate bincode;
+#[cfg(feature = "futures")]
+extern crate futures_core;
+#[cfg(feature = "itertools")]
+extern crate itertools;
+#[cfg(all(test, feature = "futures"))]
+extern crate futures;
+#[cfg(feature = "lz4")]
+extern crate lz4_flex;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "sorted-iter")]
+extern crate sorted_iter;
+#[cfg(feature = "streaming-iterator")]
+extern crate streaming_iterator;
+#[cfg(feature = "external")]
+extern crate tempfile;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "zstd")]
+extern crate zstd;
+
+/// The parts of `std` that `core` and `alloc` provide, so that modules can use the same `std`
+/// paths with or without the `std` feature.
+#[cfg(not(any(feature = "std", test)))]
+mod std {
+    pub use core::*;
+    pub use alloc::{boxed, collections, fmt, rc, string, vec};
+
+    pub mod sync {
+        pub use core::sync::*;
+        pub use alloc::sync::*;
+    }
+}
 
-use core::ptr;
-use itertools::partition;
+mod adaptors;
+#[cfg(feature = "std")]
+mod adaptive;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+mod argsort;
+#[cfg(feature = "std")]
+mod background;
+mod bits;
+mod blocks;
+mod builder;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod chunked;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+#[cfg(feature = "external")]
+mod external;
+mod fixed;
+#[cfg(feature = "std")]
+mod frame;
+mod funnel;
+mod groups;
+mod heap;
+mod histogram;
+#[cfg(feature = "external")]
+mod hybrid;
+#[cfg(feature = "instrument")]
+mod instrument;
+#[cfg(feature = "itertools")]
+pub mod itertools_compat;
+mod join;
+#[cfg(feature = "external")]
+mod lines;
+#[cfg(feature = "std")]
+mod map;
+mod median;
+mod merge;
+mod online;
+mod orders;
+mod pairing;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "prefetch")]
+mod prefetch;
+#[cfg(feature = "std")]
+mod persistent;
+#[cfg(feature = "proptest")]
+pub mod proptest_strategies;
+mod quantile;
+#[cfg(feature = "external")]
+mod records;
+#[cfg(feature = "std")]
+mod replay;
+mod rng;
+mod shared;
+mod shuffle;
+mod sort_key;
+mod strategy;
+#[cfg(feature = "futures")]
+mod stream;
+#[cfg(feature = "streaming-iterator")]
+mod streaming;
+#[cfg(feature = "threads")]
+mod threads;
+#[cfg(feature = "tokio")]
+mod tokio_external;
+#[cfg(feature = "verify")]
+mod verify;
+mod weighted;
+#[cfg(feature = "sorted-iter")]
+mod sorted_iter_impls;
+mod window;
+
+pub use adaptors::{DedupWithCounts, GroupEqual, GroupFold, RankPolicy, Rewindable, SortedChunks,
+                   SortedUnique, WithRanks};
+pub use argsort::{apply_permutation, gather, ArgSort, Gather, LazySortSlice, PrefixArgSort,
+                  StableArgSort};
+#[cfg(feature = "std")]
+pub use background::spawn_sorted;
+pub use bits::{quick_sort_bits, OrderedBits, SortedBits};
+pub use blocks::SortedBlocks;
+pub use builder::{Algorithm, CancelToken, LazySort, LazySortBuilder, PivotStrategy, Progress,
+                  SortError, Step};
+pub use chunked::SortedWithinChunks;
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::Diagnostics;
+#[cfg(feature = "external")]
+pub use external::{merge_runs, Compression, ExternalSort, ExternalSortBuilder, MergeRuns,
+                   SpillBackend, SpillCodec};
+#[cfg(feature = "bincode")]
+pub use external::BincodeCodec;
+pub use fixed::{CapacityError, FixedHeapSort};
+#[cfg(feature = "instrument")]
+pub use instrument::Stats;
+#[cfg(feature = "std")]
+pub use frame::FrameSort;
+pub use groups::{sort_within_groups, SortedGroups};
+pub use heap::{IntoSortedIter, MinHeapBy};
+pub use histogram::{Bucket, BucketSpec};
+#[cfg(feature = "external")]
+pub use hybrid::HybridSort;
+pub use join::{anti_join, inner_join, left_join, semi_join, AntiJoin, InnerJoin, LeftJoin,
+               SemiJoin};
+#[cfg(feature = "external")]
+pub use lines::{sort_lines, sort_lines_by_key, SortedLines};
+#[cfg(feature = "std")]
+pub use map::{LazySortMap, SortedByKey, SortedByValue, SortedByValueDesc};
+pub use median::RunningMedian;
+pub use merge::{difference, intersection, kmerge, merge, multiset_difference,
+                multiset_intersection, multiset_union, symmetric_difference, union, Difference,
+                Intersection, KMerge, Merge, MultisetDifference, MultisetIntersection,
+                MultisetUnion, Side, SymmetricDifference, Union};
+pub use online::{OnlineSorter, WatermarkSort};
+pub use orders::{IndexSort, SortOrders};
+pub use pairing::PairingHeapSort;
+#[cfg(feature = "rayon")]
+pub use parallel::ParallelLazySortIterator;
+#[cfg(feature = "std")]
+pub use persistent::PersistentSort;
+pub use quantile::QuantileSketch;
+#[cfg(feature = "external")]
+pub use records::{sort_records, sort_records_by_key, SortedRecords};
+#[cfg(feature = "std")]
+pub use replay::DecisionLog;
+pub use shared::Shared;
+pub use shuffle::{ShuffleGroup, ShuffleSort};
+pub use sort_key::{quick_sort_by_sort_key, SortKey, SortedBySortKey};
+pub use strategy::{LazySortStrategy, StrategySort};
+#[cfg(feature = "futures")]
+pub use stream::{LazySortStream, QuickSortStream};
+#[cfg(feature = "streaming-iterator")]
+pub use streaming::{StreamingRefs, StreamingSort};
+#[cfg(feature = "tokio")]
+pub use tokio_external::SortedStream;
+pub use window::{tumbling_top_k, Kth, SortedWindows, TopKWindow, TumblingTopK};
+
+use builder::StepFn;
+#[cfg(feature = "std")]
+use adaptive::AdaptiveThreshold;
+#[cfg(feature = "std")]
+use replay::Decisions;
+use rng::XorShift;
+use std::array;
+use std::boxed::Box;
 use std::cmp::Ordering::{self, Less};
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter::FusedIterator;
 use std::mem;
+use std::ops::ControlFlow;
+use std::ptr;
+use std::vec::Vec;
 
 /// An iterator extension trait that provides two methods for lazily sorting.
 pub trait LazySortIterator: Iterator
@@ -28,12 +223,151 @@ pub trait LazySortIterator: Iterator
 {
     /// Lazily sort using quicksort.
     fn quick_sort(self) -> QuickSort<Self::Item> {
-        QuickSort { inner: QuickSortInternal::new(self.collect()) }
+        QuickSort::with_config(self.collect(), Config::default())
+    }
+
+    /// Lazily sort using quicksort, partitioning the elements around a pivot sampled from the
+    /// first few as the rest arrive, rather than collecting them all before the first pass.
+    ///
+    /// This overlaps the work of an expensive upstream iterator, such as one decoding its
+    /// elements, with the first partition, so the first element is ready sooner once the last
+    /// one arrives.
+    fn quick_sort_pipelined(self) -> QuickSort<Self::Item>
+        where Self: ExactSizeIterator
+    {
+        QuickSort::pipelined(self, Config::default())
+    }
+
+    /// Splits the elements by `pred` in a single pass, and lazily sorts each side with quicksort.
+    /// The first sorter holds the elements for which `pred` returned `true`, and the second the
+    /// rest.
+    fn partition_sorted<F>(self, pred: F) -> (QuickSort<Self::Item>, QuickSort<Self::Item>)
+        where F: FnMut(&Self::Item) -> bool
+    {
+        let (matching, rest) = self.partition(pred);
+        (QuickSort::with_config(matching, Config::default()),
+         QuickSort::with_config(rest, Config::default()))
     }
 
     /// Lazily sort using heapsort.
     fn heap_sort(self) -> HeapSort<Self::Item> {
-        HeapSort(self.map(|el| ReverseOrder(el)).collect())
+        HeapSort::from_reversed(self.map(ReverseOrder).collect())
+    }
+
+    /// Lazily sorts each consecutive chunk of at most `chunk_size` elements independently, for
+    /// when only local order is needed.
+    ///
+    /// Panics if `chunk_size` is zero.
+    fn sorted_within_chunks(self, chunk_size: usize) -> SortedWithinChunks<Self> {
+        SortedWithinChunks::new(self, chunk_size)
+    }
+
+    /// Lazily sort using a pairing heap, which can be melded with another in `O(1)`.
+    fn pairing_heap_sort(self) -> PairingHeapSort<Self::Item> {
+        self.collect()
+    }
+
+    /// Lazily sort using a custom [`LazySortStrategy`](trait.LazySortStrategy.html).
+    fn sort_with_strategy<S>(self) -> StrategySort<Self::Item, S>
+        where S: LazySortStrategy<Self::Item>
+    {
+        StrategySort::new(self.collect())
+    }
+
+    /// Lazily sort using a heap of at most `N` elements stored inline, without allocating.
+    ///
+    /// Fails with the first element that doesn't fit if the input has more than `N` elements.
+    fn fixed_heap_sort<const N: usize>(self)
+        -> Result<FixedHeapSort<Self::Item, N>, CapacityError<Self::Item>>
+    {
+        FixedHeapSort::try_from_iter(self)
+    }
+
+    /// Returns the `N` smallest elements in ascending order, keeping them in a fixed-size array
+    /// rather than allocating.
+    ///
+    /// Panics if there are fewer than `N` elements; see
+    /// [`smallest_array_padded`](#method.smallest_array_padded).
+    fn smallest_array<const N: usize>(self) -> [Self::Item; N] {
+        let mut smallest = IntoIterator::into_iter(fixed::smallest_array::<_, _, N>(self));
+        array::from_fn(|_| {
+            smallest.next().unwrap().expect("fewer elements than the length of the array")
+        })
+    }
+
+    /// Like [`smallest_array`](#method.smallest_array), but if there are fewer than `N`
+    /// elements, the array is padded with `None`s after them.
+    fn smallest_array_padded<const N: usize>(self) -> [Option<Self::Item>; N] {
+        fixed::smallest_array::<_, _, N>(self)
+    }
+
+    /// Sorts input in which each element is at most `max_disorder` positions out of place, using
+    /// a buffer of `max_disorder + 1` elements.
+    fn watermark_sort(self, max_disorder: usize) -> WatermarkSort<Self> {
+        WatermarkSort::new(self, max_disorder)
+    }
+
+    /// Maintains a sorted view of a window of `window_len` elements sliding over the input.
+    ///
+    /// Panics if `window_len` is zero.
+    fn sorted_windows(self, window_len: usize) -> SortedWindows<Self> {
+        SortedWindows::new(self, window_len)
+    }
+
+    /// Counts the elements into the buckets that `spec` describes, each of which holds the
+    /// elements from its lower bound up to but excluding its upper bound.
+    ///
+    /// Buckets from explicit boundaries take `O(n log b)` time for `b` boundaries, without
+    /// collecting the elements. Quantile boundaries are found by selection, which takes expected
+    /// `O(n log b)` time rather than the `O(n log n)` of a full sort.
+    ///
+    /// ```
+    /// use lazy_sort::{BucketSpec, LazySortIterator};
+    ///
+    /// let latencies = vec![12, 3, 40, 7, 25, 9, 31, 18];
+    /// let buckets = latencies.into_iter().histogram(BucketSpec::<u32>::equal_width(0, 40, 4));
+    /// let counts: Vec<_> = buckets.iter().map(|bucket| bucket.count).collect();
+    /// assert_eq!(counts, [3, 2, 1, 2]);
+    /// ```
+    fn histogram(self, spec: BucketSpec<Self::Item>) -> Vec<Bucket<Self::Item>>
+        where Self::Item: Clone
+    {
+        histogram::histogram(self, spec)
+    }
+
+    /// Returns the weighted median: the smallest element such that it and the elements less than
+    /// it weigh at least half of the total weight, where `weight_fn` gives the weight of each
+    /// element. Returns `None` if the weights sum to zero.
+    ///
+    /// This selects the median in expected `O(n)` time, without sorting. Panics if a weight is
+    /// negative or not finite.
+    fn weighted_median<F>(self, weight_fn: F) -> Option<Self::Item>
+        where F: FnMut(&Self::Item) -> f64
+    {
+        let (mut v, total) = weighted::weighted(self, weight_fn)?;
+        let median = weighted::select_quantile(&mut v, total, 0.5);
+        Some(v.swap_remove(median).0)
+    }
+
+    /// Returns the weighted quantile for each of `qs`, which are clamped to `[0, 1]`. See
+    /// [`weighted_median`](#method.weighted_median).
+    ///
+    /// Each quantile is selected in expected `O(n)` time.
+    fn weighted_quantiles<F>(self, weight_fn: F, qs: &[f64]) -> Vec<Option<Self::Item>>
+        where F: FnMut(&Self::Item) -> f64,
+              Self::Item: Clone
+    {
+        match weighted::weighted(self, weight_fn) {
+            Some((mut v, total)) => {
+                qs.iter()
+                  .map(|&q| {
+                      let quantile = weighted::select_quantile(&mut v, total, q);
+                      Some(v[quantile].0.clone())
+                  })
+                  .collect()
+            }
+            None => qs.iter().map(|_| None).collect(),
+        }
     }
 }
 
@@ -41,140 +375,1618 @@ impl<T> LazySortIterator for T
     where T: Iterator,
           T::Item: Ord { }
 
+/// The common interface of the lazy sorters, which lets code be generic over the sorting strategy.
+pub trait LazySorted<T: Ord>: Iterator<Item = T> {
+    /// Returns a reference to the next element in sorted order without consuming it.
+    fn peek(&mut self) -> Option<&T>;
+
+    /// Returns the number of remaining elements.
+    fn len(&self) -> usize;
+
+    /// Returns true if there are no remaining elements.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Consumes the sorter, returning the remaining elements in no particular order.
+    fn into_inner(self) -> Vec<T> where Self: Sized;
+
+    /// Skips over all remaining elements less than `x`.
+    fn advance_to(&mut self, x: &T) {
+        while self.peek().is_some_and(|el| el < x) {
+            self.next();
+        }
+    }
+
+    /// Partitions the remaining elements around rank `k`, returning a sorter over the `k` smallest
+    /// elements and a sorter over the rest. Neither half is sorted until it is iterated.
+    fn split_at_rank(self, k: usize) -> (Self, Self) where Self: Sized;
+
+    /// Splits the remaining elements into `p` sorters over consecutive ranges of ranks, whose
+    /// lengths differ by at most one. Each can be consumed independently, for example on its own
+    /// thread, and together they yield the elements in order. Takes `O(n log p)` time.
+    ///
+    /// Panics if `p` is zero.
+    fn into_partitions(self, p: usize) -> Vec<Self>
+        where Self: Sized
+    {
+        assert!(p > 0, "can't split into zero partitions");
+        let mut partitions = Vec::with_capacity(p);
+        let mut stack = vec![(self, p)];
+        while let Some((sorter, p)) = stack.pop() {
+            if p == 1 {
+                partitions.push(sorter);
+                continue;
+            }
+            let half = p / 2;
+            let k = sorter.len() * half / p;
+            let (smaller, larger) = sorter.split_at_rank(k);
+            stack.push((larger, p - half));
+            stack.push((smaller, half));
+        }
+        partitions
+    }
+
+    /// Calls `f` with mutable access to the remaining elements, in no particular order, and then
+    /// restores the sorter's invariants so that iteration continues in the new sorted order.
+    ///
+    /// Any partial sorting work done so far is discarded.
+    fn modify_remaining<F>(&mut self, f: F) where F: FnOnce(&mut [T]);
+
+    /// Groups the remaining elements into runs of equal elements, yielded in sorted order.
+    fn group_equal(self) -> GroupEqual<Self>
+        where Self: Sized
+    {
+        GroupEqual::new(self)
+    }
+
+    /// Yields each distinct remaining element once, in sorted order.
+    fn sorted_unique(self) -> SortedUnique<Self>
+        where Self: Sized
+    {
+        SortedUnique::new(self)
+    }
+
+    /// Yields each distinct remaining element once, in sorted order, along with its number of
+    /// occurrences.
+    fn dedup_with_counts(self) -> DedupWithCounts<Self>
+        where Self: Sized
+    {
+        DedupWithCounts::new(self)
+    }
+
+    /// Yields the remaining elements in sorted order along with their ranks, with ties ranked
+    /// according to `policy`.
+    fn with_ranks(self, policy: RankPolicy) -> WithRanks<Self>
+        where Self: Sized
+    {
+        WithRanks::new(self, policy)
+    }
+
+    /// Folds each run of adjacent remaining elements with equal keys, starting each run from a
+    /// clone of `init`, and yields each key along with its run's result in sorted order.
+    ///
+    /// `key_fn` should be consistent with the sort order (for example, a prefix of the fields
+    /// compared by `Ord`), or else elements with equal keys might not be adjacent.
+    fn group_fold<K, A, FK, FF>(self, key_fn: FK, init: A, fold_fn: FF)
+                                -> GroupFold<Self, K, A, FK, FF>
+        where Self: Sized,
+              K: PartialEq,
+              A: Clone,
+              FK: FnMut(&T) -> K,
+              FF: FnMut(A, T) -> A
+    {
+        GroupFold::new(self, key_fn, init, fold_fn)
+    }
+
+    /// Lazily merges the remaining elements with another sorted iterator, which is trusted to be
+    /// sorted. See [`merge`](fn.merge.html).
+    fn merge<J>(self, other: J) -> Merge<Self, J::IntoIter>
+        where Self: Sized,
+              J: IntoIterator<Item = T>
+    {
+        merge::merge(self, other)
+    }
+
+    /// Lazily yields the distinct elements that are either remaining or in another sorted
+    /// iterator. See [`union`](fn.union.html).
+    fn union<J>(self, other: J) -> Union<Self, J::IntoIter>
+        where Self: Sized,
+              J: IntoIterator<Item = T>
+    {
+        merge::union(self, other)
+    }
+
+    /// Lazily yields the distinct elements that are both remaining and in another sorted
+    /// iterator. See [`intersection`](fn.intersection.html).
+    fn intersection<J>(self, other: J) -> Intersection<Self, J::IntoIter>
+        where Self: Sized,
+              J: IntoIterator<Item = T>
+    {
+        merge::intersection(self, other)
+    }
+
+    /// Lazily yields the distinct remaining elements that aren't in another sorted iterator. See
+    /// [`difference`](fn.difference.html).
+    fn difference<J>(self, other: J) -> Difference<Self, J::IntoIter>
+        where Self: Sized,
+              J: IntoIterator<Item = T>
+    {
+        merge::difference(self, other)
+    }
+
+    /// Lazily yields the distinct elements that are either remaining or in another sorted iterator,
+    /// but not both, tagged with the side they came from. See
+    /// [`symmetric_difference`](fn.symmetric_difference.html).
+    fn symmetric_difference<J>(self, other: J) -> SymmetricDifference<Self, J::IntoIter>
+        where Self: Sized,
+              J: IntoIterator<Item = T>
+    {
+        merge::symmetric_difference(self, other)
+    }
+
+    /// Collects the remaining elements into a `BTreeSet`, dropping duplicates.
+    ///
+    /// `BTreeSet` notices that its input is already sorted, so the tree is bulk-built in `O(n)`
+    /// after about two comparisons per element to check the order and find duplicates, instead of
+    /// a search per insertion.
+    fn into_btree_set(self) -> BTreeSet<T>
+        where Self: Sized
+    {
+        self.collect()
+    }
+
+    /// Lazily yields the multiset union of the remaining elements and another sorted iterator,
+    /// keeping each element as many times as the input with more of it. See
+    /// [`multiset_union`](fn.multiset_union.html).
+    fn multiset_union<J>(self, other: J) -> MultisetUnion<Self, J::IntoIter>
+        where Self: Sized,
+              J: IntoIterator<Item = T>
+    {
+        merge::multiset_union(self, other)
+    }
+
+    /// Lazily yields the multiset intersection of the remaining elements and another sorted
+    /// iterator, keeping each element as many times as the input with fewer of it. See
+    /// [`multiset_intersection`](fn.multiset_intersection.html).
+    fn multiset_intersection<J>(self, other: J) -> MultisetIntersection<Self, J::IntoIter>
+        where Self: Sized,
+              J: IntoIterator<Item = T>
+    {
+        merge::multiset_intersection(self, other)
+    }
+
+    /// Lazily yields the remaining elements minus another sorted iterator's as multisets, so that
+    /// each occurrence in `other` cancels out one remaining occurrence. See
+    /// [`multiset_difference`](fn.multiset_difference.html).
+    fn multiset_difference<J>(self, other: J) -> MultisetDifference<Self, J::IntoIter>
+        where Self: Sized,
+              J: IntoIterator<Item = T>
+    {
+        merge::multiset_difference(self, other)
+    }
+
+    /// Moves up to `n` of the next elements into `out` in sorted order, which some sorters do
+    /// faster than calling `next` for each.
+    fn next_chunk_into(&mut self, n: usize, out: &mut Vec<T>)
+        where Self: Sized
+    {
+        out.extend(self.by_ref().take(n));
+    }
+
+    /// Removes the next elements for as long as they're equal to `x`, passing each to `f`, which
+    /// quicksorts do without comparing the elements they set aside with an equal pivot.
+    fn drain_equal<F>(&mut self, x: &T, mut f: F)
+        where Self: Sized,
+              F: FnMut(T)
+    {
+        while self.peek().is_some_and(|el| el == x) {
+            f(self.next().unwrap());
+        }
+    }
+
+    /// Returns up to `k` of the next elements in sorted order, in one call, through
+    /// [`next_chunk_into`](#method.next_chunk_into). To reuse a buffer from batch to batch, call
+    /// that instead.
+    fn next_n(&mut self, k: usize) -> Vec<T>
+        where Self: Sized
+    {
+        let mut out = Vec::with_capacity(k.min(self.len()));
+        self.next_chunk_into(k, &mut out);
+        out
+    }
+
+    /// Yields the remaining elements in sorted pages of `n`, except for the last, which may be
+    /// shorter. See [`next_chunk_into`](#method.next_chunk_into).
+    ///
+    /// Panics if `n` is zero.
+    fn sorted_chunks(self, n: usize) -> SortedChunks<Self, T>
+        where Self: Sized
+    {
+        SortedChunks::new(self, n)
+    }
+
+    /// Remembers the elements as they're yielded, so that calling
+    /// [`rewind`](struct.Rewindable.html#method.rewind) on the result yields them again without
+    /// repeating any comparisons. Each element is cloned when it's yielded.
+    fn rewindable(self) -> Rewindable<Self>
+        where Self: Sized,
+              T: Clone
+    {
+        Rewindable::new(self)
+    }
+
+    /// Lets several consumers iterate the remaining elements while sorting them only once. Clone
+    /// the result to add consumers; see [`Shared`](struct.Shared.html).
+    fn shared(self) -> Shared<Self>
+        where Self: Sized,
+              T: Clone
+    {
+        Shared::new(self)
+    }
+
+    /// Wraps the sorter in a `StreamingIterator` that lends each element from a slot it holds,
+    /// for passes that only read the elements and never need to own them.
+    #[cfg(feature = "streaming-iterator")]
+    fn streaming(self) -> StreamingSort<Self, T>
+        where Self: Sized
+    {
+        StreamingSort::new(self)
+    }
+
+    /// Serializes the remaining elements to `serializer` as a sequence in sorted order, such as a
+    /// JSON array, sorting each one just before it's written rather than collecting them first.
+    #[cfg(feature = "serde")]
+    fn serialize_sorted_into<S>(self, serializer: S) -> Result<S::Ok, S::Error>
+        where Self: Sized,
+              T: serde::Serialize,
+              S: serde::Serializer
+    {
+        use serde::ser::SerializeSeq;
+
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for el in self {
+            seq.serialize_element(&el)?;
+        }
+        seq.end()
+    }
+}
+
+/// Methods for lazy sorters of key-value pairs.
+pub trait LazySortedPairs<K: Ord, V: Ord>: LazySorted<(K, V)> {
+    /// Collects the remaining pairs into a `BTreeMap`, bulk-building it in `O(n)` like
+    /// [`into_btree_set`](trait.LazySorted.html#method.into_btree_set). If a key occurs more
+    /// than once, the value yielded last, which is the largest, is kept.
+    fn into_btree_map(self) -> BTreeMap<K, V>
+        where Self: Sized
+    {
+        self.collect()
+    }
+}
+
+impl<K: Ord, V: Ord, S> LazySortedPairs<K, V> for S where S: LazySorted<(K, V)> { }
+
 /// An iterator that lazily sorts its input using quicksort.
+///
+/// The elements smaller than each pivot wait in a partition nested in that pivot's, so the
+/// partitions form a chain, which takes space for every link. A split that would leave more than
+/// seven eighths of a partition below its pivot first sets the elements equal to the pivot aside,
+/// to be yielded right after it without another comparison, and failing that is redone around
+/// the partition's median, found by quickselect in expected linear time. Each link then holds at
+/// most seven eighths of the one before it, so the chain is at most `log(n) / log(8/7)`, about
+/// `5.2 log2(n)`, links long, whatever the input and pivots.
 #[derive(Debug, Clone)]
 pub struct QuickSort<T> {
     inner: QuickSortInternal<T>,
+    config: Config,
+}
+
+impl<T: Ord> QuickSort<T> {
+    fn with_config(v: Vec<T>, config: Config) -> QuickSort<T> {
+        QuickSort::partitioned(v, config, QuickSortInternal::new)
+    }
+
+    /// Returns a quicksort of `v` whose partitions are built by `partition`, once `config` has
+    /// been set up for a sort of `v.len()` elements.
+    pub(crate) fn partitioned<F>(v: Vec<T>, mut config: Config, partition: F) -> QuickSort<T>
+        where F: FnOnce(Vec<T>, &mut Config) -> QuickSortInternal<T>
+    {
+        config.start(v.len());
+        QuickSort {
+            inner: partition(v, &mut config),
+            config,
+        }
+    }
+
+    /// Partitions the elements of `iter` as they arrive around the median of the first
+    /// `PIPELINE_SAMPLE` of them.
+    fn pipelined<I>(mut iter: I, mut config: Config) -> QuickSort<T>
+        where I: ExactSizeIterator<Item = T>
+    {
+        let len = iter.len();
+        let mut sample: Vec<T> = iter.by_ref().take(PIPELINE_SAMPLE).collect();
+        if len <= config.threshold.max(PIPELINE_SAMPLE) {
+            sample.extend(iter);
+            return QuickSort::with_config(sample, config);
+        }
+        config.start(len);
+        let mut comparisons = 0;
+        let mid = sample.len() / 2;
+        sample.select_nth_unstable_by(mid, |a, b| {
+            comparisons += 1;
+            a.cmp(b)
+        });
+        let pivot = sample.swap_remove(mid);
+        // The same layout as a split partition: the elements greater than the pivot, and then
+        // the pivot, with the rest in `less`.
+        let mut greater = Vec::with_capacity(len / 2 + 1);
+        let mut less = Vec::with_capacity(len / 2);
+        for el in sample.into_iter().chain(iter) {
+            comparisons += 1;
+            if el > pivot {
+                greater.push(el);
+            } else {
+                less.push(el);
+            }
+        }
+        if unbalanced(len, less.len()) {
+            // Splitting it again from scratch redoes it around the median.
+            less.append(&mut greater);
+            less.push(pivot);
+            return QuickSort::with_config(less, config);
+        }
+        config.count(comparisons, len);
+        config.count_partition(len, 2);
+        config.record_split(0, len, greater.len(), less.len());
+        greater.push(pivot);
+        let less = QuickSortInternal::at_depth(less, &mut config, 1);
+        let inner = Recursive::with_less(greater, Some(Box::new(less)), 0);
+        QuickSort {
+            inner: QuickSortInternal::Recursive(inner),
+            config,
+        }
+    }
+
+    /// Works towards the next element, making at most `budget` comparisons.
+    ///
+    /// Returns `ControlFlow::Break` with the result of `next` if it's reached within the budget,
+    /// and otherwise `ControlFlow::Continue`, keeping the progress made so that calling again
+    /// picks up where this call stopped.
+    pub fn try_next(&mut self, mut budget: usize) -> ControlFlow<Option<T>> {
+        match self.inner.try_next(&mut self.config, &mut budget) {
+            ControlFlow::Continue(()) if self.config.cancelled() => ControlFlow::Break(None),
+            ControlFlow::Break(Some(next)) => {
+                self.emitted();
+                // Sorting the rest eagerly doesn't fit in a budget, so once the crossover is
+                // reached that's left to the next call to `next`.
+                if let Some(ref mut eager_in) = self.config.eager_in {
+                    *eager_in = eager_in.saturating_sub(1);
+                }
+                ControlFlow::Break(Some(next))
+            }
+            next => next,
+        }
+    }
+
+    #[inline]
+    fn emitted(&self) {
+        // Counting the remaining elements walks every partition, so it's only done if needed.
+        if self.config.on_step.is_some() {
+            self.config.step(Step::Emit { remaining: self.inner.len() });
+        }
+    }
+
+    /// Counts `n` elements towards the crossover, and sorts the rest eagerly once it's reached.
+    #[inline]
+    fn consumed(&mut self, n: usize) {
+        match self.config.eager_in {
+            Some(ref mut eager_in) if *eager_in > n => *eager_in -= n,
+            Some(_) => self.sort_eagerly(),
+            None => {}
+        }
+    }
+
+    /// Sorts the remaining elements in one go. Once most of the input has been consumed, this is
+    /// cheaper than partitioning the rest lazily, which allocates for every split.
+    #[cold]
+    fn sort_eagerly(&mut self) {
+        self.config.eager_in = None;
+        let mut v = mem::replace(&mut self.inner, QuickSortInternal::Base(Vec::new())).into_vec();
+        let mut comparisons = 0;
+        // Sorted partitions are in descending order, so that the next element is at the end.
+        v.sort_unstable_by(|a, b| {
+            comparisons += 1;
+            b.cmp(a)
+        });
+        self.config.count(comparisons, 0);
+        self.inner = QuickSortInternal::Base(v);
+    }
+
+    /// Returns counts of the work the sorter has done so far.
+    #[cfg(feature = "instrument")]
+    pub fn stats(&self) -> &Stats {
+        &self.config.stats
+    }
+
+    /// Returns signs of pathological pivot choices in the sort so far.
+    #[cfg(feature = "diagnostics")]
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.config.diagnostics
+    }
+
+    /// Returns the number of bytes of heap memory held by the sorter's partitions, counting the
+    /// full capacity of their buffers but not any memory owned by the elements.
+    pub fn memory_usage(&self) -> usize {
+        self.inner.memory_usage()
+    }
+
+    /// Yields the remaining elements in sorted blocks of at least `min_len` elements, each lent as
+    /// a slice; see [`SortedBlocks`](struct.SortedBlocks.html).
+    pub fn sorted_blocks(self, min_len: usize) -> SortedBlocks<T> {
+        SortedBlocks::new(self, min_len)
+    }
+
+    /// Moves the next run of finalized elements into `out`: a sorted partition, or a partition of
+    /// at most `min_len` elements sorted outright, along with the pivot after it.
+    pub(crate) fn next_block_into(&mut self, min_len: usize, out: &mut Vec<T>) {
+        // Steps and checks are done one element at a time.
+        if self.config.on_step.is_some() || cfg!(feature = "verify") {
+            out.extend(self.by_ref().take(min_len.max(1)));
+        } else {
+            self.inner.next_block_into(&mut self.config, min_len, out);
+        }
+    }
+
+    /// Returns true if `x` is among the remaining elements, without yielding or sorting any.
+    ///
+    /// Only partitions whose pivots bound a range that could hold `x` are searched, so this takes
+    /// about `log2(n)` comparisons once the front of the sort has been partitioned, and a scan of
+    /// the unpartitioned elements otherwise.
+    pub fn contains(&self, x: &T) -> bool {
+        self.inner.contains(x)
+    }
+
+    /// Returns the smallest remaining element that isn't less than `x`, without yielding anything.
+    ///
+    /// Partitions are split only along the way to it, as by a selection, so the elements before
+    /// it stay unsorted; the elements after its partition are scanned without being sorted.
+    pub fn successor(&mut self, x: &T) -> Option<&T> {
+        self.inner.partition_to(x, &mut self.config);
+        self.inner.successor(x)
+    }
+
+    /// Returns the partitions holding the remaining elements, in the order they'll be yielded.
+    ///
+    /// The front partitions are the smallest and most deeply nested, and are sorted or close to
+    /// it; the back ones are large and unpartitioned, so yielding their elements takes the most
+    /// work.
+    pub fn partitions(&self) -> impl Iterator<Item = PartitionInfo<'_, T>> {
+        let mut partitions = Vec::new();
+        let (mut inner, mut depth) = (&self.inner, 0);
+        loop {
+            match *inner {
+                QuickSortInternal::Base(ref v) => {
+                    partitions.push(PartitionInfo {
+                        depth,
+                        len: v.len(),
+                        pivot: None,
+                        sorted: true,
+                    });
+                    break;
+                }
+                QuickSortInternal::Recursive(ref r) => {
+                    partitions.push(PartitionInfo {
+                        depth,
+                        len: r.greater.len(),
+                        pivot: r.greater.last().filter(|_| r.less.is_some() || r.equal > 0),
+                        sorted: false,
+                    });
+                    match r.less {
+                        Some(ref less) => inner = less,
+                        None => break,
+                    }
+                    depth += 1;
+                }
+            }
+        }
+        partitions.into_iter().rev().filter(|partition| partition.len > 0)
+    }
+
+    /// Does one partition of the work the next call to `next` would do, returning the number of
+    /// elements partitioned, or zero if the next element is ready.
+    #[cfg(feature = "futures")]
+    pub(crate) fn step(&mut self) -> usize {
+        self.inner.step(&mut self.config)
+    }
 }
 
 impl<T: Ord> Iterator for QuickSort<T> {
     type Item = T;
 
     fn next(&mut self) -> Option<T> {
-        self.inner.next()
+        let next = self.inner.next(&mut self.config);
+        if next.is_some() {
+            self.emitted();
+            self.consumed(1);
+        }
+        // Sorting up to the following element now does work the next call would do anyway.
+        #[cfg(feature = "verify")]
+        if let Some(ref el) = next {
+            let remaining = self.inner.len();
+            if let Some(following) = self.inner.peek(&mut self.config) {
+                verify::check_emitted(el, following, remaining);
+            }
+        }
+        next
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.inner.size_hint()
+        let len = self.inner.len();
+        (len, Some(len))
     }
 }
 
+impl<T: Ord> FusedIterator for QuickSort<T> {}
+
+impl<T: Ord> LazySorted<T> for QuickSort<T> {
+    fn peek(&mut self) -> Option<&T> {
+        self.inner.peek(&mut self.config)
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn into_inner(self) -> Vec<T> {
+        self.inner.into_vec()
+    }
+
+    fn advance_to(&mut self, x: &T) {
+        self.inner.advance_to(x, &mut self.config)
+    }
+
+    fn next_chunk_into(&mut self, n: usize, out: &mut Vec<T>) {
+        // Steps and checks are done one element at a time.
+        if self.config.on_step.is_some() || cfg!(feature = "verify") {
+            out.extend(self.by_ref().take(n));
+        } else {
+            let before = out.len();
+            self.inner.next_into(&mut self.config, n, out);
+            self.consumed(out.len() - before);
+        }
+    }
+
+    fn drain_equal<F>(&mut self, x: &T, mut f: F)
+        where F: FnMut(T)
+    {
+        // Steps and checks are done one element at a time.
+        if self.config.on_step.is_some() || cfg!(feature = "verify") {
+            while self.peek().is_some_and(|el| el == x) {
+                f(self.next().unwrap());
+            }
+        } else {
+            let mut drained = 0;
+            self.inner.drain_equal(x, &mut self.config, &mut |el| {
+                drained += 1;
+                f(el)
+            });
+            self.consumed(drained);
+        }
+    }
+
+    fn split_at_rank(self, k: usize) -> (QuickSort<T>, QuickSort<T>) {
+        let (smallest, rest) = split_at_rank(self.inner.into_vec(), k);
+        (QuickSort::with_config(smallest, self.config.clone()),
+         QuickSort::with_config(rest, self.config))
+    }
+
+    fn modify_remaining<F>(&mut self, f: F)
+        where F: FnOnce(&mut [T])
+    {
+        let mut v = mem::replace(&mut self.inner, QuickSortInternal::Base(Vec::new())).into_vec();
+        f(&mut v);
+        self.inner = QuickSortInternal::new(v, &mut self.config);
+    }
+}
+
+/// A partition of a quicksort's remaining elements, created by
+/// [`QuickSort::partitions`](struct.QuickSort.html#method.partitions).
+#[derive(Debug, PartialEq, Eq)]
+pub struct PartitionInfo<'a, T: 'a> {
+    /// How many partitions this one is nested in.
+    pub depth: usize,
+    /// The number of elements in the partition.
+    pub len: usize,
+    /// The pivot the partition was split around, which is its smallest element, or `None` if it
+    /// hasn't been split yet. The elements less than the pivot are in the partitions before it.
+    pub pivot: Option<&'a T>,
+    /// Whether the partition is fully sorted, so that yielding its elements takes no comparisons.
+    pub sorted: bool,
+}
+
+impl<'a, T> Clone for PartitionInfo<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for PartitionInfo<'a, T> {}
+
+/// The tuning parameters shared by every partition of a quicksort.
+#[derive(Debug, Clone)]
+struct Config {
+    /// Partitions of at most this many elements are sorted eagerly with insertion sort.
+    threshold: usize,
+    /// Times the first large partitions to choose `threshold`, if it's adaptive.
+    #[cfg(feature = "std")]
+    adaptive: Option<AdaptiveThreshold>,
+    pivot: PivotStrategy,
+    rng: XorShift,
+    /// The fraction of the input after which the rest is sorted eagerly, if any.
+    crossover: Option<f64>,
+    /// How many more elements are yielded before the rest is sorted eagerly.
+    eager_in: Option<usize>,
+    /// Whether buffers give back their spare capacity as their elements are consumed.
+    release: bool,
+    /// Whether the middle and median-of-three strategies pick their candidates at random, near
+    /// where they'd otherwise be.
+    perturb: bool,
+    /// Checked while partitioning, which is abandoned once it's cancelled.
+    cancel: Option<CancelToken>,
+    /// Called with each step of the sort, which is then partitioned one comparison at a time.
+    on_step: Option<StepFn>,
+    /// Whether partitions too large for the cache prefetch ahead of their scans.
+    #[cfg(feature = "prefetch")]
+    prefetch: bool,
+    /// A log the pivots are recorded in or replayed from.
+    #[cfg(feature = "std")]
+    decisions: Option<Decisions>,
+    #[cfg(feature = "instrument")]
+    stats: Stats,
+    #[cfg(feature = "diagnostics")]
+    diagnostics: Diagnostics,
+    /// The depth beyond which partitions count as fallback triggers.
+    #[cfg(feature = "diagnostics")]
+    depth_limit: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            threshold: 32,
+            #[cfg(feature = "std")]
+            adaptive: None,
+            pivot: PivotStrategy::Middle,
+            rng: XorShift::new(rng::process_seed()),
+            crossover: Some(0.5),
+            eager_in: None,
+            release: false,
+            perturb: cfg!(feature = "std"),
+            cancel: None,
+            on_step: None,
+            #[cfg(feature = "prefetch")]
+            prefetch: false,
+            #[cfg(feature = "std")]
+            decisions: None,
+            #[cfg(feature = "instrument")]
+            stats: Stats::default(),
+            #[cfg(feature = "diagnostics")]
+            diagnostics: Diagnostics::default(),
+            #[cfg(feature = "diagnostics")]
+            depth_limit: 0,
+        }
+    }
+}
+
+/// Buffers with less capacity than this many elements keep it.
+const RELEASE_MIN_CAPACITY: usize = 64;
+
+/// How many elements are partitioned between checks of a cancellation token.
+const CANCEL_CHECK_INTERVAL: usize = 1 << 10;
+
+impl Config {
+    /// Sets up a sort of `len` elements: the crossover countdown and, with diagnostics, the depth
+    /// beyond which partitions count as fallback triggers.
+    fn start(&mut self, len: usize) {
+        #[cfg(feature = "diagnostics")]
+        {
+            self.depth_limit = Diagnostics::depth_limit(len);
+        }
+        self.start_crossover(len);
+    }
+
+    /// Records a split of a partition of `len` elements at `depth`, pivot included, that left
+    /// `greater` elements after the pivot and `less` before it, if diagnostics are collected.
+    #[inline]
+    fn record_split(&mut self, depth: usize, len: usize, greater: usize, less: usize) {
+        #[cfg(feature = "diagnostics")]
+        {
+            let depth_limit = self.depth_limit;
+            self.diagnostics.record_split(depth_limit, depth, len, greater, less);
+        }
+        #[cfg(not(feature = "diagnostics"))]
+        let _ = (depth, len, greater, less);
+    }
+
+    /// Starts counting down to the crossover to eager sorting for an input of `len` elements.
+    /// Observed sorts are left lazy so that every step is reported, as are cancellable ones, since
+    /// the eager sort can't be cancelled.
+    fn start_crossover(&mut self, len: usize) {
+        let lazy = self.on_step.is_some() || self.cancel.is_some();
+        self.eager_in = self.crossover.filter(|_| !lazy).map(|fraction| {
+            (len as f64 * fraction.clamp(0.0, 1.0)) as usize
+        });
+    }
+
+    /// Frees the spare capacity of `v` once three quarters of it is unused, if buffers are
+    /// released as they're consumed. Waiting until then copies each element at most a constant
+    /// number of times.
+    #[inline]
+    fn release<T>(&self, v: &mut Vec<T>) {
+        if self.release && v.capacity() >= RELEASE_MIN_CAPACITY && v.len() <= v.capacity() / 4 {
+            v.shrink_to_fit();
+        }
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Runs `partition`, which partitions `len` elements, timing it if the threshold is adaptive
+    /// and hasn't been chosen yet.
+    #[inline]
+    fn time_partition<F, R>(&mut self, len: usize, partition: F) -> R
+        where F: FnOnce() -> R
+    {
+        #[cfg(feature = "std")]
+        if let Some(ref mut adaptive) = self.adaptive {
+            let (split, threshold) = adaptive.measure(len, partition);
+            if let Some(threshold) = threshold {
+                self.threshold = threshold;
+            }
+            return split;
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = len;
+        partition()
+    }
+
+    /// Reports a step of the sort, if there's a callback for them.
+    #[inline]
+    fn step(&self, step: Step) {
+        if let Some(ref on_step) = self.on_step {
+            (on_step.0)(step);
+        }
+    }
+
+    /// Reports a swap of the elements at `a` and `b` of the partition at `depth`, unless they're
+    /// the same element.
+    #[inline]
+    fn swapped(&self, depth: usize, a: usize, b: usize) {
+        if a != b {
+            self.step(Step::Swap { depth, a, b });
+        }
+    }
+
+    /// Adds to the sort's statistics, if they're being counted.
+    #[inline]
+    fn count(&mut self, comparisons: usize, moves: usize) {
+        #[cfg(feature = "instrument")]
+        {
+            self.stats.comparisons += comparisons as u64;
+            self.stats.moves += moves as u64;
+        }
+        #[cfg(not(feature = "instrument"))]
+        let _ = (comparisons, moves);
+    }
+
+    /// Records a partition of `len` elements, which made `allocations` allocations, if statistics
+    /// are being counted.
+    #[inline]
+    fn count_partition(&mut self, len: usize, allocations: usize) {
+        #[cfg(feature = "instrument")]
+        {
+            self.stats.allocations += allocations as u64;
+            self.stats.partition_sizes.push(len);
+        }
+        #[cfg(not(feature = "instrument"))]
+        let _ = (len, allocations);
+    }
+
+    /// Returns the most comparisons `choose_pivot` makes.
+    fn pivot_comparisons(&self) -> usize {
+        match self.pivot {
+            PivotStrategy::Middle | PivotStrategy::Random => 0,
+            PivotStrategy::MedianOfThree => 4,
+        }
+    }
+
+    /// Returns the index of the pivot to partition `v` around. If `v` starts at `offset` in the
+    /// partition at `depth`, given as `Some((depth, offset))`, the comparisons are reported as its
+    /// steps.
+    fn choose_pivot<T: Ord>(&mut self, v: &[T], observed: Option<(usize, usize)>) -> usize {
+        #[cfg(feature = "std")]
+        if let Some(Decisions::Replay(ref log)) = self.decisions {
+            return log.replay(v.len());
+        }
+        let pivot = match self.pivot {
+            // A random element of the middle half still splits sorted input evenly.
+            PivotStrategy::Middle if self.perturb => {
+                let quarter = v.len() / 4;
+                quarter + self.rng.gen_index(v.len() - 2 * quarter)
+            }
+            PivotStrategy::Middle => v.len() / 2,
+            PivotStrategy::Random => self.rng.gen_index(v.len()),
+            PivotStrategy::MedianOfThree => {
+                let (a, b, c) = if self.perturb {
+                    // One candidate from each third.
+                    let third = v.len() / 3;
+                    (self.rng.gen_index(third.max(1)),
+                     third + self.rng.gen_index(v.len() - 2 * third),
+                     v.len() - 1 - self.rng.gen_index(third.max(1)))
+                } else {
+                    (0, v.len() / 2, v.len() - 1)
+                };
+                let mut le = |i: usize, j: usize| {
+                    self.count(1, 0);
+                    if let Some((depth, offset)) = observed {
+                        self.step(Step::Compare { depth, a: offset + i, b: offset + j });
+                    }
+                    v[i] <= v[j]
+                };
+                if le(a, b) == le(b, c) {
+                    b
+                } else if le(b, a) == le(a, c) {
+                    a
+                } else {
+                    c
+                }
+            }
+        };
+        #[cfg(feature = "std")]
+        if let Some(Decisions::Record(ref log)) = self.decisions {
+            log.record(pivot);
+        }
+        pivot
+    }
+}
+
+/// The number of elements a pipelined quicksort collects to choose its first pivot from.
+const PIPELINE_SAMPLE: usize = 31;
+
 #[derive(Debug, Clone)]
 enum QuickSortInternal<T> {
     Base(Vec<T>),
     Recursive(Recursive<T>),
 }
 
-impl<T: Ord> QuickSortInternal<T> {
-    fn new(mut v: Vec<T>) -> QuickSortInternal<T> {
-        if v.len() <= 32 {
-            insertion_sort(&mut v, |a, b| b.cmp(a));
-            QuickSortInternal::Base(v)
-        } else {
-            QuickSortInternal::Recursive(Recursive::new(v))
+impl<T: Ord> QuickSortInternal<T> {
+    fn new(v: Vec<T>, config: &mut Config) -> QuickSortInternal<T> {
+        QuickSortInternal::at_depth(v, config, 0)
+    }
+
+    /// Returns the partition at `depth` holding the elements of `v`.
+    fn at_depth(mut v: Vec<T>, config: &mut Config, depth: usize) -> QuickSortInternal<T> {
+        if v.len() > config.threshold {
+            QuickSortInternal::Recursive(Recursive::new(v, depth))
+        } else if config.on_step.is_some() {
+            observed_insertion_sort(&mut v, config, depth);
+            QuickSortInternal::Base(v)
+        } else {
+            let mut comparisons = 0;
+            let moves = insertion_sort(&mut v, |a, b| {
+                comparisons += 1;
+                b.cmp(a)
+            });
+            config.count(comparisons, moves);
+            QuickSortInternal::Base(v)
+        }
+    }
+
+    /// Consumes the sorter, returning the remaining elements in no particular order.
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            QuickSortInternal::Base(v) => v,
+            QuickSortInternal::Recursive(r) => r.into_vec(),
+        }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        match *self {
+            QuickSortInternal::Base(ref v) => v.len(),
+            QuickSortInternal::Recursive(ref r) => r.len(),
+        }
+    }
+
+    /// Returns true if there are no remaining elements, without counting them.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        match *self {
+            QuickSortInternal::Base(ref v) => v.is_empty(),
+            // Unless the partition is exhausted, its pivot is in `greater`.
+            QuickSortInternal::Recursive(ref r) => {
+                r.greater.is_empty() && r.less.as_ref().is_none_or(|less| less.is_empty())
+            }
+        }
+    }
+
+    fn contains(&self, x: &T) -> bool {
+        let mut inner = self;
+        loop {
+            let r = match *inner {
+                // Sorted partitions are in descending order, so that the smallest can be popped.
+                QuickSortInternal::Base(ref v) => return v.binary_search_by(|el| x.cmp(el)).is_ok(),
+                QuickSortInternal::Recursive(ref r) => r,
+            };
+            match (r.less.as_ref(), r.greater.split_last()) {
+                // Elements greater than the pivot are before it, and the rest are in `less`.
+                (Some(less), Some((pivot, greater))) => match x.cmp(pivot) {
+                    Ordering::Equal => return true,
+                    Ordering::Greater => return greater.contains(x),
+                    Less => inner = less,
+                },
+                (Some(less), None) => inner = less,
+                (None, _) => return r.greater.contains(x),
+            }
+        }
+    }
+
+    /// Splits the partitions holding the elements that aren't less than `x`, from the front, up to
+    /// the first one whose pivot is less than `x`.
+    fn partition_to(&mut self, x: &T, config: &mut Config) {
+        if let QuickSortInternal::Recursive(ref mut r) = *self {
+            r.partition_to(x, config);
+        }
+    }
+
+    /// Returns the smallest element that isn't less than `x`, scanning any unpartitioned elements
+    /// that might be it.
+    fn successor(&self, x: &T) -> Option<&T> {
+        let r = match *self {
+            QuickSortInternal::Base(ref v) => {
+                let split = v.partition_point(|el| el >= x);
+                return split.checked_sub(1).map(|i| &v[i]);
+            }
+            QuickSortInternal::Recursive(ref r) => r,
+        };
+        fn smallest_at_least<'a, T: Ord>(v: &'a [T], x: &T) -> Option<&'a T> {
+            v.iter().filter(|&el| el >= x).min()
+        }
+        match (r.less.as_ref(), r.greater.split_last()) {
+            (Some(less), Some((pivot, _))) if pivot >= x => less.successor(x).or(Some(pivot)),
+            (Some(_), Some((_, greater))) => smallest_at_least(greater, x),
+            (Some(less), None) => less.successor(x),
+            (None, _) => smallest_at_least(&r.greater, x),
+        }
+    }
+
+    fn memory_usage(&self) -> usize {
+        match *self {
+            QuickSortInternal::Base(ref v) => v.capacity() * mem::size_of::<T>(),
+            QuickSortInternal::Recursive(ref r) => {
+                let less = r.less.as_ref().map_or(0, |less| {
+                    mem::size_of::<QuickSortInternal<T>>() + less.memory_usage()
+                });
+                r.greater.capacity() * mem::size_of::<T>() + less
+            }
+        }
+    }
+
+    fn next(&mut self, config: &mut Config) -> Option<T> {
+        match *self {
+            QuickSortInternal::Base(ref mut v) => {
+                let next = v.pop();
+                config.release(v);
+                next
+            }
+            QuickSortInternal::Recursive(ref mut r) => r.next(config),
+        }
+    }
+
+    /// Moves up to `n` of the next elements into `out`, descending to the smallest partition
+    /// once for a run of them rather than once per element.
+    fn next_into(&mut self, config: &mut Config, n: usize, out: &mut Vec<T>) {
+        match *self {
+            QuickSortInternal::Base(ref mut v) => {
+                let start = v.len().saturating_sub(n);
+                out.extend(v.drain(start..).rev());
+                config.release(v);
+            }
+            QuickSortInternal::Recursive(ref mut r) => r.next_into(config, n, out),
+        }
+    }
+
+    fn next_block_into(&mut self, config: &mut Config, min_len: usize, out: &mut Vec<T>) {
+        match *self {
+            QuickSortInternal::Base(ref mut v) => {
+                out.extend(v.drain(..).rev());
+                config.release(v);
+            }
+            QuickSortInternal::Recursive(ref mut r) => r.next_block_into(config, min_len, out),
+        }
+    }
+
+    fn peek(&mut self, config: &mut Config) -> Option<&T> {
+        match *self {
+            QuickSortInternal::Base(ref v) => v.last(),
+            QuickSortInternal::Recursive(ref mut r) => r.peek(config),
+        }
+    }
+
+    fn try_next(&mut self, config: &mut Config, budget: &mut usize) -> ControlFlow<Option<T>> {
+        match *self {
+            QuickSortInternal::Base(ref mut v) => {
+                let next = v.pop();
+                config.release(v);
+                ControlFlow::Break(next)
+            }
+            QuickSortInternal::Recursive(ref mut r) => r.try_next(config, budget),
+        }
+    }
+
+    #[cfg(feature = "futures")]
+    fn step(&mut self, config: &mut Config) -> usize {
+        match *self {
+            QuickSortInternal::Base(_) => 0,
+            QuickSortInternal::Recursive(ref mut r) => match r.less {
+                Some(ref mut less) => less.step(config),
+                None if r.greater.len() > 1 && r.equal == 0 => {
+                    let len = r.greater.len();
+                    if r.split_greater(config, None) { len } else { 0 }
+                }
+                None => 0,
+            },
+        }
+    }
+
+    /// Removes the next elements while they're equal to `x`, returning true if the partition runs
+    /// out before one that isn't.
+    fn drain_equal<F>(&mut self, x: &T, config: &mut Config, f: &mut F) -> bool
+        where F: FnMut(T)
+    {
+        match *self {
+            QuickSortInternal::Base(ref mut v) => {
+                // Sorted partitions are in descending order, so the elements equal to `x` are at
+                // the end.
+                if v.last().is_some_and(|el| el == x) {
+                    let mut comparisons = 1;
+                    let start = v.partition_point(|el| {
+                        comparisons += 1;
+                        el > x
+                    });
+                    config.count(comparisons, 0);
+                    v.drain(start..).rev().for_each(&mut *f);
+                    config.release(v);
+                } else {
+                    config.count(!v.is_empty() as usize, 0);
+                }
+                v.is_empty()
+            }
+            QuickSortInternal::Recursive(ref mut r) => r.drain_equal(x, config, f),
+        }
+    }
+
+    fn advance_to(&mut self, x: &T, config: &mut Config) {
+        match *self {
+            QuickSortInternal::Base(ref mut v) => {
+                while v.last().is_some_and(|el| el < x) {
+                    v.pop();
+                }
+                config.release(v);
+            }
+            QuickSortInternal::Recursive(ref mut r) => r.advance_to(x, config),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Recursive<T> {
+    greater: Vec<T>,
+    less: Option<Box<QuickSortInternal<T>>>,
+    /// How many of the elements before the pivot at the end of `greater` are equal to it. They're
+    /// yielded after it without being partitioned again.
+    equal: usize,
+    /// The progress of a suspended partition of `greater`, whose pivot is its last element.
+    scan: Option<Scan>,
+    /// How many partitions this one is nested in, reported by steps and tracing events.
+    depth: usize,
+}
+
+/// How far a partition of `greater` around the pivot at its end has got.
+#[derive(Clone, Debug)]
+enum Scan {
+    /// Moving the elements greater than the pivot to the front.
+    Greater(Pass),
+    /// Moving the elements equal to the pivot after the greater ones, since too few elements were
+    /// greater than it to split the partition evenly.
+    Equal(Pass),
+    /// Selecting the median to split around instead, since too few elements weren't less than
+    /// the pivot either. The median belongs in `greater[lo..hi]`, which is partitioned around a
+    /// pivot of its own by `pass` until it's narrowed down to the median alone.
+    Median {
+        lo: usize,
+        hi: usize,
+        pass: Option<Pass>,
+    },
+}
+
+/// A suspended scan of `greater[start..end]` that moves the elements greater than, or if `equal`
+/// equal to, the element at `pivot` to the front: `greater[start..next]` has been scanned, with
+/// those elements in `greater[start..split]`.
+#[derive(Clone, Debug)]
+struct Pass {
+    start: usize,
+    next: usize,
+    split: usize,
+    end: usize,
+    pivot: usize,
+    equal: bool,
+}
+
+impl Pass {
+    fn new(start: usize, end: usize, pivot: usize, equal: bool) -> Pass {
+        Pass {
+            start,
+            next: start,
+            split: start,
+            end,
+            pivot,
+            equal,
         }
     }
 }
 
-impl<T: Ord> Iterator for QuickSortInternal<T> {
-    type Item = T;
+/// Deducts `cost` from `budget`, if there is one, returning false if it doesn't cover it.
+fn charge(budget: &mut Option<&mut usize>, cost: usize) -> bool {
+    match *budget {
+        Some(ref mut budget) if **budget < cost => false,
+        Some(ref mut budget) => {
+            **budget -= cost;
+            true
+        }
+        None => true,
+    }
+}
 
-    fn next(&mut self) -> Option<T> {
-        match *self {
-            QuickSortInternal::Base(ref mut v) => v.pop(),
-            QuickSortInternal::Recursive(ref mut r) => r.next(),
+impl<T: Ord> Recursive<T> {
+    fn new(v: Vec<T>, depth: usize) -> Recursive<T> {
+        Recursive::with_less(v, None, depth)
+    }
+
+    fn with_less(greater: Vec<T>, less: Option<Box<QuickSortInternal<T>>>, depth: usize)
+        -> Recursive<T>
+    {
+        Recursive {
+            greater,
+            less,
+            equal: 0,
+            scan: None,
+            depth,
         }
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        match *self {
-            QuickSortInternal::Base(ref v) => (v.len(), Some(v.len())),
-            QuickSortInternal::Recursive(ref r) => r.size_hint(),
+    fn into_vec(self) -> Vec<T> {
+        match self.less {
+            Some(less) => {
+                let mut v = less.into_vec();
+                v.extend(self.greater);
+                v
+            }
+            None => self.greater,
         }
     }
-}
 
-#[derive(Clone, Debug)]
-struct Recursive<T> {
-    greater: Vec<T>,
-    less: Option<Box<QuickSortInternal<T>>>,
-}
+    #[inline]
+    fn len(&self) -> usize {
+        self.greater.len() + self.less.as_ref().map_or(0, |less| less.len())
+    }
 
-impl<T: Ord> Recursive<T> {
-    fn new(v: Vec<T>) -> Recursive<T> {
-        Recursive {
-            greater: v,
-            less: None,
-        }
-    }
-
-    fn split_greater(&mut self) -> Option<T> {
-        match self.greater.len() {
-            0 => None,
-            1 => self.greater.pop(),
-            _ => {
-                let pivot_idx = self.greater.len() - 1;
-                let split_idx = {
-                    let mid_idx = self.greater.len() / 2;
-                    // I've chosen the element in the middle of the vec as the pivot.
-                    // However, we first swap the pivot with the last element so that there is
-                    // a contiguous space in memory to be partitioned.
-                    self.greater.swap(pivot_idx, mid_idx);
-                    let (pivot, rest) = self.greater.split_last_mut().unwrap();
+    /// Removes the next element from the end of `greater`, once the elements less than it have
+    /// run out.
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        if self.less.take().is_none() {
+            // Without a nested partition, the element is one of those equal to the last pivot.
+            self.equal = self.equal.saturating_sub(1);
+        }
+        self.greater.pop()
+    }
+
+    /// Partitions `greater` around a pivot, moving the elements less than the pivot into `less`.
+    /// Afterwards, the pivot is the last element of `greater`, preceded by the `equal` elements
+    /// equal to it.
+    ///
+    /// With a `budget`, at most that many comparisons are made and deducted from it. Returns false
+    /// if the budget runs out or the sort is cancelled first, leaving `less` as `None`; the next
+    /// call resumes the partition where this one left off.
+    fn split_greater(&mut self, config: &mut Config, mut budget: Option<&mut usize>) -> bool {
+        if self.greater.len() <= 1 || self.equal > 0 {
+            return true;
+        }
+        let len = self.greater.len();
+        let pivot_idx = len - 1;
+        if self.scan.is_none() {
+            // Instrumented and observed sorts take the slow path, which counts and reports its
+            // comparisons and swaps.
+            let observed = config.on_step.is_some() || cfg!(feature = "instrument");
+            if budget.is_none() && config.cancel.is_none() && !observed {
+                // By default, the element in the middle of the vec is chosen as the pivot.
+                // However, we first swap the pivot with the last element so that there is a
+                // contiguous space in memory to be partitioned.
+                let chosen_idx = config.choose_pivot(&self.greater, None);
+                self.greater.swap(pivot_idx, chosen_idx);
+                #[cfg(feature = "prefetch")]
+                let prefetch = config.prefetch;
+                let greater = &mut self.greater;
+                let (split_idx, equal) = config.time_partition(pivot_idx, move || {
+                    let (pivot, rest) = greater.split_last_mut().unwrap();
                     // partition all but the last element, which is the pivot. This makes the vec
                     // look like [greater, greater, ..., greater, less, less, ..., less, pivot]
-                    partition(rest, |el| el > pivot)
-                };
-                // Swapping the pivot with the first less element allows us to split off
-                // vec[split_idx + 1..] to create a new vec with all the elements less than pivot.
-                self.greater.swap(pivot_idx, split_idx);
-                let split_off_idx = split_idx + 1;
-                if split_off_idx < self.greater.len() {
-                    let mut less = Box::new(QuickSortInternal::new(self.greater
-                                                                      .split_off(split_off_idx)));
-                    // Recursively compute the next element from the QuickSortInternal struct
-                    // containing the elements less than the pivot.
-                    let next = less.next();
-                    self.less = Some(less);
-                    next
-                } else {
-                    // If there were no elements less than the pivot, then return the pivot.
-                    self.greater.pop()
+                    let split_idx = {
+                        #[cfg(feature = "prefetch")]
+                        if prefetch && prefetch::worthwhile(rest) {
+                            prefetch::partition(&mut *rest, |el| el > pivot)
+                        } else {
+                            partition(&mut *rest, |el| el > pivot)
+                        }
+                        #[cfg(not(feature = "prefetch"))]
+                        partition(&mut *rest, |el| el > pivot)
+                    };
+                    // Too few elements were greater than the pivot, so the ones equal to it are
+                    // gathered after them.
+                    let equal = if unbalanced(rest.len() + 1, rest.len() - split_idx) {
+                        partition(&mut rest[split_idx..], |el| el >= pivot)
+                    } else {
+                        0
+                    };
+                    (split_idx, equal)
+                });
+                if !unbalanced(len, pivot_idx - split_idx - equal) {
+                    self.finish_split(split_idx, equal, false, config, false);
+                    return true;
+                }
+                self.scan = Some(Scan::Median {
+                    lo: 0,
+                    hi: len,
+                    pass: None,
+                });
+            } else {
+                if !charge(&mut budget, config.pivot_comparisons()) {
+                    return false;
                 }
+                let chosen_idx = config.choose_pivot(&self.greater, Some((self.depth, 0)));
+                self.greater.swap(pivot_idx, chosen_idx);
+                config.count(0, 2);
+                config.swapped(self.depth, pivot_idx, chosen_idx);
+                self.scan = Some(Scan::Greater(Pass::new(0, pivot_idx, pivot_idx, false)));
             }
         }
+        // The same partition as above, one comparison at a time so that it can be suspended.
+        // With a budget, small partitions aren't insertion sorted up front, since that can't be
+        // suspended.
+        let budgeted = budget.is_some();
+        let mid = len / 2;
+        let mut scan = self.scan.take().unwrap();
+        loop {
+            scan = match scan {
+                Scan::Greater(mut pass) => {
+                    if !self.resume(&mut pass, config, &mut budget) {
+                        self.scan = Some(Scan::Greater(pass));
+                        return false;
+                    }
+                    if !unbalanced(len, pivot_idx - pass.split) {
+                        self.finish_split(pass.split, 0, false, config, budgeted);
+                        return true;
+                    }
+                    Scan::Equal(Pass::new(pass.split, pivot_idx, pivot_idx, true))
+                }
+                Scan::Equal(mut pass) => {
+                    if !self.resume(&mut pass, config, &mut budget) {
+                        self.scan = Some(Scan::Equal(pass));
+                        return false;
+                    }
+                    if !unbalanced(len, pivot_idx - pass.split) {
+                        self.finish_split(pass.start, pass.split - pass.start, false, config,
+                                          budgeted);
+                        return true;
+                    }
+                    Scan::Median {
+                        lo: 0,
+                        hi: len,
+                        pass: None,
+                    }
+                }
+                Scan::Median { lo, hi, pass: None } if hi - lo <= 1 => {
+                    // Everything before the median is at least as great as it, and everything
+                    // after it at most as great.
+                    self.greater.swap(mid, pivot_idx);
+                    config.count(0, 2);
+                    config.swapped(self.depth, mid, pivot_idx);
+                    self.finish_split(mid, 0, true, config, budgeted);
+                    return true;
+                }
+                Scan::Median { lo, hi, pass: None } => {
+                    if !charge(&mut budget, config.pivot_comparisons()) {
+                        self.scan = Some(Scan::Median { lo, hi, pass: None });
+                        return false;
+                    }
+                    let window = &self.greater[lo..hi];
+                    let chosen_idx = lo + config.choose_pivot(window, Some((self.depth, lo)));
+                    self.greater.swap(hi - 1, chosen_idx);
+                    config.count(0, 2);
+                    config.swapped(self.depth, hi - 1, chosen_idx);
+                    Scan::Median {
+                        lo,
+                        hi,
+                        pass: Some(Pass::new(lo, hi - 1, hi - 1, false)),
+                    }
+                }
+                Scan::Median { lo, hi, pass: Some(mut pass) } => {
+                    if !self.resume(&mut pass, config, &mut budget) {
+                        self.scan = Some(Scan::Median { lo, hi, pass: Some(pass) });
+                        return false;
+                    }
+                    if pass.equal {
+                        // `greater[pass.pivot..pass.split]` are all equal to the window's pivot.
+                        if mid < pass.split {
+                            Scan::Median { lo: mid, hi: mid + 1, pass: None }
+                        } else {
+                            Scan::Median { lo: pass.split, hi, pass: None }
+                        }
+                    } else {
+                        // The window's pivot goes right after the elements greater than it.
+                        let pivot = pass.split;
+                        self.greater.swap(hi - 1, pivot);
+                        config.count(0, 2);
+                        config.swapped(self.depth, hi - 1, pivot);
+                        match mid.cmp(&pivot) {
+                            Ordering::Less => Scan::Median { lo, hi: pivot, pass: None },
+                            Ordering::Equal => Scan::Median { lo: mid, hi: mid + 1, pass: None },
+                            Ordering::Greater => Scan::Median {
+                                lo,
+                                hi,
+                                pass: Some(Pass::new(pivot + 1, hi, pivot, true)),
+                            },
+                        }
+                    }
+                }
+            };
+        }
     }
-}
 
-impl<T: Ord> Iterator for Recursive<T> {
-    type Item = T;
+    /// Continues `pass`, returning false if the budget runs out or the sort is cancelled before
+    /// it's done.
+    fn resume(&mut self,
+              pass: &mut Pass,
+              config: &mut Config,
+              budget: &mut Option<&mut usize>)
+              -> bool {
+        while pass.next < pass.end {
+            let out_of_budget = budget.as_ref().is_some_and(|budget| **budget == 0);
+            let check_cancel = pass.next.is_multiple_of(CANCEL_CHECK_INTERVAL);
+            if out_of_budget || (check_cancel && config.cancelled()) {
+                return false;
+            }
+            if let Some(ref mut budget) = *budget {
+                **budget -= 1;
+            }
+            config.count(1, 0);
+            config.step(Step::Compare {
+                depth: self.depth,
+                a: pass.next,
+                b: pass.pivot,
+            });
+            let (el, pivot) = (&self.greater[pass.next], &self.greater[pass.pivot]);
+            if if pass.equal { el >= pivot } else { el > pivot } {
+                if pass.split != pass.next {
+                    self.greater.swap(pass.split, pass.next);
+                    config.count(0, 2);
+                    config.swapped(self.depth, pass.split, pass.next);
+                }
+                pass.split += 1;
+            }
+            pass.next += 1;
+        }
+        true
+    }
 
-    fn next(&mut self) -> Option<T> {
-        let next = if let Some(ref mut less) = self.less {
-            less.next()
+    /// Moves the elements of `greater` after the first `split_idx`, which are greater than the
+    /// pivot at its end, and the `equal` ones after them, into `less`. If the pivot is the
+    /// partition's median, elements equal to it may be on either side of it.
+    fn finish_split(&mut self,
+                    split_idx: usize,
+                    equal: usize,
+                    median: bool,
+                    config: &mut Config,
+                    budgeted: bool) {
+        let pivot_idx = self.greater.len() - 1;
+        let less_idx = split_idx + equal;
+        // Swapping the pivot with the first less element allows us to split off
+        // vec[less_idx + 1..] to create a new vec with all the elements less than pivot.
+        self.greater.swap(pivot_idx, less_idx);
+        config.swapped(self.depth, pivot_idx, less_idx);
+        let split_off_idx = less_idx + 1;
+        let less_len = self.greater.len() - split_off_idx;
+        #[cfg(feature = "verify")]
+        if split_idx > 0 && less_len > 0 && !median {
+            let greater = &self.greater;
+            verify::check_split(&greater[split_idx / 2],
+                                &greater[less_idx],
+                                &greater[split_off_idx + less_len / 2]);
+        }
+        #[cfg(not(feature = "verify"))]
+        let _ = median;
+        config.count(0, 2 + less_len);
+        config.count_partition(self.greater.len(), if less_len == 0 { 1 } else { 2 });
+        config.record_split(self.depth, self.greater.len(), less_idx, less_len);
+        // If there were no elements less than the pivot, then `less` is empty and the pivot
+        // is returned next.
+        #[cfg(feature = "tracing")]
+        ::tracing::trace!(target: "lazy_sort::quick_sort", len = self.greater.len(),
+                          less = less_len, depth = self.depth, "split partition");
+        config.step(Step::Partition {
+            depth: self.depth,
+            len: self.greater.len(),
+            less: less_len,
+        });
+        let depth = self.depth + 1;
+        let less = if split_off_idx == self.greater.len() {
+            QuickSortInternal::Base(Vec::new())
+        } else if budgeted {
+            let less = self.greater.split_off(split_off_idx);
+            QuickSortInternal::Recursive(Recursive::new(less, depth))
         } else {
-            return self.split_greater();
+            QuickSortInternal::at_depth(self.greater.split_off(split_off_idx), config, depth)
         };
+        config.release(&mut self.greater);
+        self.less = Some(Box::new(less));
+        self.equal = equal;
+    }
+
+    fn try_next(&mut self, config: &mut Config, budget: &mut usize) -> ControlFlow<Option<T>> {
+        if self.less.is_none() && !self.split_greater(config, Some(budget)) {
+            return ControlFlow::Continue(());
+        }
+        if let Some(ref mut less) = self.less {
+            match less.try_next(config, budget) {
+                ControlFlow::Break(None) => {}
+                next => return next,
+            }
+        }
+        ControlFlow::Break(self.pop())
+    }
+
+    fn next(&mut self, config: &mut Config) -> Option<T> {
+        if self.less.is_none() && !self.split_greater(config, None) {
+            return None;
+        }
+        // Recursively compute the next element from the QuickSortInternal struct
+        // containing the elements less than the pivot.
+        let next = self.less.as_mut().and_then(|less| less.next(config));
         if next.is_some() {
             next
         } else {
-            self.less = None;
             // The pivot is always the last element in the vec, and it's the first element
             // to be returned once all of the elements less than it have been returned.
-            self.greater.pop()
+            self.pop()
         }
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
+    fn next_into(&mut self, config: &mut Config, n: usize, out: &mut Vec<T>) {
+        let end = out.len() + n;
+        while out.len() < end {
+            if self.less.is_none() && !self.split_greater(config, None) {
+                return;
+            }
+            if let Some(ref mut less) = self.less {
+                less.next_into(config, end - out.len(), out);
+            }
+            if out.len() < end {
+                // The elements less than the pivot have run out, so it's next.
+                match self.pop() {
+                    Some(pivot) => out.push(pivot),
+                    None => return,
+                }
+            }
+        }
+    }
+
+    fn next_block_into(&mut self, config: &mut Config, min_len: usize, out: &mut Vec<T>) {
+        if self.less.is_none() && self.scan.is_none() && self.greater.len() <= min_len {
+            // Sorting a small partition outright is cheaper than partitioning it lazily.
+            let mut comparisons = 0;
+            self.greater.sort_unstable_by(|a, b| {
+                comparisons += 1;
+                a.cmp(b)
+            });
+            config.count(comparisons, 0);
+            out.append(&mut self.greater);
+            self.equal = 0;
+            return;
+        }
+        if self.less.is_none() && !self.split_greater(config, None) {
+            return;
+        }
+        if let Some(ref mut less) = self.less {
+            if !less.is_empty() {
+                less.next_block_into(config, min_len, out);
+                if !less.is_empty() {
+                    return;
+                }
+            }
+        }
+        out.extend(self.pop());
+    }
+
+    fn peek(&mut self, config: &mut Config) -> Option<&T> {
+        if self.less.is_none() && !self.split_greater(config, None) {
+            return None;
+        }
+        let less_is_empty = self.less.as_ref().is_none_or(|less| less.is_empty());
         match self.less {
-            None => (self.greater.len(), Some(self.greater.len())),
-            Some(ref less) => {
-                let (lower, upper) = less.size_hint();
-                (lower + self.greater.len(),
-                 upper.map(|upper| upper + self.greater.len()))
+            Some(ref mut less) if !less_is_empty => less.peek(config),
+            _ => self.greater.last(),
+        }
+    }
+
+    fn partition_to(&mut self, x: &T, config: &mut Config) {
+        if self.less.is_none() && !self.split_greater(config, None) {
+            return;
+        }
+        config.count(1, 0);
+        if self.greater.last().is_none_or(|pivot| pivot >= x) {
+            if let Some(ref mut less) = self.less {
+                less.partition_to(x, config);
+            }
+        }
+    }
+
+    fn drain_equal<F>(&mut self, x: &T, config: &mut Config, f: &mut F) -> bool
+        where F: FnMut(T)
+    {
+        loop {
+            if self.less.is_none() && !self.split_greater(config, None) {
+                return false;
+            }
+            if let Some(ref mut less) = self.less {
+                if !less.drain_equal(x, config, f) {
+                    return false;
+                }
+            }
+            match self.greater.last() {
+                None => return true,
+                Some(pivot) => {
+                    config.count(1, 0);
+                    if pivot != x {
+                        return false;
+                    }
+                }
+            }
+            // The elements set aside with the pivot are equal to it, so they're taken without
+            // comparing them.
+            f(self.pop().unwrap());
+            while self.equal > 0 {
+                f(self.pop().unwrap());
+            }
+        }
+    }
+
+    fn advance_to(&mut self, x: &T, config: &mut Config) {
+        loop {
+            if self.less.is_none() && !self.split_greater(config, None) {
+                return;
+            }
+            config.count(1, 0);
+            if self.greater.last().is_none_or(|pivot| pivot >= x) {
+                break;
             }
+            // Every element less than the pivot is also less than `x`, so the whole partition
+            // can be dropped without sorting it.
+            self.pop();
+        }
+        if let Some(ref mut less) = self.less {
+            less.advance_to(x, config);
         }
     }
 }
@@ -206,12 +2018,74 @@ fn test_size_hint() {
     }
 }
 
-// This is copied from libcollections/slice.rs
-fn insertion_sort<T, F>(v: &mut [T], mut compare: F)
+#[test]
+fn test_memory_usage() {
+    let mut sorted = (0..1000u64).quick_sort();
+    assert_eq!(sorted.memory_usage(), 8000);
+    sorted.next();
+    // Splitting a partition moves its smaller elements into a new buffer.
+    assert!(sorted.memory_usage() > 8000);
+    assert_eq!((0..1000u64).heap_sort().memory_usage(), 8000);
+    assert_eq!((0..1u64).pairing_heap_sort().memory_usage(), 0);
+    assert!((0..1000u64).pairing_heap_sort().memory_usage() >= 999 * mem::size_of::<u64>());
+}
+
+#[test]
+fn test_into_btree() {
+    let v = vec![2, 4, 2, 5, 8, 4, 3, 4, 6];
+    let mut sorted = v.iter().cloned().quick_sort();
+    sorted.next();
+    assert_eq!(sorted.into_btree_set().into_iter().collect::<Vec<_>>(), [2, 3, 4, 5, 6, 8]);
+    let pairs = v.iter().map(|&el| (el % 3, el)).heap_sort();
+    assert_eq!(pairs.into_btree_map().into_iter().collect::<Vec<_>>(), [(0, 6), (1, 4), (2, 8)]);
+}
+
+/// Returns whether splitting a partition of `len` elements, pivot included, leaves too many of
+/// them in the nested partition of `less` elements for the chain of partitions to stay
+/// logarithmically long.
+fn unbalanced(len: usize, less: usize) -> bool {
+    less * 8 > len * 7
+}
+
+/// Moves the elements for which `pred` is true to the front, returning how many there are.
+fn partition<'a, T: 'a, I, F>(iter: I, mut pred: F) -> usize
+    where I: IntoIterator<Item = &'a mut T>,
+          I::IntoIter: DoubleEndedIterator,
+          F: FnMut(&T) -> bool
+{
+    let mut split_idx = 0;
+    let mut iter = iter.into_iter();
+    while let Some(front) = iter.next() {
+        if !pred(front) {
+            match iter.rfind(|back| pred(back)) {
+                Some(back) => mem::swap(front, back),
+                None => break,
+            }
+        }
+        split_idx += 1;
+    }
+    split_idx
+}
+
+/// Splits `v` so that the first vec holds its `k` smallest elements and the second holds the rest.
+fn split_at_rank<T: Ord>(mut v: Vec<T>, k: usize) -> (Vec<T>, Vec<T>) {
+    if k >= v.len() {
+        return (v, Vec::new());
+    }
+    if k > 0 {
+        v.select_nth_unstable(k);
+    }
+    let rest = v.split_off(k);
+    (v, rest)
+}
+
+// This is copied from libcollections/slice.rs. Returns the number of element moves.
+fn insertion_sort<T, F>(v: &mut [T], mut compare: F) -> usize
     where F: FnMut(&T, &T) -> Ordering
 {
     let len = v.len() as isize;
     let buf_v = v.as_mut_ptr();
+    let mut moves = 0;
 
     // 1 <= i < len;
     for i in 1..len {
@@ -241,26 +2115,137 @@ fn insertion_sort<T, F>(v: &mut [T], mut compare: F)
                 ptr::copy(&*buf_v.offset(j), buf_v.offset(j + 1), (i - j) as usize);
                 ptr::copy_nonoverlapping(&tmp, buf_v.offset(j), 1);
                 mem::forget(tmp);
+                moves += (i - j) as usize + 1;
+            }
+        }
+    }
+    moves
+}
+
+/// Sorts `v` in descending order like `insertion_sort`, but by swapping adjacent elements, each
+/// comparison and swap of which is reported as a step of the partition at `depth`.
+fn observed_insertion_sort<T: Ord>(v: &mut [T], config: &mut Config, depth: usize) {
+    for i in 1..v.len() {
+        let mut j = i;
+        while j > 0 {
+            config.count(1, 0);
+            config.step(Step::Compare { depth, a: j - 1, b: j });
+            if v[j - 1] >= v[j] {
+                break;
             }
+            v.swap(j - 1, j);
+            config.count(0, 2);
+            config.swapped(depth, j - 1, j);
+            j -= 1;
         }
     }
 }
 
 use std::collections::BinaryHeap;
-/// An iterator that lazily sorts its input using quicksort.
-pub struct HeapSort<T>(BinaryHeap<ReverseOrder<T>>);
+/// An iterator that lazily sorts its input using heapsort.
+#[derive(Debug, Clone)]
+pub struct HeapSort<T> {
+    heap: BinaryHeap<ReverseOrder<T>>,
+    #[cfg(feature = "instrument")]
+    stats: Stats,
+}
+
+impl<T: Ord> HeapSort<T> {
+    /// Heapifies `v`.
+    fn from_reversed(v: Vec<ReverseOrder<T>>) -> HeapSort<T> {
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(target: "lazy_sort::heap_sort", len = v.len(), "building heap");
+        #[cfg(feature = "instrument")]
+        {
+            let mut stats = Stats {
+                allocations: (v.capacity() > 0) as u64,
+                ..Stats::default()
+            };
+            let heap = instrument::count_heap_comparisons(&mut stats, || BinaryHeap::from(v));
+            HeapSort { heap, stats }
+        }
+        #[cfg(not(feature = "instrument"))]
+        HeapSort { heap: BinaryHeap::from(v) }
+    }
 
-#[derive(Eq, PartialEq)]
+    /// Heapifies `v` like `from_reversed`, but checks `cancel` as it goes, returning `v` with its
+    /// elements in no particular order if it's cancelled first.
+    fn from_reversed_cancellable(mut v: Vec<ReverseOrder<T>>, cancel: &CancelToken)
+        -> Result<HeapSort<T>, Vec<ReverseOrder<T>>>
+    {
+        #[cfg(feature = "instrument")]
+        let mut stats = Stats::default();
+        #[cfg(feature = "instrument")]
+        let heapified = instrument::count_heap_comparisons(&mut stats, || heapify(&mut v, cancel));
+        #[cfg(not(feature = "instrument"))]
+        let heapified = heapify(&mut v, cancel);
+        if !heapified {
+            return Err(v);
+        }
+        // The heap is already in order, so `BinaryHeap::from` only checks each parent.
+        #[allow(unused_mut)]
+        let mut heap = HeapSort::from_reversed(v);
+        #[cfg(feature = "instrument")]
+        {
+            heap.stats.comparisons += stats.comparisons;
+        }
+        Ok(heap)
+    }
+
+    /// Returns counts of the work the sorter has done so far.
+    #[cfg(feature = "instrument")]
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Returns the number of bytes of heap memory held by the sorter's heap, counting its full
+    /// capacity but not any memory owned by the elements.
+    pub fn memory_usage(&self) -> usize {
+        self.heap.capacity() * mem::size_of::<ReverseOrder<T>>()
+    }
+
+    /// Runs `f` on the heap, counting the comparisons it makes if statistics are being counted.
+    #[inline]
+    fn with_heap<R, F>(&mut self, f: F) -> R
+        where F: FnOnce(&mut BinaryHeap<ReverseOrder<T>>) -> R
+    {
+        #[cfg(feature = "instrument")]
+        {
+            let heap = &mut self.heap;
+            instrument::count_heap_comparisons(&mut self.stats, || f(heap))
+        }
+        #[cfg(not(feature = "instrument"))]
+        f(&mut self.heap)
+    }
+}
+
+/// Rearranges `v` into a max-heap, sifting down the parents from the last one up as
+/// `BinaryHeap::from` does. Returns false if `cancel` is cancelled first.
+fn heapify<T: Ord>(v: &mut [T], cancel: &CancelToken) -> bool {
+    for (i, pos) in (0..v.len() / 2).rev().enumerate() {
+        if i.is_multiple_of(CANCEL_CHECK_INTERVAL) && cancel.is_cancelled() {
+            return false;
+        }
+        heap::sift_down_by(v, pos, |a, b| a > b);
+    }
+    true
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 struct ReverseOrder<T>(T);
 
 impl <T: PartialOrd> PartialOrd for ReverseOrder<T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        #[cfg(feature = "instrument")]
+        instrument::count_heap_comparison();
         other.0.partial_cmp(&self.0)
     }
 }
 
 impl <T: Ord> Ord for ReverseOrder<T> {
     fn cmp(&self, other: &Self) -> Ordering {
+        #[cfg(feature = "instrument")]
+        instrument::count_heap_comparison();
         other.0.cmp(&self.0)
     }
 }
@@ -269,15 +2254,57 @@ impl<T: Ord> Iterator for HeapSort<T> {
     type Item = T;
 
     fn next(&mut self) -> Option<T> {
-        self.0.pop().map(|ReverseOrder(el)| el)
+        let next = self.with_heap(BinaryHeap::pop).map(|ReverseOrder(el)| el);
+        #[cfg(feature = "verify")]
+        if let Some(ref el) = next {
+            let heap = self.heap.as_slice();
+            if let Some(following) = heap.first() {
+                verify::check_emitted(el, &following.0, heap.len());
+            }
+            if heap.len() > 3 {
+                verify::check_heap(&heap[0].0, &heap[1].0, &heap[3].0);
+            }
+        }
+        next
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = self.0.len();
+        let len = self.heap.len();
         (len, Some(len))
     }
 }
 
+impl<T: Ord> FusedIterator for HeapSort<T> {}
+
+impl<T: Ord> LazySorted<T> for HeapSort<T> {
+    fn peek(&mut self) -> Option<&T> {
+        self.heap.peek().map(|el| &el.0)
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    fn into_inner(self) -> Vec<T> {
+        self.heap.into_vec().into_iter().map(|ReverseOrder(el)| el).collect()
+    }
+
+    fn split_at_rank(self, k: usize) -> (HeapSort<T>, HeapSort<T>) {
+        let (smallest, rest) = split_at_rank(self.into_inner(), k);
+        (HeapSort::from_reversed(smallest.into_iter().map(ReverseOrder).collect()),
+         HeapSort::from_reversed(rest.into_iter().map(ReverseOrder).collect()))
+    }
+
+    fn modify_remaining<F>(&mut self, f: F)
+        where F: FnOnce(&mut [T])
+    {
+        let heap = mem::take(&mut self.heap);
+        let mut v: Vec<_> = heap.into_iter().map(|ReverseOrder(el)| el).collect();
+        f(&mut v);
+        self.with_heap(|heap| *heap = v.into_iter().map(ReverseOrder).collect());
+    }
+}
+
 
 #[test]
 fn quick_sort() {
@@ -333,7 +2360,215 @@ fn heap_size_hint() {
     }
 }
 
-#[cfg(test)]
+#[test]
+fn quick_split_at_rank() {
+    let mut v = vec![2, 4, 2, 5, 8, 4, 3, 4, 6, 1, 9, 0, 7];
+    let mut sort_iter = v.iter().cloned().quick_sort();
+    v.sort();
+    assert_eq!(sort_iter.next(), Some(v[0]));
+    let (smallest, rest) = sort_iter.split_at_rank(4);
+    assert_eq!(smallest.collect::<Vec<_>>(), &v[1..5]);
+    assert_eq!(rest.collect::<Vec<_>>(), &v[5..]);
+}
+
+#[test]
+fn quick_into_partitions() {
+    let v: Vec<u32> = (0..103).map(|i| i * 37 % 103).collect();
+    let partitions = v.iter().cloned().quick_sort().into_partitions(4);
+    assert_eq!(partitions.iter().map(LazySorted::len).collect::<Vec<_>>(), [25, 26, 26, 26]);
+    let sorted: Vec<_> = partitions.into_iter().flatten().collect();
+    assert_eq!(sorted, (0..103).collect::<Vec<_>>());
+}
+
+#[test]
+fn quick_try_next() {
+    use std::ops::ControlFlow;
+
+    let v: Vec<u32> = (0..1000).map(|i| i * 37 % 1000).collect();
+    let mut sort_iter = v.iter().cloned().quick_sort();
+    let mut sorted = Vec::new();
+    let mut suspended = 0;
+    loop {
+        match sort_iter.try_next(50) {
+            ControlFlow::Break(Some(el)) => sorted.push(el),
+            ControlFlow::Break(None) => break,
+            ControlFlow::Continue(()) => suspended += 1,
+        }
+    }
+    assert!(suspended >= 1000 / 50);
+    assert_eq!(sorted, (0..1000).collect::<Vec<_>>());
+    assert_eq!(sort_iter.try_next(0), ControlFlow::Break(None));
+}
+
+#[test]
+fn quick_try_next_budget() {
+    use std::ops::ControlFlow;
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+    static COMPARISONS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Counted(u32);
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Counted) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Counted {
+        fn cmp(&self, other: &Counted) -> Ordering {
+            COMPARISONS.fetch_add(1, Relaxed);
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let n = 100_000;
+    // Equal elements are split off with the pivot, and a pivot as great as the middle one below
+    // falls back to the median; either way, no call makes more comparisons than its budget.
+    let mut greatest_in_middle: Vec<_> = (0..n).map(Counted).collect();
+    greatest_in_middle[n as usize / 2] = Counted(n);
+    for v in [vec![Counted(7); n as usize], greatest_in_middle] {
+        let config = Config {
+            perturb: false,
+            ..Config::default()
+        };
+        let mut sorted = v.clone();
+        sorted.sort();
+        let mut sorter = QuickSort::with_config(v, config);
+        let mut yielded = Vec::new();
+        while yielded.len() < 100 {
+            let before = COMPARISONS.load(Relaxed);
+            if let ControlFlow::Break(next) = sorter.try_next(64) {
+                yielded.push(next.unwrap());
+            }
+            // Checking the splits with `verify` takes comparisons of its own.
+            assert!(COMPARISONS.load(Relaxed) - before <= 64 || cfg!(feature = "verify"));
+        }
+        assert_eq!(yielded, &sorted[..100]);
+    }
+}
+
+#[test]
+fn heap_split_at_rank() {
+    let mut v = vec![2, 4, 2, 5, 8, 4, 3, 4, 6, 1, 9, 0, 7];
+    let (smallest, rest) = v.iter().cloned().heap_sort().split_at_rank(20);
+    v.sort();
+    assert_eq!(smallest.collect::<Vec<_>>(), v);
+    assert_eq!(rest.count(), 0);
+}
+
+#[test]
+fn quick_peek_and_advance_to() {
+    let mut v: Vec<_> = (0..200).map(|i| (i * 37) % 101).collect();
+    let mut sort_iter = v.iter().cloned().quick_sort();
+    v.sort();
+    assert_eq!(sort_iter.peek(), Some(&v[0]));
+    assert_eq!(sort_iter.peek(), Some(&v[0]));
+    assert_eq!(sort_iter.next(), Some(v[0]));
+    sort_iter.advance_to(&50);
+    assert_eq!(sort_iter.len(), v.iter().filter(|&&el| el >= 50).count());
+    assert_eq!(sort_iter.peek(), Some(&50));
+    let rest: Vec<_> = v.into_iter().filter(|&el| el >= 50).collect();
+    assert_eq!(sort_iter.collect::<Vec<_>>(), rest);
+}
+
+#[test]
+fn heap_peek_and_advance_to() {
+    let mut v: Vec<_> = (0..200).map(|i| (i * 37) % 101).collect();
+    let mut sort_iter = v.iter().cloned().heap_sort();
+    v.sort();
+    assert_eq!(sort_iter.peek(), Some(&v[0]));
+    sort_iter.advance_to(&50);
+    assert_eq!(sort_iter.peek(), Some(&50));
+    assert_eq!(sort_iter.into_inner().len(), v.iter().filter(|&&el| el >= 50).count());
+}
+
+#[test]
+fn quick_modify_remaining() {
+    let mut sort_iter = (0..100).quick_sort();
+    assert_eq!(sort_iter.next(), Some(0));
+    sort_iter.modify_remaining(|rest| for el in rest.iter_mut().filter(|el| **el % 2 == 0) {
+        *el += 1000;
+    });
+    let mut expected: Vec<_> = (1..100)
+        .map(|el| if el % 2 == 0 { el + 1000 } else { el })
+        .collect();
+    expected.sort();
+    assert_eq!(sort_iter.collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn test_partition_sorted() {
+    let (even, odd) = (0..100).map(|i| (i * 37) % 100).partition_sorted(|&i| i % 2 == 0);
+    assert_eq!((even.len(), odd.len()), (50, 50));
+    assert_eq!(even.take(3).collect::<Vec<_>>(), [0, 2, 4]);
+    assert_eq!(odd.collect::<Vec<_>>(), (0..50).map(|i| 2 * i + 1).collect::<Vec<_>>());
+}
+
+#[test]
+fn quick_sorted_chunks() {
+    let v: Vec<_> = (0..1000).map(|i| (i * 7919) % 1000).collect();
+    let mut chunks = v.iter().cloned().quick_sort().sorted_chunks(64);
+    assert_eq!(chunks.len(), 16);
+    let first = chunks.next().unwrap();
+    assert_eq!(first, (0..64).collect::<Vec<_>>());
+    assert_eq!(first.capacity(), 64);
+    assert!(chunks.by_ref().take(14).flatten().eq(64..960));
+    assert_eq!(chunks.next(), Some((960..1000).collect()));
+    assert_eq!(chunks.next(), None);
+    let built = LazySortBuilder::new().threshold(0).sort(v.clone()).unwrap();
+    assert!(built.sorted_chunks(7).flatten().eq(0..1000));
+
+    let mut sorter = v.iter().cloned().heap_sort();
+    let mut out = vec![-1];
+    sorter.next_chunk_into(3, &mut out);
+    assert_eq!(out, [-1, 0, 1, 2]);
+    assert_eq!(sorter.len(), 997);
+
+    // Inputs that run out of elements partway through a partition, with a remainder chunk.
+    let v: Vec<u32> = (0..1003).rev().map(|i| i * 7919 % 1009).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    for &n in &[1, 7, 1000] {
+        let chunks: Vec<_> = v.iter().cloned().quick_sort().sorted_chunks(n).collect();
+        assert_eq!(chunks.len(), v.len().div_ceil(n));
+        assert!(chunks[..chunks.len() - 1].iter().all(|chunk| chunk.len() == n));
+        assert_eq!(chunks.concat(), expected);
+    }
+}
+
+#[test]
+fn quick_partitions() {
+    // Unperturbed pivots keep the smallest element from being a pivot with nothing before it.
+    let config = Config {
+        perturb: false,
+        ..Config::default()
+    };
+    let mut sorter = QuickSort::with_config((0..1000).map(|i| (i * 7919) % 1000).collect(), config);
+    assert_eq!(sorter.partitions().collect::<Vec<_>>(),
+               [PartitionInfo { depth: 0, len: 1000, pivot: None, sorted: false }]);
+    assert_eq!(sorter.next(), Some(0));
+    let partitions: Vec<_> = sorter.partitions().collect();
+    assert!(partitions[0].sorted);
+    assert_eq!(partitions.iter().map(|p| p.len).sum::<usize>(), 999);
+    assert!(partitions.windows(2).all(|w| w[0].depth > w[1].depth));
+    let pivots: Vec<_> = partitions.iter().filter_map(|p| p.pivot).collect();
+    assert_eq!(pivots.len(), partitions.len() - 1);
+    assert!(pivots.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn heap_modify_remaining() {
+    let mut sort_iter = (0..100).heap_sort();
+    assert_eq!(sort_iter.next(), Some(0));
+    sort_iter.modify_remaining(|rest| for el in rest.iter_mut() {
+        *el = 100 - *el;
+    });
+    assert_eq!(sort_iter.collect::<Vec<_>>(), (1..100).collect::<Vec<_>>());
+}
+
+#[cfg(all(test, feature = "unstable"))]
 mod bench {
     extern crate test;
 
@@ -359,7 +2594,7 @@ mod bench {
         b.iter(|| {
             let mut v = v.clone();
             v.sort();
-            v.iter().cloned().take(k).collect::<Vec<_>>();
+            v.iter().cloned().take(k).collect::<Vec<_>>()
         });
     }
 
@@ -423,3 +2658,139 @@ mod bench {
         take_eager(b, 50_000);
     }
 }
+
+#[cfg(feature = "bincode")]
+#[test]
+fn test_serialize_sorted_into() {
+    use bincode::Options;
+
+    let v: Vec<u32> = (0..1000).map(|i| (i * 7919) % 1009).collect();
+    let mut sorted = v.clone();
+    sorted.sort();
+    let options = bincode::DefaultOptions::new();
+    let mut streamed = Vec::new();
+    let mut sorter = v.into_iter().quick_sort();
+    sorter.by_ref().take(10).for_each(drop);
+    sorter.serialize_sorted_into(&mut bincode::Serializer::new(&mut streamed, options)).unwrap();
+    assert_eq!(streamed, options.serialize(&sorted[10..]).unwrap());
+}
+
+#[test]
+fn test_next_n() {
+    let v: Vec<u32> = (0..1000).map(|i| (i * 7919) % 1009).collect();
+    let mut sorted = v.clone();
+    sorted.sort();
+    let mut sorter = v.into_iter().quick_sort();
+    for batch in sorted.chunks(64) {
+        assert_eq!(sorter.next_n(64), batch);
+    }
+    assert!(sorter.next_n(64).is_empty());
+    assert_eq!(vec![3, 1, 2].into_iter().heap_sort().next_n(2), [1, 2]);
+}
+
+#[test]
+fn test_contains() {
+    let mut sorter = (0..1000u32).map(|i| (i * 7919) % 1009).quick_sort();
+    assert!(sorter.contains(&0) && !sorter.contains(&1009));
+    for _ in 0..3 {
+        sorter.by_ref().take(300).for_each(drop);
+        let sorted = sorter.clone().collect::<Vec<_>>();
+        for x in 0..1010 {
+            assert_eq!(sorter.contains(&x), sorted.binary_search(&x).is_ok());
+        }
+    }
+    assert_eq!(sorter.len(), 100);
+}
+
+#[test]
+fn test_successor() {
+    let mut sorter = (0..1000u32).map(|i| (i * 7919) % 1009 * 2).quick_sort();
+    let sorted = sorter.clone().collect::<Vec<_>>();
+    for x in (0..2030).step_by(7) {
+        let i = sorted.partition_point(|&el| el < x);
+        assert_eq!(sorter.successor(&x), sorted.get(i));
+    }
+    assert_eq!(sorter.len(), 1000);
+    assert!(sorter.eq(sorted));
+}
+
+#[test]
+fn test_quick_sort_pipelined() {
+    let v: Vec<u32> = (0..1000).map(|i| (i * 7919) % 1009 / 3).collect();
+    let mut sorted = v.clone();
+    sorted.sort();
+    let mut sorter = v.iter().cloned().quick_sort_pipelined();
+    assert_eq!(sorter.len(), 1000);
+    assert_eq!(sorter.partitions().count(), 2);
+    assert_eq!(sorter.peek(), Some(&0));
+    assert!(sorter.eq(sorted.iter().cloned()));
+    let short = v[..20].iter().cloned().quick_sort_pipelined().collect::<Vec<_>>();
+    assert!(short.len() == 20 && short.windows(2).all(|w| w[0] <= w[1]));
+}
+
+#[test]
+fn quick_eager_crossover() {
+    let v: Vec<u32> = (0..1000).map(|i| (i * 7919) % 1009).collect();
+    let mut sorted = v.clone();
+    sorted.sort();
+    // Unperturbed pivots keep the partitions left after each prefix the same from run to run.
+    let config = Config {
+        crossover: Some(0.25),
+        perturb: false,
+        ..Config::default()
+    };
+    let mut sorter = QuickSort::with_config(v.clone(), config);
+    assert!(sorter.by_ref().take(200).eq(sorted[..200].iter().cloned()));
+    assert_eq!(sorter.next_n(49), &sorted[200..249]);
+    assert!(sorter.partitions().count() > 1);
+    assert_eq!(sorter.next(), Some(sorted[249]));
+    assert_eq!(sorter.partitions().collect::<Vec<_>>(),
+               [PartitionInfo { depth: 0, len: 750, pivot: None, sorted: true }]);
+    assert!(sorter.eq(sorted[250..].iter().cloned()));
+
+    // Elements reached within a budget count towards the crossover too.
+    let config = Config {
+        crossover: Some(0.25),
+        perturb: false,
+        ..Config::default()
+    };
+    let mut budgeted = QuickSort::with_config(v.clone(), config);
+    for &el in &sorted[..250] {
+        let mut next = budgeted.try_next(64);
+        while next.is_continue() {
+            next = budgeted.try_next(64);
+        }
+        assert_eq!(next, ControlFlow::Break(Some(el)));
+    }
+    assert!(budgeted.partitions().count() > 1);
+    assert_eq!(budgeted.next(), Some(sorted[250]));
+    assert_eq!(budgeted.partitions().collect::<Vec<_>>(),
+               [PartitionInfo { depth: 0, len: 749, pivot: None, sorted: true }]);
+
+    let config = Config {
+        crossover: None,
+        perturb: false,
+        ..Config::default()
+    };
+    let mut lazy = QuickSort::with_config(v, config);
+    assert!(lazy.by_ref().take(900).eq(sorted[..900].iter().cloned()));
+    assert!(lazy.partitions().count() > 1);
+}
+
+#[test]
+fn quick_bounded_depth() {
+    let n = 10_000;
+    let bound = ((n as f64).ln() / (8.0f64 / 7.0).ln()) as usize + 1;
+    let inputs: [Vec<u32>; 2] = [vec![7; n], (0..n as u32).map(|i| i % 3).collect()];
+    for v in inputs {
+        let config = Config {
+            threshold: 0,
+            crossover: None,
+            ..Config::default()
+        };
+        let mut sorter = QuickSort::with_config(v, config);
+        while sorter.next().is_some() {
+            assert!(sorter.partitions().all(|p| p.depth <= bound));
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Sorting the lines of a text stream.
+
+use std::cmp::Ordering;
+use std::io::{self, BufRead, Read, Write};
+use std::mem;
+use super::{ExternalSort, ExternalSortBuilder, SpillCodec};
+
+/// Lazily sorts the lines of `reader`, without their line endings.
+///
+/// The lines are sorted in memory if they fit in `builder`'s memory budget, and spilled to disk
+/// otherwise. The budget counts each line's text as well as its bookkeeping.
+pub fn sort_lines<R: BufRead>(reader: R, builder: ExternalSortBuilder)
+    -> io::Result<SortedLines>
+{
+    fn no_key(_: &str) {}
+    sort_lines_by_key(reader, builder, no_key as fn(&str))
+}
+
+/// Lazily sorts the lines of `reader` by the key that `key_fn` extracts from each, breaking ties
+/// by comparing whole lines.
+///
+/// Keys aren't spilled; `key_fn` is called again on each line read back from disk.
+pub fn sort_lines_by_key<R, K, F>(reader: R, builder: ExternalSortBuilder, key_fn: F)
+    -> io::Result<SortedLines<K, F>>
+    where R: BufRead,
+          K: Ord,
+          F: Fn(&str) -> K + Clone
+{
+    let mut error = None;
+    let lines = reader.lines().map_while(|line| match line {
+        Ok(line) => Some(KeyedLine {
+            key: key_fn(&line),
+            line,
+        }),
+        Err(err) => {
+            error = Some(err);
+            None
+        }
+    });
+    let weigh = |line: &KeyedLine<K>| mem::size_of::<KeyedLine<K>>() + line.line.len();
+    let sorted = builder.sort_weighed(lines, LineCodec(key_fn.clone()), weigh)?;
+    match error {
+        Some(err) => Err(err),
+        None => Ok(SortedLines(sorted)),
+    }
+}
+
+/// The sorted lines of a text stream, created by [`sort_lines`](fn.sort_lines.html) or
+/// [`sort_lines_by_key`](fn.sort_lines_by_key.html).
+///
+/// If reading spilled lines back fails, iteration stops early and the error is available from
+/// [`error`](#method.error).
+#[derive(Debug)]
+pub struct SortedLines<K = (), F = fn(&str)>(ExternalSort<KeyedLine<K>, LineCodec<F>>)
+    where K: Ord,
+          F: Fn(&str) -> K;
+
+impl<K: Ord, F: Fn(&str) -> K> SortedLines<K, F> {
+    /// Returns the error that ended iteration early, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.0.error()
+    }
+}
+
+impl<K: Ord, F: Fn(&str) -> K> Iterator for SortedLines<K, F> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.0.next().map(|keyed| keyed.line)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// A line ordered by its key, then by its text.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct KeyedLine<K> {
+    key: K,
+    line: String,
+}
+
+impl<K: Ord> Ord for KeyedLine<K> {
+    fn cmp(&self, other: &KeyedLine<K>) -> Ordering {
+        self.key.cmp(&other.key).then_with(|| self.line.cmp(&other.line))
+    }
+}
+
+impl<K: Ord> PartialOrd for KeyedLine<K> {
+    fn partial_cmp(&self, other: &KeyedLine<K>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> PartialEq for KeyedLine<K> {
+    fn eq(&self, other: &KeyedLine<K>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord> Eq for KeyedLine<K> {}
+
+/// Spills a line as its length followed by its text, and recomputes its key when read back.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct LineCodec<F>(F);
+
+impl<F> ::std::fmt::Debug for LineCodec<F> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("LineCodec")
+    }
+}
+
+impl<K, F: Fn(&str) -> K> SpillCodec<KeyedLine<K>> for LineCodec<F> {
+    fn encode<W: Write>(&self, el: &KeyedLine<K>, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(el.line.len() as u64).to_le_bytes())?;
+        writer.write_all(el.line.as_bytes())
+    }
+
+    fn decode<R: Read>(&self, reader: &mut R) -> io::Result<KeyedLine<K>> {
+        let mut len = [0; 8];
+        reader.read_exact(&mut len)?;
+        let mut bytes = vec![0; u64::from_le_bytes(len) as usize];
+        reader.read_exact(&mut bytes)?;
+        let line = String::from_utf8(bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(KeyedLine {
+            key: (self.0)(&line),
+            line,
+        })
+    }
+}
+
+#[test]
+fn sort_lines_in_memory_and_spilled() {
+    let text = "pear 3\nfig 10\napple 7\r\nkiwi 1\nfig 2\n";
+    for &budget in &[1, 1 << 20] {
+        let builder = ExternalSortBuilder::new().memory_budget(budget);
+        let sorted: Vec<_> = sort_lines(text.as_bytes(), builder.clone()).unwrap().collect();
+        assert_eq!(sorted, ["apple 7", "fig 10", "fig 2", "kiwi 1", "pear 3"]);
+
+        let by_count = |line: &str| line.split(' ').nth(1).unwrap().parse::<u32>().unwrap();
+        let sorted: Vec<_> = sort_lines_by_key(text.as_bytes(), builder, by_count).unwrap()
+            .collect();
+        assert_eq!(sorted, ["kiwi 1", "fig 2", "pear 3", "apple 7", "fig 10"]);
+    }
+}
@@ -0,0 +1,171 @@
+//! Lazily sorting the entries of hash maps.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+use std::iter::FusedIterator;
+use super::{LazySortIterator, QuickSort};
+
+/// An extension trait for lazily sorting the entries of a `HashMap`, taken by value or by
+/// reference, without first collecting them into a vec.
+///
+/// ```
+/// use lazy_sort::LazySortMap;
+/// use std::collections::HashMap;
+///
+/// let counts: HashMap<_, _> = vec![("a", 3), ("b", 7), ("c", 1), ("d", 5)].into_iter().collect();
+/// let top: Vec<_> = counts.sorted_by_value_desc_lazy().take(2).collect();
+/// assert_eq!(top, [("b", 7), ("d", 5)]);
+/// ```
+pub trait LazySortMap<K, V>: IntoIterator<Item = (K, V)>
+    where Self: Sized
+{
+    /// Lazily sorts the entries by key.
+    fn sorted_by_key_lazy(self) -> SortedByKey<K, V>
+        where K: Ord
+    {
+        SortedByKey(self.into_iter().map(KeyOrder).quick_sort())
+    }
+
+    /// Lazily sorts the entries by value, smallest first. Entries with equal values are yielded
+    /// in no particular order.
+    fn sorted_by_value_lazy(self) -> SortedByValue<K, V>
+        where V: Ord
+    {
+        SortedByValue(self.into_iter().map(ValueOrder).quick_sort())
+    }
+
+    /// Lazily sorts the entries by value, largest first, such as for the top entries by count.
+    /// Entries with equal values are yielded in no particular order.
+    fn sorted_by_value_desc_lazy(self) -> SortedByValueDesc<K, V>
+        where V: Ord
+    {
+        SortedByValueDesc(self.into_iter().map(ValueOrder).quick_sort())
+    }
+}
+
+impl<K, V, S: BuildHasher> LazySortMap<K, V> for HashMap<K, V, S> { }
+
+impl<'a, K, V, S: BuildHasher> LazySortMap<&'a K, &'a V> for &'a HashMap<K, V, S> { }
+
+/// An entry ordered by its key.
+#[derive(Debug, Clone)]
+struct KeyOrder<K, V>((K, V));
+
+impl<K: Ord, V> Ord for KeyOrder<K, V> {
+    fn cmp(&self, other: &KeyOrder<K, V>) -> Ordering {
+        (self.0).0.cmp(&(other.0).0)
+    }
+}
+
+impl<K: Ord, V> PartialOrd for KeyOrder<K, V> {
+    fn partial_cmp(&self, other: &KeyOrder<K, V>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> PartialEq for KeyOrder<K, V> {
+    fn eq(&self, other: &KeyOrder<K, V>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, V> Eq for KeyOrder<K, V> {}
+
+/// An entry ordered by its value, in descending order if `DESC` is true.
+#[derive(Debug, Clone)]
+struct ValueOrder<K, V, const DESC: bool>((K, V));
+
+impl<K, V: Ord, const DESC: bool> Ord for ValueOrder<K, V, DESC> {
+    fn cmp(&self, other: &ValueOrder<K, V, DESC>) -> Ordering {
+        let ordering = (self.0).1.cmp(&(other.0).1);
+        if DESC { ordering.reverse() } else { ordering }
+    }
+}
+
+impl<K, V: Ord, const DESC: bool> PartialOrd for ValueOrder<K, V, DESC> {
+    fn partial_cmp(&self, other: &ValueOrder<K, V, DESC>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K, V: Ord, const DESC: bool> PartialEq for ValueOrder<K, V, DESC> {
+    fn eq(&self, other: &ValueOrder<K, V, DESC>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K, V: Ord, const DESC: bool> Eq for ValueOrder<K, V, DESC> {}
+
+macro_rules! sorted_entries {
+    ($(#[$doc:meta])* $name:ident, $order:ty, $bound:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone)]
+        pub struct $name<K, V>(QuickSort<$order>);
+
+        impl<K, V> $name<K, V>
+            where $bound: Ord
+        {
+            /// Returns the next entry in sorted order without consuming it.
+            pub fn peek(&mut self) -> Option<&(K, V)> {
+                use super::LazySorted;
+
+                self.0.peek().map(|entry| &entry.0)
+            }
+        }
+
+        impl<K, V> Iterator for $name<K, V>
+            where $bound: Ord
+        {
+            type Item = (K, V);
+
+            fn next(&mut self) -> Option<(K, V)> {
+                self.0.next().map(|entry| entry.0)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.0.size_hint()
+            }
+        }
+
+        impl<K, V> ExactSizeIterator for $name<K, V> where $bound: Ord { }
+
+        impl<K, V> FusedIterator for $name<K, V> where $bound: Ord { }
+    }
+}
+
+sorted_entries!(
+    /// The entries of a map lazily sorted by key, created by
+    /// [`sorted_by_key_lazy`](trait.LazySortMap.html#method.sorted_by_key_lazy).
+    SortedByKey, KeyOrder<K, V>, K);
+sorted_entries!(
+    /// The entries of a map lazily sorted by value, created by
+    /// [`sorted_by_value_lazy`](trait.LazySortMap.html#method.sorted_by_value_lazy).
+    SortedByValue, ValueOrder<K, V, false>, V);
+sorted_entries!(
+    /// The entries of a map lazily sorted by value in descending order, created by
+    /// [`sorted_by_value_desc_lazy`](trait.LazySortMap.html#method.sorted_by_value_desc_lazy).
+    SortedByValueDesc, ValueOrder<K, V, true>, V);
+
+impl<K: Ord, V> SortedByKey<K, V> {
+    /// Collects the remaining entries into a `BTreeMap`, which it builds in bulk in `O(n)`, since
+    /// they're yielded in key order.
+    pub fn into_btree_map(self) -> BTreeMap<K, V> {
+        self.collect()
+    }
+}
+
+#[test]
+fn sorted_map_entries() {
+    let map: HashMap<u32, char> = (0..26).map(|i| ((i * 7) % 26, (b'a' + i as u8) as char))
+                                          .collect();
+    let mut by_key = (&map).sorted_by_key_lazy();
+    assert_eq!(by_key.len(), 26);
+    assert_eq!(by_key.peek(), Some(&(&0, &'a')));
+    assert_eq!(by_key.take(3).collect::<Vec<_>>(), [(&0, &'a'), (&1, &'p'), (&2, &'e')]);
+    assert_eq!((&map).sorted_by_value_lazy().map(|(_, &v)| v).take(3).collect::<String>(), "abc");
+    let tree = map.clone().sorted_by_key_lazy().into_btree_map();
+    assert!(tree.iter().eq((&map).sorted_by_key_lazy()));
+    let desc: Vec<_> = map.sorted_by_value_desc_lazy().take(2).collect();
+    assert_eq!(desc, [(19, 'z'), (12, 'y')]);
+}
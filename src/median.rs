@@ -0,0 +1,98 @@
+//! Tracking the median of a stream.
+
+use std::collections::BinaryHeap;
+use super::ReverseOrder;
+
+/// The median of a growing collection of elements, kept up to date on every push.
+///
+/// The elements are split between two heaps: a max-heap of the smaller half and a min-heap of the
+/// larger half. Pushing costs `O(log n)` and reading the median costs `O(1)`.
+#[derive(Debug, Clone)]
+pub struct RunningMedian<T> {
+    /// The smaller half of the elements. It holds the same number of elements as `upper`, or one
+    /// more.
+    lower: BinaryHeap<T>,
+    /// The larger half of the elements.
+    upper: BinaryHeap<ReverseOrder<T>>,
+}
+
+impl<T: Ord> Default for RunningMedian<T> {
+    fn default() -> RunningMedian<T> {
+        RunningMedian::new()
+    }
+}
+
+impl<T: Ord> RunningMedian<T> {
+    /// Returns an empty `RunningMedian`.
+    pub fn new() -> RunningMedian<T> {
+        RunningMedian {
+            lower: BinaryHeap::new(),
+            upper: BinaryHeap::new(),
+        }
+    }
+
+    /// Adds an element.
+    pub fn push(&mut self, el: T) {
+        if self.lower.peek().is_none_or(|max| el <= *max) {
+            self.lower.push(el);
+        } else {
+            self.upper.push(ReverseOrder(el));
+        }
+        if self.lower.len() > self.upper.len() + 1 {
+            let el = self.lower.pop().unwrap();
+            self.upper.push(ReverseOrder(el));
+        } else if self.upper.len() > self.lower.len() {
+            let ReverseOrder(el) = self.upper.pop().unwrap();
+            self.lower.push(el);
+        }
+    }
+
+    /// Returns the median, or `None` if there are no elements. For an even number of elements,
+    /// this is the lower of the two middle elements.
+    pub fn median(&self) -> Option<&T> {
+        self.lower.peek()
+    }
+
+    /// Returns the upper of the two middle elements for an even number of elements, or the median
+    /// for an odd number.
+    pub fn upper_median(&self) -> Option<&T> {
+        if self.lower.len() > self.upper.len() {
+            self.lower.peek()
+        } else {
+            self.upper.peek().map(|el| &el.0)
+        }
+    }
+
+    /// Returns the number of elements pushed.
+    pub fn len(&self) -> usize {
+        self.lower.len() + self.upper.len()
+    }
+
+    /// Returns true if no elements have been pushed.
+    pub fn is_empty(&self) -> bool {
+        self.lower.is_empty()
+    }
+}
+
+impl<T: Ord> Extend<T> for RunningMedian<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for el in iter {
+            self.push(el);
+        }
+    }
+}
+
+#[test]
+fn running_median() {
+    let mut median = RunningMedian::new();
+    assert_eq!(median.median(), None);
+    let mut seen = Vec::new();
+    for el in (0..101).map(|i| (i * 37) % 101) {
+        median.push(el);
+        seen.push(el);
+        seen.sort();
+        assert_eq!(median.median(), Some(&seen[(seen.len() - 1) / 2]));
+        assert_eq!(median.upper_median(), Some(&seen[seen.len() / 2]));
+    }
+    assert_eq!(median.len(), 101);
+}
@@ -0,0 +1,710 @@
+//! Combinators over already-sorted iterators.
+
+use std::cmp::Ordering::{Equal, Greater, Less};
+use std::mem;
+use std::vec::Vec;
+
+/// Lazily merges two sorted iterators into a single sorted iterator.
+///
+/// Both inputs are trusted to be sorted; if they aren't, the output won't be either. Of equal
+/// elements, those from `a` are yielded first.
+pub fn merge<I, J>(a: I, b: J) -> Merge<I::IntoIter, J::IntoIter>
+    where I: IntoIterator,
+          J: IntoIterator<Item = I::Item>,
+          I::Item: Ord
+{
+    Merge {
+        a: Peeked::new(a.into_iter()),
+        b: Peeked::new(b.into_iter()),
+    }
+}
+
+/// An iterator that merges two sorted iterators.
+#[derive(Debug, Clone)]
+pub struct Merge<I: Iterator, J: Iterator<Item = I::Item>> {
+    a: Peeked<I, I::Item>,
+    b: Peeked<J, I::Item>,
+}
+
+impl<I, J> Iterator for Merge<I, J>
+    where I: Iterator,
+          J: Iterator<Item = I::Item>,
+          I::Item: Ord
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let take_b = match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) => b < a,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        if take_b {
+            self.b.next()
+        } else {
+            self.a.next()
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a_lower, a_upper) = self.a.size_hint();
+        let (b_lower, b_upper) = self.b.size_hint();
+        let upper = match (a_upper, b_upper) {
+            (Some(a), Some(b)) => a.checked_add(b),
+            _ => None,
+        };
+        (a_lower.saturating_add(b_lower), upper)
+    }
+}
+
+/// Lazily merges any number of sorted iterators into a single sorted iterator.
+///
+/// The merge uses a loser tree, so each element costs `O(log m)` comparisons for `m` inputs. As
+/// with [`merge`](fn.merge.html), the inputs are trusted to be sorted, and equal elements are
+/// yielded in the order of the inputs they came from.
+pub fn kmerge<I>(inputs: I) -> KMerge<<I::Item as IntoIterator>::IntoIter>
+    where I: IntoIterator,
+          I::Item: IntoIterator,
+          <I::Item as IntoIterator>::Item: Ord
+{
+    let sources: Vec<_> = inputs.into_iter().map(IntoIterator::into_iter).collect();
+    KMerge {
+        heads: Vec::with_capacity(sources.len()),
+        tree: Vec::new(),
+        sources,
+    }
+}
+
+/// An iterator that merges any number of sorted iterators.
+#[derive(Debug, Clone)]
+pub struct KMerge<I: Iterator> {
+    sources: Vec<I>,
+    /// The next element of each source, or `None` if it's exhausted.
+    heads: Vec<Option<I::Item>>,
+    /// `tree[0]` is the index of the source with the smallest head, and `tree[1..]` are the
+    /// internal nodes of the tournament, each holding the loser of the match played there. The
+    /// leaf of source `i` is at position `i + sources.len()`. Empty until the first call to
+    /// `next`, so that constructing the merge doesn't pull from any source.
+    tree: Vec<usize>,
+}
+
+impl<I> KMerge<I>
+    where I: Iterator,
+          I::Item: Ord
+{
+    /// Returns the iterators being merged.
+    #[cfg(feature = "external")]
+    pub(crate) fn sources(&self) -> &[I] {
+        &self.sources
+    }
+
+    /// Returns the iterators being merged, dropping any elements taken from them but not yet
+    /// yielded.
+    #[cfg(feature = "external")]
+    pub(crate) fn into_sources(self) -> Vec<I> {
+        self.sources
+    }
+
+    /// Returns the element taken from each source but not yet yielded, which is empty until the
+    /// first call to `next`.
+    #[cfg(feature = "external")]
+    pub(crate) fn heads(&self) -> &[Option<I::Item>] {
+        &self.heads
+    }
+
+    /// Returns true if source `a`'s head should be yielded before source `b`'s.
+    fn beats(&self, a: usize, b: usize) -> bool {
+        match (self.heads[a].as_ref(), self.heads[b].as_ref()) {
+            (Some(x), Some(y)) => x.cmp(y).then(a.cmp(&b)) == Less,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => a < b,
+        }
+    }
+
+    fn build(&mut self) {
+        let m = self.sources.len();
+        self.heads.extend(self.sources.iter_mut().map(Iterator::next));
+        let mut winners = vec![0; 2 * m];
+        for i in 0..m {
+            winners[m + i] = i;
+        }
+        self.tree = vec![0; m];
+        for p in (1..m).rev() {
+            let (left, right) = (winners[2 * p], winners[2 * p + 1]);
+            if self.beats(left, right) {
+                winners[p] = left;
+                self.tree[p] = right;
+            } else {
+                winners[p] = right;
+                self.tree[p] = left;
+            }
+        }
+        self.tree[0] = winners[1];
+    }
+}
+
+impl<I> Iterator for KMerge<I>
+    where I: Iterator,
+          I::Item: Ord
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let m = self.sources.len();
+        if m == 0 {
+            return None;
+        }
+        if self.tree.is_empty() {
+            self.build();
+        }
+        let mut winner = self.tree[0];
+        let next = self.heads[winner].take()?;
+        self.heads[winner] = self.sources[winner].next();
+        // Replay the matches on the path from the winner's leaf to the root.
+        let mut p = (winner + m) / 2;
+        while p >= 1 {
+            if self.beats(self.tree[p], winner) {
+                mem::swap(&mut self.tree[p], &mut winner);
+            }
+            p /= 2;
+        }
+        self.tree[0] = winner;
+        Some(next)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let heads = self.heads.iter().filter(|head| head.is_some()).count();
+        self.sources.iter().fold((heads, Some(heads)), |(lower, upper), source| {
+            let (source_lower, source_upper) = source.size_hint();
+            (lower.saturating_add(source_lower),
+             upper.and_then(|upper| source_upper.and_then(|s| upper.checked_add(s))))
+        })
+    }
+}
+
+/// Lazily yields the distinct elements that are in either of two sorted iterators.
+pub fn union<I, J>(a: I, b: J) -> Union<I::IntoIter, J::IntoIter>
+    where I: IntoIterator,
+          J: IntoIterator<Item = I::Item>,
+          I::Item: Ord
+{
+    Union {
+        a: Peeked::new(a.into_iter()),
+        b: Peeked::new(b.into_iter()),
+    }
+}
+
+/// Lazily yields the distinct elements that are in both of two sorted iterators.
+pub fn intersection<I, J>(a: I, b: J) -> Intersection<I::IntoIter, J::IntoIter>
+    where I: IntoIterator,
+          J: IntoIterator<Item = I::Item>,
+          I::Item: Ord
+{
+    Intersection {
+        a: Peeked::new(a.into_iter()),
+        b: Peeked::new(b.into_iter()),
+    }
+}
+
+/// Lazily yields the distinct elements of sorted iterator `a` that aren't in sorted iterator `b`.
+pub fn difference<I, J>(a: I, b: J) -> Difference<I::IntoIter, J::IntoIter>
+    where I: IntoIterator,
+          J: IntoIterator<Item = I::Item>,
+          I::Item: Ord
+{
+    Difference {
+        a: Peeked::new(a.into_iter()),
+        b: Peeked::new(b.into_iter()),
+    }
+}
+
+/// An iterator over the union of two sorted iterators.
+#[derive(Debug, Clone)]
+pub struct Union<I: Iterator, J: Iterator<Item = I::Item>> {
+    a: Peeked<I, I::Item>,
+    b: Peeked<J, I::Item>,
+}
+
+impl<I, J> Iterator for Union<I, J>
+    where I: Iterator,
+          J: Iterator<Item = I::Item>,
+          I::Item: Ord
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let next = match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) if b < a => self.b.next(),
+            (Some(_), _) => self.a.next(),
+            (None, _) => self.b.next(),
+        }?;
+        self.a.skip_equal(&next);
+        self.b.skip_equal(&next);
+        Some(next)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a_lower, a_upper) = self.a.size_hint();
+        let (b_lower, b_upper) = self.b.size_hint();
+        let upper = match (a_upper, b_upper) {
+            (Some(a), Some(b)) => a.checked_add(b),
+            _ => None,
+        };
+        ((a_lower > 0 || b_lower > 0) as usize, upper)
+    }
+}
+
+/// An iterator over the intersection of two sorted iterators.
+#[derive(Debug, Clone)]
+pub struct Intersection<I: Iterator, J: Iterator<Item = I::Item>> {
+    a: Peeked<I, I::Item>,
+    b: Peeked<J, I::Item>,
+}
+
+impl<I, J> Iterator for Intersection<I, J>
+    where I: Iterator,
+          J: Iterator<Item = I::Item>,
+          I::Item: Ord
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        loop {
+            let ordering = match (self.a.peek(), self.b.peek()) {
+                (Some(a), Some(b)) => a.cmp(b),
+                _ => return None,
+            };
+            match ordering {
+                Less => {
+                    self.a.next();
+                }
+                Greater => {
+                    self.b.next();
+                }
+                Equal => {
+                    let next = self.a.next()?;
+                    self.a.skip_equal(&next);
+                    self.b.skip_equal(&next);
+                    return Some(next);
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let upper = match (self.a.size_hint().1, self.b.size_hint().1) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        (0, upper)
+    }
+}
+
+/// An iterator over the difference of two sorted iterators.
+#[derive(Debug, Clone)]
+pub struct Difference<I: Iterator, J: Iterator<Item = I::Item>> {
+    a: Peeked<I, I::Item>,
+    b: Peeked<J, I::Item>,
+}
+
+impl<I, J> Iterator for Difference<I, J>
+    where I: Iterator,
+          J: Iterator<Item = I::Item>,
+          I::Item: Ord
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        loop {
+            let next = self.a.next()?;
+            self.a.skip_equal(&next);
+            self.b.skip_less(&next);
+            if self.b.peek() != Some(&next) {
+                return Some(next);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.a.size_hint().1)
+    }
+}
+
+/// Lazily yields the distinct elements that are in exactly one of two sorted iterators, tagged
+/// with the side they came from.
+pub fn symmetric_difference<I, J>(a: I, b: J) -> SymmetricDifference<I::IntoIter, J::IntoIter>
+    where I: IntoIterator,
+          J: IntoIterator<Item = I::Item>,
+          I::Item: Ord
+{
+    SymmetricDifference {
+        a: Peeked::new(a.into_iter()),
+        b: Peeked::new(b.into_iter()),
+    }
+}
+
+/// Which of two inputs an element came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// The first input.
+    Left,
+    /// The second input.
+    Right,
+}
+
+/// An iterator over the symmetric difference of two sorted iterators.
+#[derive(Debug, Clone)]
+pub struct SymmetricDifference<I: Iterator, J: Iterator<Item = I::Item>> {
+    a: Peeked<I, I::Item>,
+    b: Peeked<J, I::Item>,
+}
+
+impl<I, J> Iterator for SymmetricDifference<I, J>
+    where I: Iterator,
+          J: Iterator<Item = I::Item>,
+          I::Item: Ord
+{
+    type Item = (Side, I::Item);
+
+    fn next(&mut self) -> Option<(Side, I::Item)> {
+        loop {
+            let ordering = match (self.a.peek(), self.b.peek()) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => Less,
+                (None, Some(_)) => Greater,
+                (None, None) => return None,
+            };
+            match ordering {
+                Less => {
+                    let next = self.a.next()?;
+                    self.a.skip_equal(&next);
+                    return Some((Side::Left, next));
+                }
+                Greater => {
+                    let next = self.b.next()?;
+                    self.b.skip_equal(&next);
+                    return Some((Side::Right, next));
+                }
+                Equal => {
+                    let next = self.a.next()?;
+                    self.a.skip_equal(&next);
+                    self.b.skip_equal(&next);
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let upper = match (self.a.size_hint().1, self.b.size_hint().1) {
+            (Some(a), Some(b)) => a.checked_add(b),
+            _ => None,
+        };
+        (0, upper)
+    }
+}
+
+/// Lazily yields the union of two sorted iterators as multisets: each element occurs as many times
+/// as in whichever input has more of it. Of equal elements, those from `a` are yielded first.
+pub fn multiset_union<I, J>(a: I, b: J) -> MultisetUnion<I::IntoIter, J::IntoIter>
+    where I: IntoIterator,
+          J: IntoIterator<Item = I::Item>,
+          I::Item: Ord
+{
+    MultisetUnion {
+        a: Peeked::new(a.into_iter()),
+        b: Peeked::new(b.into_iter()),
+    }
+}
+
+/// Lazily yields the intersection of two sorted iterators as multisets: each element occurs as
+/// many times as in whichever input has fewer of it. The elements are taken from `a`.
+pub fn multiset_intersection<I, J>(a: I, b: J) -> MultisetIntersection<I::IntoIter, J::IntoIter>
+    where I: IntoIterator,
+          J: IntoIterator<Item = I::Item>,
+          I::Item: Ord
+{
+    MultisetIntersection {
+        a: Peeked::new(a.into_iter()),
+        b: Peeked::new(b.into_iter()),
+    }
+}
+
+/// Lazily yields the difference of two sorted iterators as multisets: each element of `a` occurs
+/// as many times as it does in `a` beyond its occurrences in `b`.
+pub fn multiset_difference<I, J>(a: I, b: J) -> MultisetDifference<I::IntoIter, J::IntoIter>
+    where I: IntoIterator,
+          J: IntoIterator<Item = I::Item>,
+          I::Item: Ord
+{
+    MultisetDifference {
+        a: Peeked::new(a.into_iter()),
+        b: Peeked::new(b.into_iter()),
+    }
+}
+
+/// An iterator over the multiset union of two sorted iterators.
+#[derive(Debug, Clone)]
+pub struct MultisetUnion<I: Iterator, J: Iterator<Item = I::Item>> {
+    a: Peeked<I, I::Item>,
+    b: Peeked<J, I::Item>,
+}
+
+impl<I, J> Iterator for MultisetUnion<I, J>
+    where I: Iterator,
+          J: Iterator<Item = I::Item>,
+          I::Item: Ord
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let ordering = match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => Less,
+            (None, _) => Greater,
+        };
+        match ordering {
+            Less => self.a.next(),
+            Greater => self.b.next(),
+            Equal => {
+                // An occurrence in `b` is matched by this one from `a`.
+                self.b.next();
+                self.a.next()
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a_lower, a_upper) = self.a.size_hint();
+        let (b_lower, b_upper) = self.b.size_hint();
+        let upper = match (a_upper, b_upper) {
+            (Some(a), Some(b)) => a.checked_add(b),
+            _ => None,
+        };
+        (a_lower.max(b_lower), upper)
+    }
+}
+
+/// An iterator over the multiset intersection of two sorted iterators.
+#[derive(Debug, Clone)]
+pub struct MultisetIntersection<I: Iterator, J: Iterator<Item = I::Item>> {
+    a: Peeked<I, I::Item>,
+    b: Peeked<J, I::Item>,
+}
+
+impl<I, J> Iterator for MultisetIntersection<I, J>
+    where I: Iterator,
+          J: Iterator<Item = I::Item>,
+          I::Item: Ord
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        loop {
+            let ordering = match (self.a.peek(), self.b.peek()) {
+                (Some(a), Some(b)) => a.cmp(b),
+                _ => return None,
+            };
+            match ordering {
+                Less => {
+                    self.a.next();
+                }
+                Greater => {
+                    self.b.next();
+                }
+                Equal => {
+                    self.b.next();
+                    return self.a.next();
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let upper = match (self.a.size_hint().1, self.b.size_hint().1) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        (0, upper)
+    }
+}
+
+/// An iterator over the multiset difference of two sorted iterators.
+#[derive(Debug, Clone)]
+pub struct MultisetDifference<I: Iterator, J: Iterator<Item = I::Item>> {
+    a: Peeked<I, I::Item>,
+    b: Peeked<J, I::Item>,
+}
+
+impl<I, J> Iterator for MultisetDifference<I, J>
+    where I: Iterator,
+          J: Iterator<Item = I::Item>,
+          I::Item: Ord
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        loop {
+            let next = self.a.next()?;
+            self.b.skip_less(&next);
+            if self.b.peek() != Some(&next) {
+                return Some(next);
+            }
+            // This occurrence is cancelled out by one in `b`.
+            self.b.next();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let lower = self.a.size_hint().0.saturating_sub(self.b.size_hint().1.unwrap_or(usize::MAX));
+        (lower, self.a.size_hint().1)
+    }
+}
+
+/// Like `std::iter::Peekable`, but with the item type as a parameter so that the combinators in
+/// this module can derive `Debug` and `Clone`.
+#[derive(Debug, Clone)]
+pub(crate) struct Peeked<I, T> {
+    iter: I,
+    peeked: Option<Option<T>>,
+}
+
+impl<I: Iterator<Item = T>, T> Peeked<I, T> {
+    pub(crate) fn new(iter: I) -> Peeked<I, T> {
+        Peeked {
+            iter,
+            peeked: None,
+        }
+    }
+
+    pub(crate) fn peek(&mut self) -> Option<&T> {
+        let iter = &mut self.iter;
+        self.peeked.get_or_insert_with(|| iter.next()).as_ref()
+    }
+
+    pub(crate) fn next(&mut self) -> Option<T> {
+        match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.iter.next(),
+        }
+    }
+
+    /// Skips over the elements equal to `x`, assuming they're next.
+    pub(crate) fn skip_equal(&mut self, x: &T)
+        where T: PartialEq
+    {
+        while self.peek() == Some(x) {
+            self.next();
+        }
+    }
+
+    /// Skips over the elements less than `x`, assuming they're next.
+    pub(crate) fn skip_less(&mut self, x: &T)
+        where T: Ord
+    {
+        while self.peek().is_some_and(|el| el < x) {
+            self.next();
+        }
+    }
+
+    pub(crate) fn size_hint(&self) -> (usize, Option<usize>) {
+        let peeked = match self.peeked {
+            Some(Some(_)) => 1,
+            Some(None) => return (0, Some(0)),
+            None => 0,
+        };
+        let (lower, upper) = self.iter.size_hint();
+        (lower.saturating_add(peeked), upper.and_then(|upper| upper.checked_add(peeked)))
+    }
+}
+
+#[test]
+fn merge_sorters() {
+    use super::{LazySortIterator, LazySorted};
+
+    let a = [5, 1, 9, 3, 7, 3];
+    let b = [4, 8, 2, 6, 0, 3];
+    let merged: Vec<_> = a.iter().cloned().quick_sort().merge(b.iter().cloned().heap_sort())
+                          .collect();
+    let mut expected: Vec<_> = a.iter().chain(&b).cloned().collect();
+    expected.sort();
+    assert_eq!(merged, expected);
+    assert_eq!(merge(vec![1, 2], Vec::new()).collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+fn kmerge_sorters() {
+    use super::LazySortIterator;
+
+    let inputs: Vec<Vec<u32>> = (0..7).map(|i| (0..i * 13).map(|j| (j * 31 + i) % 50).collect())
+                                     .collect();
+    let merged: Vec<_> = kmerge(inputs.iter().map(|v| v.iter().cloned().quick_sort())).collect();
+    let mut expected: Vec<_> = inputs.iter().flat_map(|v| v.iter().cloned()).collect();
+    expected.sort();
+    assert_eq!(merged, expected);
+    assert_eq!(kmerge(Vec::<Vec<u32>>::new()).next(), None);
+    assert_eq!(kmerge(vec![vec![2, 3], vec![1]]).size_hint(), (3, Some(3)));
+}
+
+#[test]
+fn kmerge_stable() {
+    use std::cmp::Ordering;
+
+    #[derive(Debug)]
+    struct Tagged(u32, char);
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Tagged) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Tagged) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Tagged) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let inputs = vec![vec![Tagged(1, 'a'), Tagged(2, 'a')],
+                      vec![Tagged(1, 'b')],
+                      vec![Tagged(0, 'c'), Tagged(1, 'c')]];
+    let tags: String = kmerge(inputs).map(|el| el.1).collect();
+    assert_eq!(tags, "cabca");
+}
+
+#[test]
+fn set_operations() {
+    use super::{LazySortIterator, LazySorted};
+
+    let a = [5, 1, 9, 3, 7, 3, 3];
+    let b = [4, 8, 3, 6, 0, 9, 9];
+    let quick = |v: &[i32]| v.iter().cloned().quick_sort();
+    assert_eq!(quick(&a).union(quick(&b)).collect::<Vec<_>>(),
+               vec![0, 1, 3, 4, 5, 6, 7, 8, 9]);
+    assert_eq!(quick(&a).intersection(quick(&b)).collect::<Vec<_>>(), vec![3, 9]);
+    assert_eq!(quick(&a).difference(quick(&b)).collect::<Vec<_>>(), vec![1, 5, 7]);
+    assert_eq!(difference(quick(&b), Vec::new()).collect::<Vec<_>>(), vec![0, 3, 4, 6, 8, 9]);
+    assert_eq!(quick(&a).multiset_union(quick(&b)).collect::<Vec<_>>(),
+               vec![0, 1, 3, 3, 3, 4, 5, 6, 7, 8, 9, 9]);
+    assert_eq!(quick(&a).multiset_intersection(quick(&b)).collect::<Vec<_>>(), vec![3, 9]);
+    assert_eq!(quick(&a).multiset_difference(quick(&b)).collect::<Vec<_>>(),
+               vec![1, 3, 3, 5, 7]);
+    assert_eq!(multiset_intersection(vec![1, 1, 2, 2, 2], vec![1, 2, 2, 3]).collect::<Vec<_>>(),
+               vec![1, 2, 2]);
+    assert_eq!(quick(&a).symmetric_difference(quick(&b)).collect::<Vec<_>>(),
+               vec![(Side::Right, 0),
+                    (Side::Left, 1),
+                    (Side::Right, 4),
+                    (Side::Left, 5),
+                    (Side::Right, 6),
+                    (Side::Left, 7),
+                    (Side::Right, 8)]);
+}
@@ -0,0 +1,163 @@
+//! Sorting input that arrives interleaved with output.
+
+use std::collections::BinaryHeap;
+use std::iter::{FromIterator, FusedIterator};
+use super::{HeapSort, ReverseOrder};
+
+/// A sorter that accepts new elements at any time.
+///
+/// Every [`pop_min`](#method.pop_min) returns the smallest of the elements pushed and not yet
+/// popped, so pushes and pops can be interleaved freely. Both cost `O(log n)`. Iterating pops
+/// until the sorter is empty.
+#[derive(Debug, Clone)]
+pub struct OnlineSorter<T>(BinaryHeap<ReverseOrder<T>>);
+
+impl<T: Ord> Default for OnlineSorter<T> {
+    fn default() -> OnlineSorter<T> {
+        OnlineSorter::new()
+    }
+}
+
+impl<T: Ord> OnlineSorter<T> {
+    /// Returns an empty sorter.
+    pub fn new() -> OnlineSorter<T> {
+        OnlineSorter(BinaryHeap::new())
+    }
+
+    /// Adds an element.
+    pub fn push(&mut self, el: T) {
+        self.0.push(ReverseOrder(el));
+    }
+
+    /// Removes and returns the smallest element, or `None` if the sorter is empty.
+    pub fn pop_min(&mut self) -> Option<T> {
+        self.0.pop().map(|ReverseOrder(el)| el)
+    }
+
+    /// Returns the smallest element without removing it.
+    pub fn peek_min(&self) -> Option<&T> {
+        self.0.peek().map(|el| &el.0)
+    }
+
+    /// Returns the number of elements in the sorter.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the sorter is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T: Ord> Iterator for OnlineSorter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.pop_min()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+impl<T: Ord> Extend<T> for OnlineSorter<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.0.extend(iter.into_iter().map(ReverseOrder));
+    }
+}
+
+impl<T: Ord> FromIterator<T> for OnlineSorter<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> OnlineSorter<T> {
+        OnlineSorter(iter.into_iter().map(ReverseOrder).collect())
+    }
+}
+
+impl<T: Ord> From<HeapSort<T>> for OnlineSorter<T> {
+    fn from(sorter: HeapSort<T>) -> OnlineSorter<T> {
+        OnlineSorter(sorter.heap)
+    }
+}
+
+/// An iterator that sorts input in which no element is more than `max_disorder` positions away
+/// from where it belongs.
+///
+/// Only `max_disorder + 1` elements are buffered, and each costs `O(log max_disorder)`. Created by
+/// [`watermark_sort`](trait.LazySortIterator.html#method.watermark_sort). If the input is more out
+/// of order than promised, the output isn't sorted.
+#[derive(Debug, Clone)]
+pub struct WatermarkSort<I: Iterator> {
+    iter: I,
+    buffer: OnlineSorter<I::Item>,
+    max_disorder: usize,
+    done: bool,
+}
+
+impl<I> WatermarkSort<I>
+    where I: Iterator,
+          I::Item: Ord
+{
+    pub(crate) fn new(iter: I, max_disorder: usize) -> WatermarkSort<I> {
+        WatermarkSort {
+            iter,
+            buffer: OnlineSorter::new(),
+            max_disorder,
+            done: false,
+        }
+    }
+}
+
+impl<I> Iterator for WatermarkSort<I>
+    where I: Iterator,
+          I::Item: Ord
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        while !self.done && self.buffer.len() <= self.max_disorder {
+            match self.iter.next() {
+                Some(el) => self.buffer.push(el),
+                None => self.done = true,
+            }
+        }
+        self.buffer.pop_min()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = if self.done { (0, Some(0)) } else { self.iter.size_hint() };
+        let buffered = self.buffer.len();
+        (lower.saturating_add(buffered), upper.and_then(|upper| upper.checked_add(buffered)))
+    }
+}
+
+impl<I> FusedIterator for WatermarkSort<I>
+    where I: Iterator,
+          I::Item: Ord
+{ }
+
+#[test]
+fn online_sorter() {
+    let mut sorter = OnlineSorter::new();
+    sorter.push(5);
+    sorter.push(3);
+    assert_eq!(sorter.pop_min(), Some(3));
+    sorter.push(1);
+    sorter.push(4);
+    assert_eq!(sorter.peek_min(), Some(&1));
+    assert_eq!(sorter.pop_min(), Some(1));
+    sorter.extend(vec![2, 6]);
+    assert_eq!(sorter.len(), 4);
+    assert_eq!(sorter.collect::<Vec<_>>(), vec![2, 4, 5, 6]);
+}
+
+#[test]
+fn watermark_sort() {
+    use super::LazySortIterator;
+
+    let v = [1, 0, 3, 2, 4, 7, 5, 6, 9, 8];
+    assert_eq!(v.iter().cloned().watermark_sort(2).collect::<Vec<_>>(),
+               (0..10).collect::<Vec<_>>());
+    assert_eq!(v.iter().cloned().watermark_sort(0).collect::<Vec<_>>(), v);
+    assert_eq!(v.iter().cloned().watermark_sort(2).size_hint(), (10, Some(10)));
+}
@@ -0,0 +1,181 @@
+//! Lazily sorting one shared buffer in several orders.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::sync::Arc;
+use super::{LazySortIterator, LazySorted, QuickSort};
+
+/// A buffer of elements collected once and shared by any number of lazy sorts of its indices,
+/// each in its own order.
+///
+/// Rather than a copy of each element, each sort holds its index along with a clone of an `Arc`
+/// of the buffer and the sort's comparator, so starting a sort costs an atomic increment per
+/// element, and its elements take two words each. The sorts can be consumed independently of
+/// each other.
+///
+/// ```
+/// use lazy_sort::SortOrders;
+///
+/// let rows = SortOrders::new(vec![("carol", 31), ("alice", 45), ("bob", 27)]);
+/// let by_name: Vec<_> = rows.sorted_by_key(|&(name, _)| name).collect();
+/// let oldest: Vec<_> = rows.sorted_by(|a, b| b.1.cmp(&a.1)).take(2).collect();
+/// assert_eq!(by_name, [1, 2, 0]);
+/// assert_eq!(oldest, [1, 0]);
+/// assert_eq!(rows.data()[oldest[0]], ("alice", 45));
+/// ```
+#[derive(Debug)]
+pub struct SortOrders<T>(Arc<[T]>);
+
+impl<T> SortOrders<T> {
+    /// Collects `iter` into a shared buffer.
+    pub fn new<I>(iter: I) -> SortOrders<T>
+        where I: IntoIterator<Item = T>
+    {
+        SortOrders(iter.into_iter().collect())
+    }
+
+    /// Returns the shared buffer, which the sorts' indices index into.
+    pub fn data(&self) -> &Arc<[T]> {
+        &self.0
+    }
+
+    /// Lazily sorts the indices of the buffer by comparing their elements with `compare`, which
+    /// must be a total order. The order of indices of equal elements is unspecified.
+    pub fn sorted_by<F>(&self, compare: F) -> IndexSort<T, F>
+        where F: Fn(&T, &T) -> Ordering
+    {
+        let order = Arc::new(Order {
+            data: self.0.clone(),
+            compare,
+        });
+        IndexSort((0..self.0.len()).map(|index| {
+                                        ByOrder {
+                                            index,
+                                            order: order.clone(),
+                                        }
+                                    })
+                                    .quick_sort())
+    }
+
+    /// Lazily sorts the indices of the buffer by the key that `key` extracts from their elements.
+    pub fn sorted_by_key<K, F>(&self, key: F) -> IndexSort<T, impl Fn(&T, &T) -> Ordering>
+        where K: Ord,
+              F: Fn(&T) -> K
+    {
+        self.sorted_by(move |a, b| key(a).cmp(&key(b)))
+    }
+}
+
+impl<T> Clone for SortOrders<T> {
+    fn clone(&self) -> SortOrders<T> {
+        SortOrders(self.0.clone())
+    }
+}
+
+impl<T> From<Arc<[T]>> for SortOrders<T> {
+    fn from(data: Arc<[T]>) -> SortOrders<T> {
+        SortOrders(data)
+    }
+}
+
+/// The shared buffer along with the comparator of one sort.
+struct Order<T, F> {
+    data: Arc<[T]>,
+    compare: F,
+}
+
+/// An index into the shared buffer, ordered by its element under the sort's comparator.
+struct ByOrder<T, F> {
+    index: usize,
+    order: Arc<Order<T, F>>,
+}
+
+impl<T, F> Clone for ByOrder<T, F> {
+    fn clone(&self) -> Self {
+        ByOrder {
+            index: self.index,
+            order: self.order.clone(),
+        }
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Ord for ByOrder<T, F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let data = &self.order.data;
+        (self.order.compare)(&data[self.index], &data[other.index])
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> PartialOrd for ByOrder<T, F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> PartialEq for ByOrder<T, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Eq for ByOrder<T, F> {}
+
+/// An iterator that lazily yields the indices of a shared buffer in the order of one comparator,
+/// created by [`SortOrders::sorted_by`](struct.SortOrders.html#method.sorted_by).
+pub struct IndexSort<T, F>(QuickSort<ByOrder<T, F>>);
+
+impl<T, F: Fn(&T, &T) -> Ordering> IndexSort<T, F> {
+    /// Returns the next index in sorted order without consuming it.
+    pub fn peek(&mut self) -> Option<usize> {
+        self.0.peek().map(|el| el.index)
+    }
+}
+
+impl<T, F> Clone for IndexSort<T, F> {
+    fn clone(&self) -> Self {
+        IndexSort(self.0.clone())
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> fmt::Debug for IndexSort<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IndexSort").field("remaining", &self.0.len()).finish()
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Iterator for IndexSort<T, F> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.0.next().map(|el| el.index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> ExactSizeIterator for IndexSort<T, F> { }
+
+impl<T, F: Fn(&T, &T) -> Ordering> FusedIterator for IndexSort<T, F> { }
+
+#[test]
+fn sort_orders() {
+    use std::vec::Vec;
+
+    let orders = SortOrders::new((0..100u32).map(|i| (i * 37 % 100, i % 7)));
+    let mut by_first = orders.sorted_by_key(|&(a, _)| a);
+    let by_second = orders.sorted_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    assert_eq!(by_first.peek(), Some(0));
+    assert_eq!(by_second.len(), 100);
+    let mut expected = orders.data().to_vec();
+    expected.sort();
+    let first: Vec<_> = by_first.by_ref().take(3).map(|i| orders.data()[i]).collect();
+    assert_eq!(first, expected[..3]);
+    expected.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let second: Vec<_> = by_second.map(|i| orders.data()[i]).collect();
+    assert_eq!(second, expected);
+    assert_eq!(by_first.len(), 97);
+    assert_eq!(Arc::strong_count(orders.data()), 2);
+}
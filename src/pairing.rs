@@ -0,0 +1,223 @@
+//! A lazy heapsort backed by a pairing heap, which can meld with another in `O(1)`.
+
+use std::fmt;
+use std::iter::{FromIterator, FusedIterator};
+use std::mem;
+use std::vec::Vec;
+use super::{split_at_rank, LazySorted};
+
+/// A lazy heapsort whose heap can absorb another in constant time.
+///
+/// Compared to [`HeapSort`](struct.HeapSort.html), building the heap and taking each element cost
+/// `O(1)` and amortized `O(log n)` respectively, but with more allocation and pointer-chasing.
+/// [`meld`](#method.meld) makes it the better choice when partially consumed sorters are merged
+/// often.
+pub struct PairingHeapSort<T> {
+    root: Option<Node<T>>,
+    len: usize,
+}
+
+struct Node<T> {
+    el: T,
+    children: Vec<Node<T>>,
+}
+
+/// Makes the node with the larger element a child of the other, favouring `a` on ties.
+fn meld_nodes<T: Ord>(mut a: Node<T>, mut b: Node<T>) -> Node<T> {
+    if b.el < a.el {
+        b.children.push(a);
+        b
+    } else {
+        a.children.push(b);
+        a
+    }
+}
+
+impl<T: Ord> PairingHeapSort<T> {
+    /// Returns an empty sorter.
+    pub fn new() -> PairingHeapSort<T> {
+        PairingHeapSort {
+            root: None,
+            len: 0,
+        }
+    }
+
+    /// Adds an element.
+    pub fn push(&mut self, el: T) {
+        self.meld_node(Node {
+            el,
+            children: Vec::new(),
+        });
+        self.len += 1;
+    }
+
+    /// Moves all of `other`'s remaining elements into this sorter in `O(1)`.
+    pub fn meld(&mut self, mut other: PairingHeapSort<T>) {
+        if let Some(root) = other.root.take() {
+            self.meld_node(root);
+            self.len += mem::replace(&mut other.len, 0);
+        }
+    }
+
+    fn meld_node(&mut self, node: Node<T>) {
+        self.root = Some(match self.root.take() {
+            Some(root) => meld_nodes(root, node),
+            None => node,
+        });
+    }
+}
+
+impl<T> PairingHeapSort<T> {
+    /// Returns the number of bytes of heap memory held by the sorter's nodes, counting the full
+    /// capacity of their child lists but not any memory owned by the elements.
+    pub fn memory_usage(&self) -> usize {
+        let mut bytes = 0;
+        let mut stack: Vec<&Node<T>> = self.root.iter().collect();
+        while let Some(node) = stack.pop() {
+            bytes += node.children.capacity() * mem::size_of::<Node<T>>();
+            stack.extend(&node.children);
+        }
+        bytes
+    }
+
+    /// Visits every remaining element in no particular order.
+    fn for_each_ref<'a, F: FnMut(&'a T)>(&'a self, mut f: F) {
+        let mut stack: Vec<&Node<T>> = self.root.iter().collect();
+        while let Some(node) = stack.pop() {
+            f(&node.el);
+            stack.extend(&node.children);
+        }
+    }
+}
+
+impl<T: Ord> Default for PairingHeapSort<T> {
+    fn default() -> PairingHeapSort<T> {
+        PairingHeapSort::new()
+    }
+}
+
+// The heap can be a path as long as the sorter, so dropping, cloning, and formatting it walk the
+// nodes with an explicit stack rather than recursing.
+impl<T> Drop for PairingHeapSort<T> {
+    fn drop(&mut self) {
+        let mut stack: Vec<Node<T>> = self.root.take().into_iter().collect();
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+        }
+    }
+}
+
+impl<T: Ord + Clone> Clone for PairingHeapSort<T> {
+    fn clone(&self) -> PairingHeapSort<T> {
+        let mut clone = PairingHeapSort::new();
+        self.for_each_ref(|el| clone.push(el.clone()));
+        clone
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PairingHeapSort<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut list = f.debug_list();
+        self.for_each_ref(|el| {
+            list.entry(el);
+        });
+        list.finish()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for PairingHeapSort<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> PairingHeapSort<T> {
+        let mut sorter = PairingHeapSort::new();
+        sorter.extend(iter);
+        sorter
+    }
+}
+
+impl<T: Ord> Extend<T> for PairingHeapSort<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for el in iter {
+            self.push(el);
+        }
+    }
+}
+
+impl<T: Ord> Iterator for PairingHeapSort<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let Node { el, children } = self.root.take()?;
+        self.len -= 1;
+        // Meld the children in pairs from the left, then meld the pairs from the right.
+        let mut pairs = Vec::with_capacity(children.len().div_ceil(2));
+        let mut children = children.into_iter();
+        while let Some(a) = children.next() {
+            pairs.push(match children.next() {
+                Some(b) => meld_nodes(a, b),
+                None => a,
+            });
+        }
+        self.root = pairs.into_iter().rev().reduce(|right, left| meld_nodes(left, right));
+        Some(el)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T: Ord> FusedIterator for PairingHeapSort<T> { }
+
+impl<T: Ord> LazySorted<T> for PairingHeapSort<T> {
+    fn peek(&mut self) -> Option<&T> {
+        self.root.as_ref().map(|root| &root.el)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn into_inner(mut self) -> Vec<T> {
+        let mut v = Vec::with_capacity(self.len);
+        let mut stack: Vec<Node<T>> = self.root.take().into_iter().collect();
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+            v.push(node.el);
+        }
+        v
+    }
+
+    fn split_at_rank(self, k: usize) -> (PairingHeapSort<T>, PairingHeapSort<T>) {
+        let (smallest, rest) = split_at_rank(self.into_inner(), k);
+        (smallest.into_iter().collect(), rest.into_iter().collect())
+    }
+
+    fn modify_remaining<F>(&mut self, f: F)
+        where F: FnOnce(&mut [T])
+    {
+        let mut v = mem::take(self).into_inner();
+        f(&mut v);
+        self.extend(v);
+    }
+}
+
+#[test]
+fn pairing_heap_sort() {
+    use super::LazySortIterator;
+
+    let v = [2, 4, 2, 5, 8, 4, 3, 4, 6];
+    let mut expected = v.to_vec();
+    expected.sort();
+    let sorted = v.iter().cloned().pairing_heap_sort();
+    assert_eq!(sorted.clone().collect::<Vec<_>>(), expected);
+
+    let (mut smallest, rest) = sorted.split_at_rank(4);
+    assert_eq!(smallest.next(), Some(2));
+    smallest.meld(rest);
+    assert_eq!(smallest.len(), 8);
+    assert_eq!(smallest.collect::<Vec<_>>(), &expected[1..]);
+
+    // A descending input builds a single path, which must not overflow the stack.
+    let long: PairingHeapSort<_> = (0..200_000).rev().collect();
+    drop(long.clone());
+    assert_eq!(long.take(3).collect::<Vec<_>>(), [0, 1, 2]);
+}
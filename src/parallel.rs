@@ -0,0 +1,199 @@
+//! Parallel construction of lazy sorters with rayon.
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::BinaryHeap;
+use super::heap::{self, sift_down_ptr};
+use super::{unbalanced, Config, HeapSort, QuickSort, QuickSortInternal, Recursive, ReverseOrder};
+
+/// Partitions smaller than this are left to the sequential sorter.
+const PARALLEL_THRESHOLD: usize = 1 << 14;
+
+/// Extends rayon's parallel iterators with lazy sorts whose setup runs in parallel.
+pub trait ParallelLazySortIterator: ParallelIterator
+    where Self::Item: Ord + Sync
+{
+    /// Collects the input in parallel and partitions it in parallel until the partition holding
+    /// the smallest elements is small, then lazily quicksorts the rest sequentially.
+    ///
+    /// Partitioning towards the smallest elements is the `O(n)` work that the first call to
+    /// `next` would otherwise do on one thread.
+    fn par_quick_sort(self) -> QuickSort<Self::Item> {
+        QuickSort::partitioned(self.collect(), Config::default(), |v, config| {
+            par_partition(v, config, 0)
+        })
+    }
+
+    /// Collects the input in parallel and builds the heap for a lazy heapsort in parallel.
+    ///
+    /// The subtrees below the top few levels of the heap are heapified on separate threads, then
+    /// the top levels are sifted down on one. `BinaryHeap` checks the result once more when it
+    /// takes ownership, which costs a comparison per element but moves nothing.
+    fn par_heap_sort(self) -> HeapSort<Self::Item> {
+        let mut v: Vec<_> = self.map(ReverseOrder).collect();
+        par_heapify(&mut v);
+        HeapSort::from_reversed(v)
+    }
+
+    /// Returns the `k` smallest elements in sorted order.
+    ///
+    /// Each thread keeps the `k` smallest elements of its share of the input in a bounded heap,
+    /// and the heaps are merged pairwise, so this takes `O(n log k)` work and `O(k)` memory per
+    /// thread.
+    fn smallest(self, k: usize) -> Vec<Self::Item> {
+        if k == 0 {
+            return Vec::new();
+        }
+        self.fold(BinaryHeap::new, |mut heap, el| {
+                push_bounded(&mut heap, el, k);
+                heap
+            })
+            .reduce(BinaryHeap::new, |a, b| {
+                let (mut larger, smaller) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+                for el in smaller {
+                    push_bounded(&mut larger, el, k);
+                }
+                larger
+            })
+            .into_sorted_vec()
+    }
+}
+
+impl<I> ParallelLazySortIterator for I
+    where I: ParallelIterator,
+          I::Item: Ord + Sync
+{ }
+
+/// Adds `el` to a max-heap of the `k` smallest elements seen so far.
+fn push_bounded<T: Ord>(heap: &mut BinaryHeap<T>, el: T, k: usize) {
+    if heap.len() < k {
+        heap.push(el);
+    } else if heap.peek().is_some_and(|max| el < *max) {
+        *heap.peek_mut().unwrap() = el;
+    }
+}
+
+/// A pointer to a heap whose disjoint subtrees are sifted on different threads.
+struct HeapPtr<T>(*mut T);
+
+unsafe impl<T: Send> Send for HeapPtr<T> {}
+unsafe impl<T: Send> Sync for HeapPtr<T> {}
+
+/// Rearranges a large `v` into a max-heap, heapifying the subtrees below the top levels in
+/// parallel.
+fn par_heapify<T: Ord + Send + Sync>(v: &mut [T]) {
+    let len = v.len();
+    // `BinaryHeap::from` heapifies small inputs well enough itself.
+    if len < 2 * PARALLEL_THRESHOLD {
+        return;
+    }
+    // The subtrees rooted at this depth are heapified in parallel. Each holds at least
+    // PARALLEL_THRESHOLD elements.
+    let mut depth = 0;
+    let subtrees = 4 * rayon::current_num_threads();
+    while (1 << depth) < subtrees && len >> (depth + 1) >= PARALLEL_THRESHOLD {
+        depth += 1;
+    }
+    let heap = HeapPtr(v.as_mut_ptr());
+    ((1 << depth) - 1..(2 << depth) - 1).into_par_iter().for_each(|root| {
+        let heap = &heap;
+        // The nodes of the subtree at each level form a contiguous range, the deepest of which
+        // are sifted first.
+        let mut levels = Vec::new();
+        let (mut first, mut count) = (root, 1usize);
+        while first < len / 2 {
+            levels.push((first, count));
+            first = 2 * first + 1;
+            count *= 2;
+        }
+        for (first, count) in levels.into_iter().rev() {
+            for i in (first..(first + count).min(len / 2)).rev() {
+                // Safety: sifting down only touches the descendants of `i`, which are in the
+                // subtree of `root`, and no two threads sift within the same subtree.
+                unsafe { sift_down_ptr(heap.0, len, i, |a, b| a > b) };
+            }
+        }
+    });
+    for i in (0..(1 << depth) - 1).rev() {
+        heap::sift_down_by(v, i, |a, b| a > b);
+    }
+}
+
+/// Builds the partitions that the first call to `next` would, splitting each one in parallel.
+fn par_partition<T>(mut v: Vec<T>, config: &mut Config, depth: usize) -> QuickSortInternal<T>
+    where T: Ord + Send + Sync
+{
+    if v.len() <= PARALLEL_THRESHOLD.max(config.threshold) {
+        return QuickSortInternal::at_depth(v, config, depth);
+    }
+    let len = v.len();
+    let pivot = v.swap_remove(config.choose_pivot(&v, None));
+    // Like the sequential partition, elements equal to the pivot go with the lesser ones.
+    let (mut less, mut greater): (Vec<T>, Vec<T>) =
+        v.into_par_iter().partition(|el| *el <= pivot);
+    if unbalanced(len, less.len()) {
+        // The sequential split redoes it around the median.
+        less.append(&mut greater);
+        less.push(pivot);
+        return QuickSortInternal::at_depth(less, config, depth);
+    }
+    config.count(len - 1, len);
+    config.count_partition(len, 2);
+    config.record_split(depth, len, greater.len(), less.len());
+    greater.push(pivot);
+    let less = par_partition(less, config, depth + 1);
+    QuickSortInternal::Recursive(Recursive::with_less(greater, Some(Box::new(less)), depth))
+}
+
+#[test]
+fn par_quick_sort() {
+    use super::rng::XorShift;
+
+    let mut rng = XorShift::new(3);
+    let v: Vec<u64> = (0..100_000).map(|_| rng.next_u64() % 1000).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    let sorted = v.into_par_iter().par_quick_sort();
+    assert_eq!(sorted.size_hint(), (expected.len(), Some(expected.len())));
+    assert_eq!(sorted.collect::<Vec<_>>(), expected);
+    assert_eq!(Vec::<u64>::new().into_par_iter().par_quick_sort().next(), None);
+}
+
+#[cfg(feature = "diagnostics")]
+#[test]
+fn par_quick_sort_diagnostics() {
+    use super::rng::XorShift;
+    use super::Diagnostics;
+
+    let mut rng = XorShift::new(5);
+    let v: Vec<u64> = (0..200_000).map(|_| rng.next_u64()).collect();
+    let mut sorted = v.into_par_iter().par_quick_sort();
+    assert!(sorted.by_ref().take(1000).is_sorted());
+    let report = *sorted.diagnostics();
+    // The partitions split in parallel count towards the depth of those nested in them.
+    assert!(report.max_depth > 0 && report.max_depth <= Diagnostics::depth_limit(200_000));
+    assert_eq!(report.fallback_triggers, 0);
+}
+
+#[test]
+fn par_heap_sort() {
+    use super::rng::XorShift;
+
+    let mut rng = XorShift::new(5);
+    for &len in &[0, 1, 1000, 300_000] {
+        let v: Vec<u64> = (0..len).map(|_| rng.next_u64() % 10_000).collect();
+        let mut expected = v.clone();
+        expected.sort();
+        let sorted = v.into_par_iter().par_heap_sort();
+        assert_eq!(sorted.collect::<Vec<_>>(), expected);
+    }
+}
+
+#[test]
+fn par_smallest() {
+    use rayon::iter::IntoParallelRefIterator;
+
+    let v: Vec<u32> = (0..50_000).map(|i| i * 7919 % 50_000).collect();
+    assert_eq!(v.par_iter().cloned().smallest(5), [0, 1, 2, 3, 4]);
+    assert_eq!(v.par_iter().cloned().smallest(0), []);
+    assert_eq!((0..3).into_par_iter().smallest(10), [0, 1, 2]);
+}
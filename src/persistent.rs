@@ -0,0 +1,195 @@
+//! A lazy quicksort whose partitions are shared between clones.
+
+use std::cmp::Ordering;
+use std::iter::{FromIterator, FusedIterator};
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::vec::Vec;
+
+/// Partitions of at most this many elements are sorted outright.
+const THRESHOLD: usize = 16;
+
+/// A lazy quicksort that can be cloned in `O(1)`, with clones sharing the sorting work.
+///
+/// The remaining partitions form a persistent list of shared nodes. A node is split at most
+/// once, by whichever clone reaches it first, and every other clone that reaches it reuses the
+/// split, so divergent consumers of a partially sorted state only pay for the partitions they
+/// alone need. Elements are cloned out of the shared partitions as they're yielded.
+///
+/// A comparison that panics while one clone is splitting a partition poisons that partition,
+/// which makes other clones panic when they reach it.
+///
+/// ```
+/// use lazy_sort::PersistentSort;
+///
+/// let mut sorter: PersistentSort<_> = vec![5, 3, 9, 1, 7].into_iter().collect();
+/// assert_eq!(sorter.next(), Some(1));
+/// let branch = sorter.clone();
+/// assert_eq!(sorter.collect::<Vec<_>>(), [3, 5, 7, 9]);
+/// assert_eq!(branch.take(2).collect::<Vec<_>>(), [3, 5]);
+/// ```
+#[derive(Debug)]
+pub struct PersistentSort<T> {
+    list: Option<Arc<List<T>>>,
+    /// How many elements of the first node, once it's sorted, have already been yielded.
+    offset: usize,
+    len: usize,
+}
+
+/// The remaining nodes, in the order they'll be yielded.
+#[derive(Debug)]
+struct List<T> {
+    node: Arc<Node<T>>,
+    rest: Option<Arc<List<T>>>,
+}
+
+#[derive(Debug)]
+struct Node<T>(Mutex<State<T>>);
+
+#[derive(Debug)]
+enum State<T> {
+    Unsplit(Vec<T>),
+    /// The elements in ascending order.
+    Sorted(Vec<T>),
+    /// The elements less than, equal to, and greater than a pivot.
+    Split([Arc<Node<T>>; 3]),
+}
+
+impl<T: Ord> Node<T> {
+    fn new(mut v: Vec<T>) -> Arc<Node<T>> {
+        let state = if v.len() > THRESHOLD {
+            State::Unsplit(v)
+        } else {
+            v.sort_unstable();
+            State::Sorted(v)
+        };
+        Arc::new(Node(Mutex::new(state)))
+    }
+}
+
+/// Splits `v` three ways around its middle element.
+fn split<T: Ord>(mut v: Vec<T>) -> State<T> {
+    let pivot = v.swap_remove(v.len() / 2);
+    let (mut less, mut equal, mut greater) = (Vec::new(), Vec::new(), Vec::new());
+    for el in v {
+        match el.cmp(&pivot) {
+            Ordering::Less => less.push(el),
+            Ordering::Equal => equal.push(el),
+            Ordering::Greater => greater.push(el),
+        }
+    }
+    equal.push(pivot);
+    State::Split([Node::new(less), Arc::new(Node(Mutex::new(State::Sorted(equal)))),
+                  Node::new(greater)])
+}
+
+impl<T: Ord> PersistentSort<T> {
+    /// Returns a sorter over the elements of `v`.
+    pub fn new(v: Vec<T>) -> PersistentSort<T> {
+        let len = v.len();
+        PersistentSort {
+            list: Some(Arc::new(List {
+                node: Node::new(v),
+                rest: None,
+            })),
+            offset: 0,
+            len,
+        }
+    }
+}
+
+impl<T> Clone for PersistentSort<T> {
+    fn clone(&self) -> PersistentSort<T> {
+        PersistentSort {
+            list: self.list.clone(),
+            offset: self.offset,
+            len: self.len,
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for PersistentSort<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> PersistentSort<T> {
+        PersistentSort::new(iter.into_iter().collect())
+    }
+}
+
+impl<T: Ord + Clone> Iterator for PersistentSort<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            let list = self.list.clone()?;
+            let mut state = list.node.0.lock().expect("a comparison panicked in another clone");
+            if let State::Unsplit(ref mut v) = *state {
+                let v = mem::take(v);
+                *state = split(v);
+            }
+            match *state {
+                State::Sorted(ref v) if self.offset < v.len() => {
+                    let el = v[self.offset].clone();
+                    self.offset += 1;
+                    if self.offset == v.len() {
+                        self.list = list.rest.clone();
+                        self.offset = 0;
+                    }
+                    self.len -= 1;
+                    return Some(el);
+                }
+                State::Sorted(_) => self.list = list.rest.clone(),
+                State::Split(ref parts) => {
+                    self.list = parts.iter().rev().fold(list.rest.clone(), |rest, node| {
+                        Some(Arc::new(List {
+                            node: node.clone(),
+                            rest,
+                        }))
+                    });
+                }
+                State::Unsplit(_) => unreachable!(),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T: Ord + Clone> ExactSizeIterator for PersistentSort<T> { }
+
+impl<T: Ord + Clone> FusedIterator for PersistentSort<T> { }
+
+#[test]
+fn persistent_sort() {
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+    static COMPARISONS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Counted(u32);
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Counted) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Counted {
+        fn cmp(&self, other: &Counted) -> Ordering {
+            COMPARISONS.fetch_add(1, Relaxed);
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let v: Vec<_> = (0..2000).map(|i| Counted((i * 7919) % 1000)).collect();
+    let mut sorter = PersistentSort::new(v);
+    assert_eq!(sorter.by_ref().take(500).last(), Some(Counted(249)));
+    let branch = sorter.clone();
+    let sorted: Vec<_> = sorter.map(|c| c.0).collect();
+    let comparisons = COMPARISONS.load(Relaxed);
+    assert_eq!(sorted, (250..1000).flat_map(|i| vec![i, i]).collect::<Vec<_>>());
+    assert_eq!(branch.len(), 1500);
+    assert!(branch.map(|c| c.0).eq(sorted));
+    // The branch reuses every split made by the first consumer.
+    assert_eq!(COMPARISONS.load(Relaxed), comparisons);
+}
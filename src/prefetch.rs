@@ -0,0 +1,96 @@
+//! Software prefetching for partitions too large for the cache.
+//!
+//! Partitioning scans a partition from both ends at once. The hardware prefetcher follows each
+//! scan well enough for small elements, but once elements span several cache lines, touching
+//! each one stalls on memory, so the scans ask for the elements a few lines ahead of them.
+
+use std::mem;
+
+/// Partitions smaller than this many bytes are assumed to be in cache already.
+const MIN_BYTES: usize = 1 << 18;
+
+/// How far ahead of each scan's position to prefetch, in bytes.
+const DISTANCE_BYTES: usize = 512;
+
+/// Returns whether partitioning `v` is worth prefetching for.
+pub(crate) fn worthwhile<T>(v: &[T]) -> bool {
+    mem::size_of_val(v) >= MIN_BYTES
+}
+
+/// Hints that `el` will be read soon. This is only a hint, and does nothing on targets without a
+/// prefetch instruction.
+#[inline(always)]
+fn prefetch<T>(el: &T) {
+    #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"))]
+    unsafe {
+        #[cfg(target_arch = "x86")]
+        use std::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+        #[cfg(target_arch = "x86_64")]
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+        // Prefetching never faults, and `sse` is enabled, so this is always safe.
+        _mm_prefetch::<_MM_HINT_T0>(el as *const T as *const i8);
+    }
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        std::arch::asm!("prfm pldl1keep, [{}]",
+                        in(reg) el as *const T,
+                        options(nostack, readonly, preserves_flags));
+    }
+    #[cfg(not(any(all(any(target_arch = "x86", target_arch = "x86_64"),
+                      target_feature = "sse"),
+                  target_arch = "aarch64")))]
+    let _ = el;
+}
+
+/// Does the same as `partition`, but prefetches ahead of both scans.
+pub(crate) fn partition<T, F>(v: &mut [T], mut pred: F) -> usize
+    where F: FnMut(&T) -> bool
+{
+    let distance = (DISTANCE_BYTES / mem::size_of::<T>().max(1)).max(1);
+    let (mut front, mut back) = (0, v.len());
+    while front < back {
+        if let Some(ahead) = v.get(front + distance) {
+            prefetch(ahead);
+        }
+        if pred(&v[front]) {
+            front += 1;
+            continue;
+        }
+        loop {
+            back -= 1;
+            if back == front {
+                return front;
+            }
+            if let Some(ahead) = back.checked_sub(distance) {
+                prefetch(&v[ahead]);
+            }
+            if pred(&v[back]) {
+                break;
+            }
+        }
+        v.swap(front, back);
+        front += 1;
+    }
+    front
+}
+
+#[test]
+fn prefetched_partition() {
+    use super::LazySortBuilder;
+
+    let mut v: Vec<u32> = (0..1000).map(|i| (i * 7919) % 1009).collect();
+    let split = partition(&mut v, |&el| el > 500);
+    assert!(v[..split].iter().all(|&el| el > 500));
+    assert!(v[split..].iter().all(|&el| el <= 500));
+    assert_eq!(partition(&mut [1, 2, 3][..], |_| false), 0);
+    assert_eq!(partition(&mut [1, 2, 3][..], |_| true), 3);
+
+    // Elements of two cache lines each, in a partition well past `MIN_BYTES`.
+    let v: Vec<[u64; 16]> = (0..20_000u64).map(|i| [(i * 7919) % 20_011; 16]).collect();
+    assert!(worthwhile(&v));
+    let mut expected = v.clone();
+    expected.sort();
+    let sorted: Vec<_> = LazySortBuilder::new().prefetch(true).sort(v).unwrap().collect();
+    assert_eq!(sorted, expected);
+}
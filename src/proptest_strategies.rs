@@ -0,0 +1,197 @@
+//! `proptest` strategies for partially consumed sorters, and checkers that compare sorted output
+//! against `Vec::sort`.
+//!
+//! Enabled by the `proptest` feature. Each strategy builds a sorter from elements drawn from
+//! `element`, with a number of them drawn from `size`, and then takes an arbitrary number of them
+//! from it, so properties exercise sorters midway through sorting as well as fresh ones.
+//!
+//! ```
+//! # extern crate lazy_sort;
+//! # extern crate proptest;
+//! use lazy_sort::proptest_strategies::{check_sorter, quick_sort};
+//! use proptest::prelude::*;
+//! use proptest::test_runner::TestRunner;
+//!
+//! # fn main() {
+//! TestRunner::default().run(&quick_sort(any::<u8>(), 0..100), |sorter| {
+//!     check_sorter(sorter, usize::MAX)
+//! }).unwrap();
+//! # }
+//! ```
+
+use proptest::collection::{self, SizeRange};
+use proptest::prelude::*;
+use proptest::sample::Index;
+use proptest::test_runner::TestCaseError;
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::vec::Vec;
+use super::{Algorithm, Config, HeapSort, LazySort, LazySortBuilder, LazySortIterator,
+            LazySorted, PairingHeapSort, PivotStrategy, QuickSort};
+use rng::XorShift;
+
+/// Returns a strategy for the elements of a sorter, along with how many of them to take from it.
+fn consumed<S>(element: S, size: SizeRange) -> impl Strategy<Value = (Vec<S::Value>, usize)>
+    where S: Strategy
+{
+    (collection::vec(element, size), any::<Index>()).prop_map(|(v, consumed)| {
+        let consumed = consumed.index(v.len() + 1);
+        (v, consumed)
+    })
+}
+
+/// Takes `consumed` elements from `sorter`.
+fn consume<I: Iterator>(mut sorter: I, consumed: usize) -> I {
+    sorter.by_ref().take(consumed).for_each(drop);
+    sorter
+}
+
+fn pivot_strategy() -> impl Strategy<Value = PivotStrategy> {
+    prop_oneof![Just(PivotStrategy::Middle),
+                Just(PivotStrategy::Random),
+                Just(PivotStrategy::MedianOfThree)]
+}
+
+/// Returns a strategy for partially consumed quicksorts, with arbitrary pivot strategies, seeds,
+/// and insertion sort thresholds.
+pub fn quick_sort<S>(element: S, size: impl Into<SizeRange>)
+                     -> impl Strategy<Value = QuickSort<S::Value>>
+    where S: Strategy,
+          S::Value: Ord
+{
+    (consumed(element, size.into()), pivot_strategy(), any::<u64>(), 0..64usize)
+        .prop_map(|((v, consumed), pivot, seed, threshold)| {
+            let config = Config {
+                threshold,
+                pivot,
+                rng: XorShift::new(seed),
+                ..Config::default()
+            };
+            consume(QuickSort::with_config(v, config), consumed)
+        })
+}
+
+/// Returns a strategy for partially consumed heapsorts.
+pub fn heap_sort<S>(element: S, size: impl Into<SizeRange>)
+                    -> impl Strategy<Value = HeapSort<S::Value>>
+    where S: Strategy,
+          S::Value: Ord
+{
+    consumed(element, size.into())
+        .prop_map(|(v, consumed)| consume(v.into_iter().heap_sort(), consumed))
+}
+
+/// Returns a strategy for partially consumed pairing heap sorts.
+pub fn pairing_heap_sort<S>(element: S, size: impl Into<SizeRange>)
+                            -> impl Strategy<Value = PairingHeapSort<S::Value>>
+    where S: Strategy,
+          S::Value: Ord
+{
+    consumed(element, size.into())
+        .prop_map(|(v, consumed)| consume(v.into_iter().pairing_heap_sort(), consumed))
+}
+
+/// Returns a strategy for partially consumed sorts built by a
+/// [`LazySortBuilder`](../struct.LazySortBuilder.html) with an arbitrary algorithm, pivot
+/// strategy, threshold, seed, and stability.
+pub fn lazy_sort<S>(element: S, size: impl Into<SizeRange>)
+                    -> impl Strategy<Value = LazySort<S::Value>>
+    where S: Strategy,
+          S::Value: Ord
+{
+    let algorithm = prop_oneof![Just(Algorithm::Quick),
+                                Just(Algorithm::Heap),
+                                Just(Algorithm::Funnel)];
+    (consumed(element, size.into()),
+     algorithm,
+     pivot_strategy(),
+     0..64usize,
+     any::<u64>(),
+     any::<bool>())
+        .prop_map(|((v, consumed), algorithm, pivot, threshold, seed, stable)| {
+            let sorter = LazySortBuilder::new().algorithm(algorithm)
+                                               .pivot(pivot)
+                                               .threshold(threshold)
+                                               .seed(seed)
+                                               .stable(stable)
+                                               .sort(v)
+                                               .unwrap();
+            consume(sorter, consumed)
+        })
+}
+
+/// Checks that the first `k` elements of `sorted` are the first `k` elements of `input` once it's
+/// sorted with `Vec::sort`, and that `sorted` runs out when `input` does.
+///
+/// Elements are compared with `Ord`, so equal elements may come in any order.
+pub fn check_sorted_prefix<T, I>(mut input: Vec<T>, sorted: I, k: usize)
+                                 -> Result<(), TestCaseError>
+    where T: Ord + Debug,
+          I: IntoIterator<Item = T>
+{
+    input.sort();
+    let mut sorted = sorted.into_iter();
+    for (i, expected) in input.iter().take(k).enumerate() {
+        match sorted.next() {
+            Some(ref el) if el.cmp(expected) == Ordering::Equal => {}
+            el => {
+                return Err(TestCaseError::fail(format!("element {} is {:?}, not {:?}",
+                                                       i,
+                                                       el,
+                                                       Some(expected))))
+            }
+        }
+    }
+    if k > input.len() {
+        let el = sorted.next();
+        prop_assert!(el.is_none(), "yielded {:?} after all {} elements", el, input.len());
+    }
+    Ok(())
+}
+
+/// Checks that `sorter` yields the first `k` of its remaining elements in sorted order, and that
+/// its `peek` and `len` agree with what it yields.
+pub fn check_sorter<T, S>(sorter: S, k: usize) -> Result<(), TestCaseError>
+    where T: Ord + Debug,
+          S: LazySorted<T> + Clone
+{
+    let input = sorter.clone().into_inner();
+    let len = input.len();
+    let mut sorter = sorter;
+    let mut yielded = Vec::new();
+    while yielded.len() < k {
+        prop_assert_eq!(sorter.len(), len - yielded.len());
+        let peeked = sorter.peek().is_some();
+        match sorter.next() {
+            Some(el) => {
+                prop_assert!(peeked, "peek returned None before {:?}", el);
+                yielded.push(el);
+            }
+            None => {
+                prop_assert!(!peeked, "peek returned an element, but next didn't");
+                break;
+            }
+        }
+    }
+    check_sorted_prefix(input, yielded, k)
+}
+
+#[test]
+fn proptest_strategies() {
+    use proptest::test_runner::TestRunner;
+
+    let mut runner = TestRunner::default();
+    runner.run(&(quick_sort(any::<u8>(), 0..200), 0..220usize),
+               |(sorter, k)| check_sorter(sorter, k)).unwrap();
+    runner.run(&heap_sort(any::<i16>(), 0..50), |sorter| check_sorter(sorter, usize::MAX))
+          .unwrap();
+    runner.run(&pairing_heap_sort(any::<u8>(), 0..50), |sorter| check_sorter(sorter, 10))
+          .unwrap();
+    runner.run(&lazy_sort(any::<u8>(), 0..200), |sorter| check_sorter(sorter, usize::MAX))
+          .unwrap();
+
+    let wrong = check_sorted_prefix(vec![3, 1, 2], vec![1, 3, 2], 3);
+    assert!(wrong.is_err());
+    assert!(check_sorted_prefix(vec![3, 1, 2], vec![1, 2], 2).is_ok());
+    assert!(check_sorted_prefix(vec![3, 1, 2], vec![1, 2, 3, 4], 4).is_err());
+}
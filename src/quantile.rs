@@ -0,0 +1,137 @@
+//! Approximate quantiles of unbounded streams in bounded memory.
+
+use std::vec::Vec;
+
+/// A Greenwald-Khanna quantile sketch.
+///
+/// The sketch answers quantile queries over everything inserted so far to within a rank error of
+/// `epsilon * len()`, while keeping only `O((1 / epsilon) * log(epsilon * len()))` elements.
+#[derive(Debug, Clone)]
+pub struct QuantileSketch<T> {
+    epsilon: f64,
+    len: usize,
+    /// The retained elements in ascending order. For each, `g` is its minimum rank minus that of
+    /// the previous tuple, and `delta` bounds how much its maximum rank exceeds its minimum rank.
+    tuples: Vec<Tuple<T>>,
+    inserts_since_compress: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Tuple<T> {
+    value: T,
+    g: usize,
+    delta: usize,
+}
+
+impl<T: Ord> QuantileSketch<T> {
+    /// Returns an empty sketch with a maximum rank error of `epsilon` times the number of
+    /// elements.
+    ///
+    /// Panics unless `0 < epsilon < 1`.
+    pub fn new(epsilon: f64) -> QuantileSketch<T> {
+        assert!(epsilon > 0.0 && epsilon < 1.0, "epsilon must be between 0 and 1");
+        QuantileSketch {
+            epsilon,
+            len: 0,
+            tuples: Vec::new(),
+            inserts_since_compress: 0,
+        }
+    }
+
+    /// Adds an element to the sketch.
+    pub fn insert(&mut self, value: T) {
+        let idx = self.tuples.partition_point(|t| t.value <= value);
+        // The minimum and maximum are always known exactly.
+        let delta = if idx == 0 || idx == self.tuples.len() {
+            0
+        } else {
+            self.max_gap().saturating_sub(1)
+        };
+        self.tuples.insert(idx, Tuple { value, g: 1, delta });
+        self.len += 1;
+        self.inserts_since_compress += 1;
+        if self.inserts_since_compress as f64 >= 1.0 / (2.0 * self.epsilon) {
+            self.compress();
+            self.inserts_since_compress = 0;
+        }
+    }
+
+    /// Returns an element whose rank is within `epsilon * len()` of `q * len()`, or `None` if the
+    /// sketch is empty. `q` is clamped to `[0, 1]`.
+    pub fn quantile(&self, q: f64) -> Option<&T> {
+        let last = self.tuples.last()?;
+        let q = q.clamp(0.0, 1.0);
+        let bound = q * self.len as f64 + self.epsilon * self.len as f64;
+        let mut r_min = 0;
+        for (i, t) in self.tuples.iter().enumerate() {
+            r_min += t.g;
+            if (r_min + t.delta) as f64 > bound {
+                return Some(&self.tuples[i.saturating_sub(1)].value);
+            }
+        }
+        Some(&last.value)
+    }
+
+    /// Returns the approximate quantile for each of `qs`. See [`quantile`](#method.quantile).
+    pub fn quantiles(&self, qs: &[f64]) -> Vec<Option<&T>> {
+        qs.iter().map(|&q| self.quantile(q)).collect()
+    }
+
+    /// Returns the number of elements inserted.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no elements have been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The largest `g + delta` any tuple may have without exceeding the error bound.
+    fn max_gap(&self) -> usize {
+        // Rounds down, since the product isn't negative.
+        (2.0 * self.epsilon * self.len as f64) as usize
+    }
+
+    /// Merges adjacent tuples wherever doing so keeps the error bound.
+    fn compress(&mut self) {
+        let max_gap = self.max_gap();
+        let mut i = self.tuples.len().saturating_sub(2);
+        // The first tuple holds the minimum, so it is never merged away.
+        while i >= 1 {
+            let (g, next) = (self.tuples[i].g, &self.tuples[i + 1]);
+            if g + next.g + next.delta <= max_gap {
+                self.tuples[i + 1].g += g;
+                self.tuples.remove(i);
+            }
+            i -= 1;
+        }
+    }
+}
+
+impl<T: Ord> Extend<T> for QuantileSketch<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for el in iter {
+            self.insert(el);
+        }
+    }
+}
+
+#[test]
+fn quantile_sketch_error_bound() {
+    let n = 20_000;
+    let epsilon = 0.01;
+    let mut sketch = QuantileSketch::new(epsilon);
+    sketch.extend((0..n).map(|i| (i * 7919) % n));
+    assert!(sketch.tuples.len() < n / 20);
+    for &q in &[0.0, 0.01, 0.25, 0.5, 0.9, 0.99, 1.0] {
+        // The values are a permutation of 0..n, so each value is its own rank.
+        let value = *sketch.quantile(q).unwrap() as f64;
+        assert!((value - q * n as f64).abs() <= epsilon * n as f64 + 1.0,
+                "q = {}, value = {}",
+                q,
+                value);
+    }
+    assert_eq!(sketch.quantiles(&[1.0]), vec![Some(&(n - 1))]);
+    assert_eq!(QuantileSketch::<u32>::new(0.1).quantile(0.5), None);
+}
@@ -0,0 +1,193 @@
+//! Sorting the fixed-width binary records of a stream.
+
+use std::cmp::Ordering;
+use std::io::{self, Read, Write};
+use std::iter;
+use std::mem;
+use std::vec::Vec;
+use super::{ExternalSort, ExternalSortBuilder, SpillCodec};
+
+/// Lazily sorts the `record_len`-byte records of `reader` by their bytes.
+///
+/// See [`sort_records_by_key`](fn.sort_records_by_key.html).
+pub fn sort_records<R: Read>(reader: R, record_len: usize, builder: ExternalSortBuilder)
+    -> io::Result<SortedRecords>
+{
+    fn no_key(_: &[u8]) {}
+    sort_records_by_key(reader, record_len, builder, no_key as fn(&[u8]))
+}
+
+/// Lazily sorts the `record_len`-byte records of `reader` by the key that `key_fn` extracts from
+/// each, breaking ties by comparing whole records.
+///
+/// The records are sorted in memory if they fit in `builder`'s memory budget, and spilled to disk
+/// otherwise. Keys aren't spilled; `key_fn` is called again on each record read back from disk.
+/// Fails with `InvalidData` if the input ends partway through a record.
+///
+/// Panics if `record_len` is zero.
+///
+/// ```
+/// use lazy_sort::{sort_records_by_key, ExternalSortBuilder};
+///
+/// // Records of a big-endian `u16` id followed by a byte of payload.
+/// let input = [0, 9, b'a', 0, 2, b'b', 1, 0, b'c', 0, 5, b'd'];
+/// let by_id = |record: &[u8]| u16::from_be_bytes([record[0], record[1]]);
+/// let sorted = sort_records_by_key(&input[..], 3, ExternalSortBuilder::new(), by_id).unwrap();
+/// let payloads: Vec<_> = sorted.map(|record| record[2]).collect();
+/// assert_eq!(payloads, b"bdac");
+/// ```
+pub fn sort_records_by_key<R, K, F>(mut reader: R,
+                                    record_len: usize,
+                                    builder: ExternalSortBuilder,
+                                    key_fn: F)
+                                    -> io::Result<SortedRecords<K, F>>
+    where R: Read,
+          K: Ord,
+          F: Fn(&[u8]) -> K + Clone
+{
+    assert!(record_len > 0, "record_len must be positive");
+    let mut error = None;
+    let records = iter::from_fn(|| match read_record(&mut reader, record_len) {
+        Ok(record) => record.map(|record| KeyedRecord {
+            key: key_fn(&record),
+            record,
+        }),
+        Err(err) => {
+            error = Some(err);
+            None
+        }
+    });
+    let weigh = |_: &KeyedRecord<K>| mem::size_of::<KeyedRecord<K>>() + record_len;
+    let codec = RecordCodec {
+        record_len,
+        key_fn: key_fn.clone(),
+    };
+    let sorted = builder.sort_weighed(records, codec, weigh)?;
+    match error {
+        Some(err) => Err(err),
+        None => Ok(SortedRecords(sorted)),
+    }
+}
+
+/// Reads the next record, or `None` if `reader` has run out.
+fn read_record<R: Read>(reader: &mut R, record_len: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut record = vec![0; record_len];
+    let mut filled = 0;
+    while filled < record_len {
+        match reader.read(&mut record[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          "the input ended partway through a record"))
+            }
+            Ok(read) => filled += read,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(Some(record))
+}
+
+/// The sorted records of a binary stream, created by [`sort_records`](fn.sort_records.html) or
+/// [`sort_records_by_key`](fn.sort_records_by_key.html).
+///
+/// If reading spilled records back fails, iteration stops early and the error is available from
+/// [`error`](#method.error).
+#[derive(Debug)]
+pub struct SortedRecords<K = (), F = fn(&[u8])>(ExternalSort<KeyedRecord<K>, RecordCodec<F>>)
+    where K: Ord,
+          F: Fn(&[u8]) -> K;
+
+impl<K: Ord, F: Fn(&[u8]) -> K> SortedRecords<K, F> {
+    /// Returns the error that ended iteration early, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.0.error()
+    }
+}
+
+impl<K: Ord, F: Fn(&[u8]) -> K> Iterator for SortedRecords<K, F> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        self.0.next().map(|keyed| keyed.record)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// A record ordered by its key, then by its bytes.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct KeyedRecord<K> {
+    key: K,
+    record: Vec<u8>,
+}
+
+impl<K: Ord> Ord for KeyedRecord<K> {
+    fn cmp(&self, other: &KeyedRecord<K>) -> Ordering {
+        self.key.cmp(&other.key).then_with(|| self.record.cmp(&other.record))
+    }
+}
+
+impl<K: Ord> PartialOrd for KeyedRecord<K> {
+    fn partial_cmp(&self, other: &KeyedRecord<K>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> PartialEq for KeyedRecord<K> {
+    fn eq(&self, other: &KeyedRecord<K>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord> Eq for KeyedRecord<K> {}
+
+/// Spills a record as its bytes, and recomputes its key when read back.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct RecordCodec<F> {
+    record_len: usize,
+    key_fn: F,
+}
+
+impl<F> ::std::fmt::Debug for RecordCodec<F> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("RecordCodec").field("record_len", &self.record_len).finish()
+    }
+}
+
+impl<K, F: Fn(&[u8]) -> K> SpillCodec<KeyedRecord<K>> for RecordCodec<F> {
+    fn encode<W: Write>(&self, el: &KeyedRecord<K>, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&el.record)
+    }
+
+    fn decode<R: Read>(&self, reader: &mut R) -> io::Result<KeyedRecord<K>> {
+        let mut record = vec![0; self.record_len];
+        reader.read_exact(&mut record)?;
+        Ok(KeyedRecord {
+            key: (self.key_fn)(&record),
+            record,
+        })
+    }
+}
+
+#[test]
+fn sort_records_in_memory_and_spilled() {
+    let input: Vec<u8> = (0..500u32).flat_map(|i| ((i * 7919) % 500).to_le_bytes()).collect();
+    for &budget in &[64, 1 << 20] {
+        let builder = ExternalSortBuilder::new().memory_budget(budget);
+        let by_value = |record: &[u8]| u32::from_le_bytes([record[0], record[1], record[2],
+                                                           record[3]]);
+        let sorted = sort_records_by_key(&input[..], 4, builder.clone(), by_value).unwrap();
+        assert!(sorted.map(|record| by_value(&record)).eq(0..500));
+
+        let sorted: Vec<_> = sort_records(&input[..], 2, builder).unwrap().collect();
+        assert_eq!(sorted.len(), 1000);
+        assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+    }
+    let err = sort_records(&input[..5], 4, ExternalSortBuilder::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
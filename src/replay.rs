@@ -0,0 +1,151 @@
+//! Recording the pivots a quicksort chooses, and replaying them to reproduce the sort exactly.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::vec::Vec;
+
+/// A log of the pivots chosen by quicksorts configured with
+/// [`LazySortBuilder::record_decisions`](struct.LazySortBuilder.html#method.record_decisions),
+/// which a sort configured with
+/// [`replay_decisions`](struct.LazySortBuilder.html#method.replay_decisions) then makes again.
+///
+/// The pivots are the only choices a lazy quicksort makes; ties between equal elements are
+/// always resolved the same way, so replaying the pivots over the same input repeats every
+/// comparison, whatever the pivot strategy and seed. The log is shared between clones, so one
+/// kept by the caller sees what the sort records even if the sort panics.
+///
+/// Partitioning done in parallel by
+/// [`par_sort`](struct.LazySortBuilder.html#method.par_sort) chooses pivots in an unpredictable
+/// order, so it can't be replayed.
+#[derive(Debug, Clone, Default)]
+pub struct DecisionLog(Arc<Mutex<Log>>);
+
+#[derive(Debug, Default)]
+struct Log {
+    pivots: Vec<usize>,
+    /// The position of the next pivot to replay.
+    next: usize,
+}
+
+impl DecisionLog {
+    /// Returns an empty log.
+    pub fn new() -> DecisionLog {
+        DecisionLog::default()
+    }
+
+    /// Returns a log that replays `pivots`, which are indices into the partitions they split.
+    pub fn from_pivots(pivots: Vec<usize>) -> DecisionLog {
+        DecisionLog(Arc::new(Mutex::new(Log { pivots, next: 0 })))
+    }
+
+    /// Returns the pivots recorded so far.
+    pub fn pivots(&self) -> Vec<usize> {
+        self.log().pivots.clone()
+    }
+
+    /// Encodes the pivots compactly, each as a LEB128 varint.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for &pivot in &self.log().pivots {
+            let mut pivot = pivot as u64;
+            while pivot >= 0x80 {
+                bytes.push(pivot as u8 | 0x80);
+                pivot >>= 7;
+            }
+            bytes.push(pivot as u8);
+        }
+        bytes
+    }
+
+    /// Decodes pivots encoded by [`to_bytes`](#method.to_bytes), returning `None` if `bytes` is
+    /// truncated or a pivot overflows a `usize`.
+    pub fn from_bytes(bytes: &[u8]) -> Option<DecisionLog> {
+        let mut pivots = Vec::new();
+        let mut bytes = bytes.iter();
+        while let Some(&byte) = bytes.next() {
+            let (mut pivot, mut shift, mut byte) = (0u64, 0, byte);
+            loop {
+                if shift > 63 || (shift == 63 && byte > 1) {
+                    return None;
+                }
+                pivot |= u64::from(byte & 0x7f) << shift;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+                shift += 7;
+                byte = *bytes.next()?;
+            }
+            if pivot > usize::MAX as u64 {
+                return None;
+            }
+            pivots.push(pivot as usize);
+        }
+        Some(DecisionLog::from_pivots(pivots))
+    }
+
+    /// Appends a pivot.
+    pub(crate) fn record(&self, pivot: usize) {
+        self.log().pivots.push(pivot);
+    }
+
+    /// Returns the next pivot to replay for a partition of `len` elements.
+    ///
+    /// Panics if the log has run out or the pivot is out of bounds, which means the sort being
+    /// replayed didn't have the input that was recorded.
+    pub(crate) fn replay(&self, len: usize) -> usize {
+        let mut log = self.log();
+        let pivot = *log.pivots.get(log.next).expect("the replayed decision log ran out");
+        assert!(pivot < len,
+                "replayed pivot {} is out of bounds for a partition of {} elements",
+                pivot,
+                len);
+        log.next += 1;
+        pivot
+    }
+
+    fn log(&self) -> MutexGuard<'_, Log> {
+        // A sort that panicked leaves a consistent log, which is worth keeping.
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Whether a sort records its pivots or replays them.
+#[derive(Debug, Clone)]
+pub(crate) enum Decisions {
+    Record(DecisionLog),
+    Replay(DecisionLog),
+}
+
+#[test]
+fn record_and_replay() {
+    use super::{LazySortBuilder, PivotStrategy, Step};
+
+    fn steps(builder: LazySortBuilder, v: &[u32]) -> Vec<Step> {
+        let steps = Arc::new(Mutex::new(Vec::new()));
+        let sorted: Vec<_> = {
+            let steps = steps.clone();
+            builder.on_step(move |step| steps.lock().unwrap().push(step))
+                   .sort(v.iter().cloned())
+                   .unwrap()
+                   .collect()
+        };
+        assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+        let steps = steps.lock().unwrap().clone();
+        steps
+    }
+
+    let v: Vec<u32> = (0..1000).map(|i| (i * 7919) % 1009 / 4).collect();
+    let log = DecisionLog::new();
+    let recorded = steps(LazySortBuilder::new().pivot(PivotStrategy::Random)
+                                               .seed(42)
+                                               .record_decisions(log.clone()),
+                         &v);
+    let bytes = log.to_bytes();
+    assert!(bytes.len() < 2 * log.pivots().len());
+    let replay = DecisionLog::from_bytes(&bytes).unwrap();
+    assert_eq!(replay.pivots(), log.pivots());
+    let replayed = steps(LazySortBuilder::new().replay_decisions(replay), &v);
+    assert_eq!(recorded, replayed);
+
+    assert_eq!(DecisionLog::from_bytes(&[0x80]).map(|log| log.pivots()), None);
+    assert_eq!(DecisionLog::from_bytes(&[0xac, 0x02, 0]).unwrap().pivots(), [300, 0]);
+}
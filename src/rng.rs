@@ -0,0 +1,47 @@
+//! A small pseudorandom number generator for choosing pivots.
+
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hasher};
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+/// Returns a seed chosen at random once per process, from the same source as `HashMap`'s keys, or
+/// zero without the `std` feature.
+pub fn process_seed() -> u64 {
+    #[cfg(feature = "std")]
+    {
+        static SEED: OnceLock<u64> = OnceLock::new();
+        *SEED.get_or_init(|| RandomState::new().build_hasher().finish())
+    }
+    #[cfg(not(feature = "std"))]
+    0
+}
+
+/// An xorshift generator. It is fast and tiny, which is all pivot selection needs; it is not
+/// suitable for anything that requires unpredictability.
+#[derive(Debug, Clone)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        // Xorshift gets stuck at zero, so the seed is mixed with an odd constant first.
+        let state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        XorShift(if state == 0 { 1 } else { state })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Returns a number in `0..len`. `len` must be nonzero.
+    pub fn gen_index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
@@ -0,0 +1,151 @@
+//! Sharing one lazily sorted sequence between several consumers.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::iter::FusedIterator;
+use std::rc::Rc;
+use std::vec::Vec;
+
+/// One of several consumers of the same sorted iterator, created by
+/// [`shared`](trait.LazySorted.html#method.shared).
+///
+/// Cloning a consumer creates another that starts from the same position. The underlying iterator
+/// is advanced once, by whichever consumer is furthest ahead, and each element is buffered until
+/// every consumer has seen it.
+#[derive(Debug)]
+pub struct Shared<S: Iterator> {
+    state: Rc<RefCell<State<S, S::Item>>>,
+    id: usize,
+}
+
+#[derive(Debug)]
+struct State<S, T> {
+    source: S,
+    /// The elements some consumer hasn't yet seen.
+    buffer: VecDeque<T>,
+    /// The position of `buffer[0]` in the sequence.
+    start: usize,
+    /// The position of the next element of each consumer, indexed by its id, or `None` for an id
+    /// no live consumer has. Ids of dropped consumers are reused by later clones, so there are
+    /// only ever as many as there were live consumers at once.
+    positions: Vec<Option<usize>>,
+}
+
+impl<S, T> State<S, T> {
+    /// Drops the buffered elements that every consumer has seen.
+    fn trim(&mut self) {
+        let min = self.positions.iter().filter_map(|&pos| pos).min().unwrap_or(usize::MAX);
+        while self.start < min && self.buffer.pop_front().is_some() {
+            self.start += 1;
+        }
+    }
+}
+
+impl<S: Iterator> Shared<S> {
+    pub(crate) fn new(source: S) -> Shared<S> {
+        Shared {
+            state: Rc::new(RefCell::new(State {
+                source,
+                buffer: VecDeque::new(),
+                start: 0,
+                positions: vec![Some(0)],
+            })),
+            id: 0,
+        }
+    }
+}
+
+impl<S: Iterator> Clone for Shared<S> {
+    fn clone(&self) -> Shared<S> {
+        let mut state = self.state.borrow_mut();
+        let pos = state.positions[self.id];
+        let id = match state.positions.iter().position(Option::is_none) {
+            Some(id) => {
+                state.positions[id] = pos;
+                id
+            }
+            None => {
+                state.positions.push(pos);
+                state.positions.len() - 1
+            }
+        };
+        Shared {
+            state: self.state.clone(),
+            id,
+        }
+    }
+}
+
+impl<S: Iterator> Drop for Shared<S> {
+    fn drop(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.positions[self.id] = None;
+        while state.positions.last() == Some(&None) {
+            state.positions.pop();
+        }
+        state.trim();
+    }
+}
+
+impl<S> Iterator for Shared<S>
+    where S: Iterator,
+          S::Item: Clone
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        let mut state = self.state.borrow_mut();
+        let pos = state.positions[self.id].expect("live consumers have a position");
+        let el = match state.buffer.get(pos - state.start) {
+            Some(el) => el.clone(),
+            None => {
+                let el = state.source.next()?;
+                state.buffer.push_back(el.clone());
+                el
+            }
+        };
+        state.positions[self.id] = Some(pos + 1);
+        state.trim();
+        Some(el)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let state = self.state.borrow();
+        let pos = state.positions[self.id].expect("live consumers have a position");
+        let buffered = state.start + state.buffer.len() - pos;
+        let (lower, upper) = state.source.size_hint();
+        (lower.saturating_add(buffered), upper.and_then(|upper| upper.checked_add(buffered)))
+    }
+}
+
+impl<S> FusedIterator for Shared<S>
+    where S: FusedIterator,
+          S::Item: Clone
+{ }
+
+#[test]
+fn shared() {
+    use super::{LazySortIterator, LazySorted};
+
+    let v = [5, 3, 8, 1, 9, 2];
+    let mut a = v.iter().cloned().quick_sort().shared();
+    let mut b = a.clone();
+    assert_eq!(a.by_ref().take(3).collect::<Vec<_>>(), [1, 2, 3]);
+    let c = b.clone();
+    assert_eq!(b.next(), Some(1));
+    assert_eq!(b.size_hint(), (5, Some(5)));
+    drop(a);
+    assert_eq!(b.collect::<Vec<_>>(), [2, 3, 5, 8, 9]);
+    assert_eq!(c.state.borrow().buffer.len(), v.len());
+    assert_eq!(c.collect::<Vec<_>>(), [1, 2, 3, 5, 8, 9]);
+
+    // Short-lived clones give back their positions, and the elements only they hadn't seen.
+    let mut a = (0..100).quick_sort().shared();
+    for _ in 0..10 {
+        let mut b = a.clone();
+        assert_eq!(b.next(), a.next());
+    }
+    let b = a.clone();
+    assert_eq!(b.state.borrow().positions.len(), 2);
+    assert!(b.state.borrow().buffer.is_empty());
+}
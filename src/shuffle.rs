@@ -0,0 +1,167 @@
+//! Sorting keyed records for the shuffle phase of a MapReduce.
+
+use std::cmp::Ordering;
+use super::{LazySortIterator, LazySorted, QuickSort};
+
+/// A lazy sort of key-value records by key and then by a secondary key of each value, which
+/// yields the values of each key contiguously in secondary order, as the shuffle phase of a
+/// MapReduce does for its reducers.
+///
+/// Iterate the records directly, or a group at a time with [`next_group`](#method.next_group).
+/// The order of values with equal keys and secondary keys is unspecified.
+///
+/// ```
+/// use lazy_sort::ShuffleSort;
+///
+/// let visits = vec![("bob", (3, "/c")), ("amy", (2, "/b")), ("bob", (1, "/a")),
+///                   ("amy", (1, "/d"))];
+/// // Each user's visits, ordered by time.
+/// let mut shuffled = ShuffleSort::new(visits, |&(time, _)| time);
+/// let amy = shuffled.next_group().unwrap();
+/// assert_eq!(*amy.key(), "amy");
+/// assert_eq!(amy.map(|(_, (_, page))| page).collect::<Vec<_>>(), ["/d", "/b"]);
+/// assert_eq!(shuffled.next(), Some(("bob", (1, "/a"))));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShuffleSort<K, S, V> {
+    sorter: QuickSort<Record<K, S, V>>,
+    /// The key of the last group returned by `next_group`, whose remaining values it skips.
+    group: Option<K>,
+}
+
+/// A value along with its key and secondary key, ordered by the two keys.
+#[derive(Debug, Clone)]
+struct Record<K, S, V> {
+    key: K,
+    secondary: S,
+    value: V,
+}
+
+impl<K: Ord, S: Ord, V> Ord for Record<K, S, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key).then_with(|| self.secondary.cmp(&other.secondary))
+    }
+}
+
+impl<K: Ord, S: Ord, V> PartialOrd for Record<K, S, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, S: Ord, V> PartialEq for Record<K, S, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, S: Ord, V> Eq for Record<K, S, V> {}
+
+impl<K: Ord, S: Ord, V> ShuffleSort<K, S, V> {
+    /// Sorts `records` by key, and the values of each key by the secondary key that `secondary`
+    /// extracts from them.
+    pub fn new<I, F>(records: I, mut secondary: F) -> ShuffleSort<K, S, V>
+        where I: IntoIterator<Item = (K, V)>,
+              F: FnMut(&V) -> S
+    {
+        let sorter = records.into_iter()
+                            .map(|(key, value)| {
+                                Record {
+                                    key,
+                                    secondary: secondary(&value),
+                                    value,
+                                }
+                            })
+                            .quick_sort();
+        ShuffleSort {
+            sorter,
+            group: None,
+        }
+    }
+
+    /// Returns the next key's values, skipping whatever is left of the last group this returned.
+    pub fn next_group(&mut self) -> Option<ShuffleGroup<'_, K, S, V>>
+        where K: Clone
+    {
+        if let Some(group) = self.group.take() {
+            while self.sorter.peek().is_some_and(|record| record.key == group) {
+                self.sorter.next();
+            }
+        }
+        let key = self.sorter.peek()?.key.clone();
+        self.group = Some(key.clone());
+        Some(ShuffleGroup {
+            sorter: &mut self.sorter,
+            key,
+        })
+    }
+
+    /// Returns the number of remaining records.
+    pub fn len(&self) -> usize {
+        self.sorter.len()
+    }
+
+    /// Returns true if there are no remaining records.
+    pub fn is_empty(&self) -> bool {
+        self.sorter.is_empty()
+    }
+}
+
+impl<K: Ord, S: Ord, V> Iterator for ShuffleSort<K, S, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        self.sorter.next().map(|record| (record.key, record.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.sorter.size_hint()
+    }
+}
+
+impl<K: Ord, S: Ord, V> ExactSizeIterator for ShuffleSort<K, S, V> { }
+
+/// The remaining values of one key of a [`ShuffleSort`](struct.ShuffleSort.html), in secondary
+/// order, created by [`next_group`](struct.ShuffleSort.html#method.next_group).
+#[derive(Debug)]
+pub struct ShuffleGroup<'a, K: 'a, S: 'a, V: 'a> {
+    sorter: &'a mut QuickSort<Record<K, S, V>>,
+    key: K,
+}
+
+impl<'a, K: Ord, S: Ord, V> ShuffleGroup<'a, K, S, V> {
+    /// Returns the group's key.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<'a, K: Ord, S: Ord, V> Iterator for ShuffleGroup<'a, K, S, V> {
+    type Item = (S, V);
+
+    fn next(&mut self) -> Option<(S, V)> {
+        if self.sorter.peek()?.key != self.key {
+            return None;
+        }
+        self.sorter.next().map(|record| (record.secondary, record.value))
+    }
+}
+
+#[test]
+fn shuffle_sort() {
+    use std::vec::Vec;
+
+    let records = (0..300u32).map(|i| (i % 3, (i * 7) % 100));
+    let mut shuffled = ShuffleSort::new(records, |&v| v / 10);
+    assert_eq!(shuffled.len(), 300);
+    let first = shuffled.next_group().unwrap();
+    assert_eq!(*first.key(), 0);
+    let tens: Vec<_> = first.map(|(tens, _)| tens).collect();
+    assert_eq!(tens.len(), 100);
+    assert!(tens.windows(2).all(|w| w[0] <= w[1]));
+    // Whatever of a group isn't taken is skipped by the next call.
+    assert_eq!(shuffled.next_group().unwrap().next().map(|(tens, _)| tens), Some(0));
+    let last = shuffled.next_group().unwrap();
+    assert_eq!((*last.key(), last.count()), (2, 100));
+    assert!(shuffled.next_group().is_none());
+}
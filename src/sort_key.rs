@@ -0,0 +1,244 @@
+//! Encoding values as byte strings that sort in the same order, like a database's sort keys.
+
+use std::cmp::{Ordering, Reverse};
+use std::iter::FusedIterator;
+use std::string::String;
+use std::vec::Vec;
+use super::{LazySortIterator, LazySorted, OrderedBits, QuickSort};
+
+/// A type whose values encode to byte strings that sort, compared lexicographically as by
+/// `memcmp`, in the same order as the values.
+///
+/// Integers and floats encode as their [ordered bits](trait.OrderedBits.html) in big-endian
+/// order. Byte strings and strings escape their zero bytes and end with a terminator, so that no
+/// key is a prefix of another, which lets tuples concatenate the keys of their fields: a
+/// composite key like `(date, id, score)` is then compared with a single `memcmp`, however many
+/// fields it has.
+///
+/// ```
+/// use lazy_sort::SortKey;
+///
+/// let key = |date: u32, name: &str, score: f64| (date, name, score).to_sort_key();
+/// assert!(key(20260101, "b", 1.0) < key(20260102, "a", 0.0));
+/// assert!(key(20260101, "a", 2.0) < key(20260101, "ab", -1.0));
+/// assert!(key(20260101, "a", -0.5) < key(20260101, "a", 0.5));
+/// ```
+pub trait SortKey {
+    /// Appends the value's key to `out`.
+    fn write_sort_key(&self, out: &mut Vec<u8>);
+
+    /// Returns the value's key.
+    fn to_sort_key(&self) -> Vec<u8> {
+        let mut key = Vec::new();
+        self.write_sort_key(&mut key);
+        key
+    }
+}
+
+macro_rules! bits_sort_key {
+    ($($t:ty),*) => {$(
+        impl SortKey for $t {
+            fn write_sort_key(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_ordered_bits().to_be_bytes());
+            }
+        }
+    )*}
+}
+
+bits_sort_key!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+impl SortKey for bool {
+    fn write_sort_key(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl SortKey for char {
+    fn write_sort_key(&self, out: &mut Vec<u8>) {
+        (*self as u32).write_sort_key(out);
+    }
+}
+
+impl SortKey for [u8] {
+    /// Escapes each zero byte as `00 ff`, and ends with `00 00`, which sorts before any escaped
+    /// byte, so that a string's key sorts before the keys of the strings it's a prefix of.
+    fn write_sort_key(&self, out: &mut Vec<u8>) {
+        for &byte in self {
+            out.push(byte);
+            if byte == 0 {
+                out.push(0xff);
+            }
+        }
+        out.extend_from_slice(&[0, 0]);
+    }
+}
+
+impl SortKey for str {
+    fn write_sort_key(&self, out: &mut Vec<u8>) {
+        self.as_bytes().write_sort_key(out);
+    }
+}
+
+impl SortKey for String {
+    fn write_sort_key(&self, out: &mut Vec<u8>) {
+        self.as_bytes().write_sort_key(out);
+    }
+}
+
+impl SortKey for Vec<u8> {
+    fn write_sort_key(&self, out: &mut Vec<u8>) {
+        self[..].write_sort_key(out);
+    }
+}
+
+impl<T: SortKey + ?Sized> SortKey for &T {
+    fn write_sort_key(&self, out: &mut Vec<u8>) {
+        (**self).write_sort_key(out);
+    }
+}
+
+impl<T: SortKey> SortKey for Option<T> {
+    fn write_sort_key(&self, out: &mut Vec<u8>) {
+        match *self {
+            None => out.push(0),
+            Some(ref value) => {
+                out.push(1);
+                value.write_sort_key(out);
+            }
+        }
+    }
+}
+
+impl<T: SortKey> SortKey for Reverse<T> {
+    /// Flips every bit of the key. Since no key is a prefix of another, this reverses their order.
+    fn write_sort_key(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        self.0.write_sort_key(out);
+        for byte in &mut out[start..] {
+            *byte = !*byte;
+        }
+    }
+}
+
+macro_rules! tuple_sort_key {
+    ($($name:ident)+) => {
+        impl<$($name: SortKey),+> SortKey for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn write_sort_key(&self, out: &mut Vec<u8>) {
+                let ($(ref $name,)+) = *self;
+                $($name.write_sort_key(out);)+
+            }
+        }
+    }
+}
+
+tuple_sort_key!(A);
+tuple_sort_key!(A B);
+tuple_sort_key!(A B C);
+tuple_sort_key!(A B C D);
+tuple_sort_key!(A B C D E);
+tuple_sort_key!(A B C D E F);
+
+/// Lazily sorts elements by the [sort keys](trait.SortKey.html) that `key_fn` returns for them,
+/// comparing the keys' bytes rather than the keys themselves.
+///
+/// Each key is encoded once, up front, so this pays off when comparing keys is expensive, as for
+/// composite keys, and costs an allocation per element. Elements with equal keys are yielded in
+/// no particular order.
+///
+/// ```
+/// use lazy_sort::quick_sort_by_sort_key;
+///
+/// let events = vec![("deploy", 3, 0.5), ("build", 3, 0.25), ("test", 1, 0.75)];
+/// let sorted: Vec<_> = quick_sort_by_sort_key(events, |&(name, day, _)| (day, name)).collect();
+/// assert_eq!(sorted, [("test", 1, 0.75), ("build", 3, 0.25), ("deploy", 3, 0.5)]);
+/// ```
+pub fn quick_sort_by_sort_key<I, K, F>(iter: I, key_fn: F) -> SortedBySortKey<I::Item>
+    where I: IntoIterator,
+          K: SortKey,
+          F: Fn(&I::Item) -> K
+{
+    let keyed = iter.into_iter().map(|value| {
+        KeyBytes {
+            key: key_fn(&value).to_sort_key(),
+            value,
+        }
+    });
+    SortedBySortKey(keyed.quick_sort())
+}
+
+/// An element ordered by its encoded key.
+#[derive(Debug, Clone)]
+struct KeyBytes<T> {
+    key: Vec<u8>,
+    value: T,
+}
+
+impl<T> Ord for KeyBytes<T> {
+    fn cmp(&self, other: &KeyBytes<T>) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl<T> PartialOrd for KeyBytes<T> {
+    fn partial_cmp(&self, other: &KeyBytes<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for KeyBytes<T> {
+    fn eq(&self, other: &KeyBytes<T>) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<T> Eq for KeyBytes<T> {}
+
+/// Elements lazily sorted by their encoded keys, created by
+/// [`quick_sort_by_sort_key`](fn.quick_sort_by_sort_key.html).
+#[derive(Debug, Clone)]
+pub struct SortedBySortKey<T>(QuickSort<KeyBytes<T>>);
+
+impl<T> SortedBySortKey<T> {
+    /// Returns the next element in sorted order without consuming it.
+    pub fn peek(&mut self) -> Option<&T> {
+        self.0.peek().map(|keyed| &keyed.value)
+    }
+}
+
+impl<T> Iterator for SortedBySortKey<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.next().map(|keyed| keyed.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for SortedBySortKey<T> {}
+
+impl<T> FusedIterator for SortedBySortKey<T> {}
+
+#[test]
+fn sort_keys() {
+    let values: Vec<(Option<i16>, String, Reverse<f32>)> = (0..300)
+        .map(|i| {
+            let id = if i % 7 == 0 { None } else { Some((i * 37 % 101) as i16 - 50) };
+            let name: String = "ab\0c".chars().cycle().skip(i % 4).take(i % 5).collect();
+            (id, name, Reverse((i % 11) as f32 - 5.5))
+        })
+        .collect();
+    let mut expected = values.clone();
+    expected.sort_by(|a, b| {
+        a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)).then_with(|| (b.2).0.total_cmp(&(a.2).0))
+    });
+    for w in expected.windows(2) {
+        assert!(w[0].to_sort_key() <= w[1].to_sort_key());
+    }
+    let sorted: Vec<_> = quick_sort_by_sort_key(values, |value| value.clone()).collect();
+    assert!(sorted.iter().map(SortKey::to_sort_key).eq(expected.iter().map(SortKey::to_sort_key)));
+    assert!("a\0".to_sort_key() > "a".to_sort_key() && "a\0".to_sort_key() < "a\x01".to_sort_key());
+}
@@ -0,0 +1,73 @@
+//! Marker impls that let the `sorted_iter` crate's set operations accept this crate's sorted
+//! outputs directly.
+
+use sorted_iter::sorted_iterator::SortedByItem;
+use sorted_iter::sorted_pair_iterator::SortedByKey;
+use super::{DedupWithCounts, Difference, FixedHeapSort, GroupEqual, GroupFold, HeapSort,
+            Intersection, KMerge, LazySort, Merge, MultisetDifference, MultisetIntersection,
+            MultisetUnion, PairingHeapSort, QuickSort, SortedUnique, Union};
+
+impl<T> SortedByItem for QuickSort<T> {}
+impl<T> SortedByItem for HeapSort<T> {}
+impl<T> SortedByItem for LazySort<T> {}
+impl<T> SortedByItem for PairingHeapSort<T> {}
+impl<T, const N: usize> SortedByItem for FixedHeapSort<T, N> {}
+
+impl<I: Iterator + SortedByItem> SortedByItem for GroupEqual<I> {}
+impl<I: Iterator + SortedByItem> SortedByItem for SortedUnique<I> {}
+impl<I: Iterator + SortedByItem> SortedByItem for KMerge<I> {}
+
+impl<I, J> SortedByItem for Merge<I, J>
+    where I: Iterator + SortedByItem,
+          J: Iterator<Item = I::Item> + SortedByItem
+{
+}
+
+impl<I, J> SortedByItem for Union<I, J>
+    where I: Iterator + SortedByItem,
+          J: Iterator<Item = I::Item> + SortedByItem
+{
+}
+
+impl<I, J> SortedByItem for Intersection<I, J>
+    where I: Iterator + SortedByItem,
+          J: Iterator<Item = I::Item> + SortedByItem
+{
+}
+
+impl<I, J> SortedByItem for Difference<I, J>
+    where I: Iterator + SortedByItem,
+          J: Iterator<Item = I::Item> + SortedByItem
+{
+}
+
+impl<I, J> SortedByItem for MultisetUnion<I, J>
+    where I: Iterator + SortedByItem,
+          J: Iterator<Item = I::Item> + SortedByItem
+{
+}
+
+impl<I, J> SortedByItem for MultisetIntersection<I, J>
+    where I: Iterator + SortedByItem,
+          J: Iterator<Item = I::Item> + SortedByItem
+{
+}
+
+impl<I, J> SortedByItem for MultisetDifference<I, J>
+    where I: Iterator + SortedByItem,
+          J: Iterator<Item = I::Item> + SortedByItem
+{
+}
+
+impl<I: Iterator + SortedByItem> SortedByKey for DedupWithCounts<I> {}
+impl<I: Iterator + SortedByItem, K, A, FK, FF> SortedByKey for GroupFold<I, K, A, FK, FF> {}
+
+#[test]
+fn sorted_iter_union() {
+    use sorted_iter::SortedIterator;
+    use super::LazySortIterator;
+
+    let a = (0..10).rev().quick_sort();
+    let b = (5..15).rev().heap_sort();
+    assert_eq!(a.union(b).collect::<Vec<_>>(), (0..15).collect::<Vec<_>>());
+}
@@ -0,0 +1,191 @@
+//! Plugging custom sorting algorithms into the crate's lazy sorts and their adaptors.
+
+use std::iter::FusedIterator;
+use std::mem;
+use std::vec::Vec;
+use super::{split_at_rank, HeapSort, LazySortIterator, LazySorted, PairingHeapSort, QuickSort};
+
+/// An algorithm that lazily sorts a buffer, taking the smallest remaining element at each step.
+///
+/// Implementing this is enough to sort with the algorithm through
+/// [`sort_with_strategy`](trait.LazySortIterator.html#method.sort_with_strategy), which adds
+/// peeking, splitting, and the rest of [`LazySorted`](trait.LazySorted.html) on top, and with
+/// them every adaptor the crate provides. The crate's own sorters implement it too.
+///
+/// ```
+/// use lazy_sort::{LazySortIterator, LazySortStrategy, LazySorted};
+///
+/// /// A counting sort of bytes.
+/// struct Counting {
+///     counts: Vec<usize>,
+///     next: u8,
+///     remaining: usize,
+/// }
+///
+/// impl LazySortStrategy<u8> for Counting {
+///     fn from_buffer(buffer: Vec<u8>) -> Counting {
+///         let mut counts = vec![0; 256];
+///         for &byte in &buffer {
+///             counts[byte as usize] += 1;
+///         }
+///         Counting { counts, next: 0, remaining: buffer.len() }
+///     }
+///
+///     fn next_smallest(&mut self) -> Option<u8> {
+///         if self.remaining == 0 {
+///             return None;
+///         }
+///         while self.counts[self.next as usize] == 0 {
+///             self.next += 1;
+///         }
+///         self.counts[self.next as usize] -= 1;
+///         self.remaining -= 1;
+///         Some(self.next)
+///     }
+///
+///     fn remaining(&self) -> usize {
+///         self.remaining
+///     }
+///
+///     fn into_buffer(self) -> Vec<u8> {
+///         (0..=255u8).flat_map(|byte| vec![byte; self.counts[byte as usize]]).collect()
+///     }
+/// }
+///
+/// let mut sorted = b"strategy".iter().cloned().sort_with_strategy::<Counting>();
+/// assert_eq!(sorted.peek(), Some(&b'a'));
+/// assert_eq!(sorted.sorted_unique().collect::<Vec<_>>(), b"aegrsty");
+/// ```
+pub trait LazySortStrategy<T>: Sized {
+    /// Takes ownership of the elements to sort, in no particular order.
+    fn from_buffer(buffer: Vec<T>) -> Self;
+
+    /// Removes and returns the smallest remaining element, or `None` if there are none left.
+    fn next_smallest(&mut self) -> Option<T>;
+
+    /// Returns the number of remaining elements.
+    fn remaining(&self) -> usize;
+
+    /// Returns the remaining elements, in no particular order.
+    fn into_buffer(self) -> Vec<T>;
+}
+
+macro_rules! builtin_strategy {
+    ($($sorter:ident: $sort:ident),*) => {$(
+        impl<T: Ord> LazySortStrategy<T> for $sorter<T> {
+            fn from_buffer(buffer: Vec<T>) -> $sorter<T> {
+                buffer.into_iter().$sort()
+            }
+
+            fn next_smallest(&mut self) -> Option<T> {
+                self.next()
+            }
+
+            fn remaining(&self) -> usize {
+                self.len()
+            }
+
+            fn into_buffer(self) -> Vec<T> {
+                self.into_inner()
+            }
+        }
+    )*}
+}
+
+builtin_strategy!(QuickSort: quick_sort, HeapSort: heap_sort, PairingHeapSort: pairing_heap_sort);
+
+/// An iterator that lazily sorts its input with a
+/// [`LazySortStrategy`](trait.LazySortStrategy.html), created by
+/// [`sort_with_strategy`](trait.LazySortIterator.html#method.sort_with_strategy).
+#[derive(Debug, Clone)]
+pub struct StrategySort<T, S> {
+    strategy: S,
+    /// The element taken from the strategy by `peek`, if it hasn't been yielded yet.
+    peeked: Option<T>,
+}
+
+impl<T, S: LazySortStrategy<T>> StrategySort<T, S> {
+    /// Sorts `buffer` with a new strategy.
+    pub(crate) fn new(buffer: Vec<T>) -> StrategySort<T, S> {
+        StrategySort {
+            strategy: S::from_buffer(buffer),
+            peeked: None,
+        }
+    }
+
+    /// Returns the strategy, which doesn't hold an element that was peeked at.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+}
+
+impl<T, S: LazySortStrategy<T>> Iterator for StrategySort<T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.peeked.take().or_else(|| self.strategy.next_smallest())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.strategy.remaining() + self.peeked.is_some() as usize;
+        (len, Some(len))
+    }
+}
+
+impl<T, S: LazySortStrategy<T>> FusedIterator for StrategySort<T, S> { }
+
+impl<T: Ord, S: LazySortStrategy<T>> LazySorted<T> for StrategySort<T, S> {
+    fn peek(&mut self) -> Option<&T> {
+        if self.peeked.is_none() {
+            self.peeked = self.strategy.next_smallest();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn len(&self) -> usize {
+        self.strategy.remaining() + self.peeked.is_some() as usize
+    }
+
+    fn into_inner(self) -> Vec<T> {
+        let mut v = self.strategy.into_buffer();
+        v.extend(self.peeked);
+        v
+    }
+
+    fn split_at_rank(self, k: usize) -> (StrategySort<T, S>, StrategySort<T, S>) {
+        let (smallest, rest) = split_at_rank(self.into_inner(), k);
+        (StrategySort::new(smallest), StrategySort::new(rest))
+    }
+
+    fn modify_remaining<F>(&mut self, f: F)
+        where F: FnOnce(&mut [T])
+    {
+        let strategy = mem::replace(&mut self.strategy, S::from_buffer(Vec::new()));
+        let mut v = strategy.into_buffer();
+        v.extend(self.peeked.take());
+        f(&mut v);
+        self.strategy = S::from_buffer(v);
+    }
+}
+
+#[test]
+fn strategy_sort() {
+    let v: Vec<u32> = (0..1000).map(|i| (i * 7919) % 1009).collect();
+    let mut expected = v.clone();
+    expected.sort();
+
+    let mut quick = v.iter().cloned().sort_with_strategy::<QuickSort<_>>();
+    assert_eq!(quick.peek(), Some(&expected[0]));
+    assert_eq!(quick.len(), v.len());
+    let (smallest, rest) = quick.split_at_rank(100);
+    assert!(smallest.chain(rest).eq(expected.iter().cloned()));
+
+    let mut heap = v.iter().cloned().sort_with_strategy::<HeapSort<_>>();
+    assert_eq!(heap.by_ref().take(10).collect::<Vec<_>>(), &expected[..10]);
+    heap.peek();
+    heap.modify_remaining(|rest| rest.iter_mut().for_each(|el| *el = 2000 - *el));
+    assert_eq!(heap.len(), v.len() - 10);
+    assert_eq!(heap.next(), Some(2000 - expected[v.len() - 1]));
+    assert_eq!(v.into_iter().sort_with_strategy::<PairingHeapSort<_>>().collect::<Vec<_>>(),
+               expected);
+}
@@ -0,0 +1,127 @@
+//! Lazily sorting asynchronous streams.
+
+use futures_core::stream::{FusedStream, Stream};
+use futures_core::task::{Context, Poll};
+use std::boxed::Box;
+use std::mem;
+use std::pin::Pin;
+use std::vec::Vec;
+use super::{Config, QuickSort};
+
+/// Roughly how many elements are collected or partitioned before yielding to the executor.
+const WORK_PER_POLL: usize = 1 << 12;
+
+/// Extends streams with lazy sorts.
+pub trait LazySortStream: Stream + Unpin + Sized
+    where Self::Item: Ord
+{
+    /// Collects the stream, then lazily quicksorts it into a stream of its elements in order.
+    ///
+    /// Collecting and partitioning are broken into pieces of a few thousand elements between which
+    /// the task yields, so a large sort doesn't monopolize the executor. Wrap a stream that isn't
+    /// `Unpin` with `Box::pin` first.
+    fn quick_sort(self) -> QuickSortStream<Self> {
+        QuickSortStream(State::Collecting(self, Vec::new()))
+    }
+}
+
+impl<S> LazySortStream for S
+    where S: Stream + Unpin,
+          S::Item: Ord
+{ }
+
+/// A stream that lazily sorts another, created by
+/// [`LazySortStream::quick_sort`](trait.LazySortStream.html#method.quick_sort).
+#[derive(Debug)]
+pub struct QuickSortStream<S: Stream>(State<S, S::Item>);
+
+// The elements are never pinned, so only the stream being collected needs to be `Unpin`.
+impl<S: Stream + Unpin> Unpin for QuickSortStream<S> { }
+
+#[derive(Debug)]
+enum State<S, T> {
+    Collecting(S, Vec<T>),
+    Sorting(Box<QuickSort<T>>),
+}
+
+impl<S> Stream for QuickSortStream<S>
+    where S: Stream + Unpin,
+          S::Item: Ord
+{
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<S::Item>> {
+        let this = self.get_mut();
+        let mut work = 0;
+        if let State::Collecting(ref mut stream, ref mut v) = this.0 {
+            loop {
+                if work == WORK_PER_POLL {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                match Pin::new(&mut *stream).poll_next(cx) {
+                    Poll::Ready(Some(el)) => v.push(el),
+                    Poll::Ready(None) => break,
+                    Poll::Pending => return Poll::Pending,
+                }
+                work += 1;
+            }
+        }
+        if let State::Collecting(_, ref mut v) = this.0 {
+            let v = mem::take(v);
+            this.0 = State::Sorting(Box::new(QuickSort::with_config(v, Config::default())));
+        }
+        match this.0 {
+            State::Sorting(ref mut sorter) => {
+                while work < WORK_PER_POLL {
+                    match sorter.step() {
+                        0 => return Poll::Ready(sorter.next()),
+                        partitioned => work += partitioned,
+                    }
+                }
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            State::Collecting(..) => unreachable!(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.0 {
+            State::Collecting(ref stream, ref v) => {
+                let (lower, upper) = stream.size_hint();
+                (lower.saturating_add(v.len()), upper.and_then(|upper| upper.checked_add(v.len())))
+            }
+            State::Sorting(ref sorter) => sorter.size_hint(),
+        }
+    }
+}
+
+impl<S> FusedStream for QuickSortStream<S>
+    where S: Stream + Unpin,
+          S::Item: Ord
+{
+    fn is_terminated(&self) -> bool {
+        match self.0 {
+            State::Collecting(..) => false,
+            State::Sorting(ref sorter) => sorter.size_hint().0 == 0,
+        }
+    }
+}
+
+#[test]
+fn quick_sort_stream() {
+    use futures::executor::block_on;
+    use futures::stream::{self, StreamExt};
+    use super::rng::XorShift;
+
+    let mut rng = XorShift::new(5);
+    let v: Vec<u64> = (0..20_000).map(|_| rng.next_u64() % 500).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    let sorted = LazySortStream::quick_sort(stream::iter(v.clone()));
+    assert_eq!(block_on(sorted.collect::<Vec<_>>()), expected);
+
+    let smallest = LazySortStream::quick_sort(stream::iter(v)).take(3);
+    assert_eq!(block_on(smallest.collect::<Vec<_>>()), &expected[..3]);
+}
@@ -0,0 +1,127 @@
+//! Lending sorted elements through the `streaming_iterator` crate's `StreamingIterator`, for
+//! passes that only read them.
+
+use streaming_iterator::{StreamingIterator, StreamingIteratorMut};
+use super::{LazySorted, QuickSort};
+
+/// A sorter that lends each element from a slot it holds rather than yielding it, created by
+/// [`LazySorted::streaming`](trait.LazySorted.html#method.streaming).
+///
+/// ```
+/// # extern crate lazy_sort;
+/// # extern crate streaming_iterator;
+/// use lazy_sort::{LazySortIterator, LazySorted};
+/// use streaming_iterator::StreamingIterator;
+///
+/// let words = vec!["pear".to_string(), "fig".to_string(), "apple".to_string()];
+/// let mut sorted = words.into_iter().quick_sort().streaming();
+/// let mut lengths = Vec::new();
+/// while let Some(word) = sorted.next() {
+///     lengths.push(word.len());
+/// }
+/// assert_eq!(lengths, [5, 3, 4]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamingSort<S, T> {
+    sorter: S,
+    slot: Option<T>,
+}
+
+impl<S, T> StreamingSort<S, T> {
+    pub(crate) fn new(sorter: S) -> StreamingSort<S, T> {
+        StreamingSort {
+            sorter,
+            slot: None,
+        }
+    }
+
+    /// Returns the sorter, which no longer holds the element in the slot.
+    pub fn into_inner(self) -> S {
+        self.sorter
+    }
+}
+
+impl<T: Ord, S: LazySorted<T>> StreamingIterator for StreamingSort<S, T> {
+    type Item = T;
+
+    fn advance(&mut self) {
+        self.slot = self.sorter.next();
+    }
+
+    fn get(&self) -> Option<&T> {
+        self.slot.as_ref()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.sorter.len();
+        (len, Some(len))
+    }
+}
+
+impl<T: Ord, S: LazySorted<T>> StreamingIteratorMut for StreamingSort<S, T> {
+    /// Changes to the lent element don't affect the order of those that follow it.
+    fn get_mut(&mut self) -> Option<&mut T> {
+        self.slot.as_mut()
+    }
+}
+
+/// A lazy sort of references to a slice's elements that lends each element from the slice
+/// itself, so no element is ever moved or cloned, created by
+/// [`streaming_sort_refs`](trait.LazySortSlice.html#tymethod.streaming_sort_refs).
+#[derive(Debug, Clone)]
+pub struct StreamingRefs<'a, T: 'a> {
+    sorter: QuickSort<&'a T>,
+    slot: Option<&'a T>,
+}
+
+impl<'a, T> StreamingRefs<'a, T> {
+    pub(crate) fn new(sorter: QuickSort<&'a T>) -> StreamingRefs<'a, T> {
+        StreamingRefs {
+            sorter,
+            slot: None,
+        }
+    }
+}
+
+impl<'a, T: Ord> StreamingIterator for StreamingRefs<'a, T> {
+    type Item = T;
+
+    fn advance(&mut self) {
+        self.slot = self.sorter.next();
+    }
+
+    fn get(&self) -> Option<&T> {
+        self.slot
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.sorter.len();
+        (len, Some(len))
+    }
+}
+
+#[test]
+fn streaming_sort() {
+    use std::ptr;
+    use std::vec::Vec;
+    use super::{LazySortIterator, LazySortSlice};
+
+    let v: Vec<u32> = (0..1000).map(|i| (i * 7919) % 1009).collect();
+    let mut expected = v.clone();
+    expected.sort();
+
+    let mut sorted = v.iter().cloned().quick_sort().streaming();
+    assert_eq!(sorted.get(), None);
+    assert_eq!(sorted.size_hint(), (1000, Some(1000)));
+    assert_eq!(sorted.next(), Some(&expected[0]));
+    *sorted.get_mut().unwrap() = 5000;
+    assert_eq!(sorted.get(), Some(&5000));
+    assert_eq!(sorted.by_ref().take(9).cloned().collect::<Vec<_>>(), &expected[1..10]);
+    assert!(sorted.into_inner().eq(expected[10..].iter().cloned()));
+
+    let mut refs = v.streaming_sort_refs();
+    assert_eq!(refs.nth(3), Some(&expected[3]));
+    let position = v.iter().position(|&el| el == expected[3]).unwrap();
+    assert!(ptr::eq(refs.get().unwrap(), &v[position]));
+    assert_eq!(refs.fold(0, |sum, &el| sum + el), expected[4..].iter().sum::<u32>());
+}
@@ -0,0 +1,78 @@
+//! Partitioning on several threads with `std::thread`.
+
+use std::thread;
+use super::{partition, unbalanced, Config, QuickSort, QuickSortInternal, Recursive};
+
+/// Partitions smaller than this are left to the calling thread.
+const PARALLEL_THRESHOLD: usize = 1 << 14;
+
+/// Returns a quicksort that has already done the partitioning that the first call to `next`
+/// would, with each large partition split across `threads` threads.
+pub(crate) fn quick_sort<T>(v: Vec<T>, config: Config, threads: usize) -> QuickSort<T>
+    where T: Ord + Send + Sync
+{
+    QuickSort::partitioned(v, config, |v, config| partition_smallest(v, config, threads, 0))
+}
+
+fn partition_smallest<T>(mut v: Vec<T>, config: &mut Config, threads: usize, depth: usize)
+    -> QuickSortInternal<T>
+    where T: Ord + Send + Sync
+{
+    if threads <= 1 || v.len() <= PARALLEL_THRESHOLD.max(config.threshold) {
+        return QuickSortInternal::at_depth(v, config, depth);
+    }
+    let len = v.len();
+    let pivot = v.swap_remove(config.choose_pivot(&v, None));
+    let (mut less, mut greater) = partition_chunks(v, &pivot, threads);
+    if unbalanced(len, less.len()) {
+        // The sequential split redoes it around the median.
+        less.append(&mut greater);
+        less.push(pivot);
+        return QuickSortInternal::at_depth(less, config, depth);
+    }
+    config.count(len - 1, len);
+    config.count_partition(len, 2);
+    config.record_split(depth, len, greater.len(), less.len());
+    greater.push(pivot);
+    let less = partition_smallest(less, config, threads, depth + 1);
+    QuickSortInternal::Recursive(Recursive::with_less(greater, Some(Box::new(less)), depth))
+}
+
+/// Splits `v` into the elements at most `pivot` and those greater than it, partitioning one chunk
+/// per thread in place and then gathering the chunks' halves.
+fn partition_chunks<T>(mut v: Vec<T>, pivot: &T, threads: usize) -> (Vec<T>, Vec<T>)
+    where T: Ord + Send + Sync
+{
+    let chunk_len = v.len().div_ceil(threads);
+    // The length of each chunk and the number of its elements greater than the pivot, which
+    // `partition` moves to the front.
+    let layout: Vec<(usize, usize)> = thread::scope(|scope| {
+        let handles: Vec<_> = v.chunks_mut(chunk_len).map(|chunk| {
+            scope.spawn(move || (chunk.len(), partition(chunk.iter_mut(), |el| el > pivot)))
+        }).collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+    let greater_len = layout.iter().map(|&(_, greater)| greater).sum();
+    let mut greater = Vec::with_capacity(greater_len);
+    let mut less = Vec::with_capacity(v.len() - greater_len);
+    let mut elements = v.into_iter();
+    for (len, greater_in_chunk) in layout {
+        greater.extend(elements.by_ref().take(greater_in_chunk));
+        less.extend(elements.by_ref().take(len - greater_in_chunk));
+    }
+    (less, greater)
+}
+
+#[test]
+fn threaded_quick_sort() {
+    use super::rng::XorShift;
+
+    let mut rng = XorShift::new(4);
+    let v: Vec<u64> = (0..100_000).map(|_| rng.next_u64() % 1000).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    for &threads in &[1, 3, 8] {
+        let sorted = quick_sort(v.clone(), Config::default(), threads);
+        assert_eq!(sorted.collect::<Vec<_>>(), expected);
+    }
+}
@@ -0,0 +1,89 @@
+//! Running the external sorter from async code without blocking the runtime.
+
+use futures_core::stream::Stream;
+use futures_core::task::{Context, Poll};
+use std::io;
+use std::pin::Pin;
+use tokio::sync::mpsc;
+use tokio::task;
+use super::{ExternalSortBuilder, SpillCodec};
+
+/// How many sorted elements the merge can get ahead of the consumer.
+const CHANNEL_CAPACITY: usize = 1024;
+
+impl ExternalSortBuilder {
+    /// Like [`sort_with_codec`](#method.sort_with_codec), but spills and merges on tokio's
+    /// blocking thread pool and yields the sorted elements as a stream. Must be called from
+    /// within a tokio runtime.
+    ///
+    /// The merge runs up to a bounded number of elements ahead of the stream's consumer, and stops
+    /// if the stream is dropped. If spilling or reading back a run fails, the stream ends with the
+    /// error.
+    pub fn sorted_stream<I, C>(self, iter: I, codec: C) -> SortedStream<I::Item>
+        where I: IntoIterator + Send + 'static,
+              I::Item: Ord + Send + 'static,
+              C: SpillCodec<I::Item> + Clone + Send + 'static
+    {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        task::spawn_blocking(move || {
+            let mut sorted = match self.sort_with_codec(iter, codec) {
+                Ok(sorted) => sorted,
+                Err(err) => {
+                    let _ = tx.blocking_send(Err(err));
+                    return;
+                }
+            };
+            for el in sorted.by_ref() {
+                if tx.blocking_send(Ok(el)).is_err() {
+                    return;
+                }
+            }
+            if let Some(err) = sorted.error() {
+                let _ = tx.blocking_send(Err(io::Error::new(err.kind(), err.to_string())));
+            }
+        });
+        SortedStream(rx)
+    }
+}
+
+/// The output of an external sort run from async code, created by
+/// [`ExternalSortBuilder::sorted_stream`](struct.ExternalSortBuilder.html#method.sorted_stream).
+#[derive(Debug)]
+pub struct SortedStream<T>(mpsc::Receiver<io::Result<T>>);
+
+impl<T> Stream for SortedStream<T> {
+    type Item = io::Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<io::Result<T>>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+#[test]
+fn sorted_stream() {
+    use futures::executor::block_on;
+    use futures::stream::StreamExt;
+
+    /// Encodes `u32`s as four little-endian bytes.
+    #[derive(Clone)]
+    struct FixedWidth;
+
+    impl SpillCodec<u32> for FixedWidth {
+        fn encode<W: io::Write>(&self, el: &u32, writer: &mut W) -> io::Result<()> {
+            writer.write_all(&el.to_le_bytes())
+        }
+
+        fn decode<R: io::Read>(&self, reader: &mut R) -> io::Result<u32> {
+            let mut bytes = [0; 4];
+            reader.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        }
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let _guard = runtime.enter();
+    let v: Vec<u32> = (0..5000).map(|i| i * 7919 % 5000).collect();
+    let stream = ExternalSortBuilder::new().memory_budget(1024).sorted_stream(v, FixedWidth);
+    let sorted: Vec<_> = block_on(stream.map(Result::unwrap).collect());
+    assert_eq!(sorted, (0..5000).collect::<Vec<_>>());
+}
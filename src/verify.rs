@@ -0,0 +1,108 @@
+//! Checking that the sorted output is in order and that `Ord` impls are consistent.
+//!
+//! These checks panic with a description of the violation, rather than letting a broken `Ord`
+//! impl silently produce unsorted output.
+
+use std::any;
+use std::cmp::Ordering::{self, Greater, Less};
+
+#[cold]
+fn violation<T>(what: &str) -> ! {
+    panic!("the `Ord` impl of `{}` isn't a total order: {}", any::type_name::<T>(), what)
+}
+
+/// Checks that `ordering`, the result of comparing some `a` to `b`, agrees with `reversed`, the
+/// result of comparing `b` to `a`.
+fn check_antisymmetric<T>(ordering: Ordering, reversed: Ordering) {
+    if ordering != reversed.reverse() {
+        violation::<T>(&format!("a.cmp(b) is {:?}, but b.cmp(a) is {:?}", ordering, reversed));
+    }
+}
+
+/// Checks that `next`, which is to be yielded after `prev` with `remaining` elements left, isn't
+/// less than it.
+pub(crate) fn check_emitted<T: Ord>(prev: &T, next: &T, remaining: usize) {
+    let ordering = prev.cmp(next);
+    check_antisymmetric::<T>(ordering, next.cmp(prev));
+    if ordering == Greater {
+        violation::<T>(&format!("an element was yielded before a smaller one, with {} elements \
+                                 remaining",
+                                remaining));
+    }
+}
+
+/// Checks a sample of a split partition: `greater` was placed before the pivot, and `less`
+/// after, so transitivity requires that `greater > less`.
+pub(crate) fn check_split<T: Ord>(greater: &T, pivot: &T, less: &T) {
+    let (greater_pivot, pivot_less) = (greater.cmp(pivot), pivot.cmp(less));
+    check_antisymmetric::<T>(greater_pivot, pivot.cmp(greater));
+    check_antisymmetric::<T>(pivot_less, less.cmp(pivot));
+    if greater_pivot != Greater || pivot_less == Less {
+        violation::<T>("an element was partitioned onto the wrong side of its pivot");
+    }
+    if greater.cmp(less) != Greater {
+        violation::<T>("a > b and b >= c, but not a > c");
+    }
+}
+
+/// Checks a sample of a heap, whose `root` is no greater than its `child`, which is no greater
+/// than its `grandchild`.
+pub(crate) fn check_heap<T: Ord>(root: &T, child: &T, grandchild: &T) {
+    let (root_child, child_grandchild) = (root.cmp(child), child.cmp(grandchild));
+    check_antisymmetric::<T>(root_child, child.cmp(root));
+    check_antisymmetric::<T>(child_grandchild, grandchild.cmp(child));
+    if root_child != Greater && child_grandchild != Greater && root.cmp(grandchild) == Greater {
+        violation::<T>("a <= b and b <= c, but not a <= c");
+    }
+}
+
+/// Orders rock, paper, and scissors, so that comparisons aren't transitive.
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Hand(u8);
+
+#[cfg(test)]
+impl PartialOrd for Hand {
+    fn partial_cmp(&self, other: &Hand) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+impl Ord for Hand {
+    fn cmp(&self, other: &Hand) -> Ordering {
+        match (other.0 + 3 - self.0) % 3 {
+            0 => Ordering::Equal,
+            1 => Less,
+            _ => Greater,
+        }
+    }
+}
+
+#[test]
+fn verify_total_order() {
+    use super::LazySortIterator;
+
+    let v = (0..1000).map(|i| (i * 7919) % 1009);
+    let sorted: Vec<_> = v.clone().quick_sort().collect();
+    assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(v.heap_sort().count(), 1000);
+}
+
+#[test]
+#[should_panic(expected = "isn't a total order")]
+fn verify_quick_sort_intransitive() {
+    use super::LazySortBuilder;
+
+    // Unperturbed pivots make sure the sort runs into the inconsistency.
+    let sorter = LazySortBuilder::new().perturb_pivots(false);
+    sorter.sort((0..100).map(|i| Hand(i % 3))).unwrap().for_each(drop);
+}
+
+#[test]
+#[should_panic(expected = "isn't a total order")]
+fn verify_heap_sort_intransitive() {
+    use super::LazySortIterator;
+
+    (0..100).map(|i| Hand(i % 3)).heap_sort().for_each(drop);
+}
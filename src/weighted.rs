@@ -0,0 +1,70 @@
+//! Selecting weighted quantiles without sorting.
+
+use std::vec::Vec;
+
+/// Elements along with their weights.
+type Weighted<T> = Vec<(T, f64)>;
+
+/// Collects `iter` along with the weight of each element, returning `None` if the weights sum
+/// to zero.
+///
+/// Panics if a weight is negative or not finite.
+pub(crate) fn weighted<I, F>(iter: I, mut weight_fn: F) -> Option<(Weighted<I::Item>, f64)>
+    where I: Iterator,
+          F: FnMut(&I::Item) -> f64
+{
+    let mut total = 0.0;
+    let v: Weighted<_> = iter.map(|el| {
+                            let weight = weight_fn(&el);
+                            assert!(weight >= 0.0 && weight.is_finite(),
+                                    "weights must be finite and nonnegative, not {}",
+                                    weight);
+                            total += weight;
+                            (el, weight)
+                        })
+                        .collect();
+    if total > 0.0 { Some((v, total)) } else { None }
+}
+
+/// Reorders `v` so that the element at the returned index is its `q` weighted quantile: the
+/// smallest element such that it and the elements less than it weigh at least `q` of `total`.
+///
+/// Each round partitions the remaining range around its middle element with
+/// `select_nth_unstable`, and keeps whichever side the quantile falls on, so this takes expected
+/// `O(n)` time rather than the `O(n log n)` of a full sort.
+pub(crate) fn select_quantile<T: Ord>(v: &mut [(T, f64)], total: f64, q: f64) -> usize {
+    let mut target = q.clamp(0.0, 1.0) * total;
+    let (mut lo, mut hi) = (0, v.len());
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        v[lo..hi].select_nth_unstable_by(mid - lo, |a, b| a.0.cmp(&b.0));
+        let below: f64 = v[lo..mid].iter().map(|&(_, weight)| weight).sum();
+        if below >= target {
+            hi = mid;
+        } else if below + v[mid].1 >= target {
+            return mid;
+        } else {
+            target -= below + v[mid].1;
+            lo = mid + 1;
+        }
+    }
+    // Rounding can leave the target just beyond the total weight, past the last element.
+    lo.min(hi - 1)
+}
+
+#[test]
+fn weighted_quantiles() {
+    use super::LazySortIterator;
+
+    let values = (0..1000u32).map(|i| (i * 7919) % 1000);
+    // The weight of each value is itself, so the weight up to `x` is `x (x + 1) / 2`.
+    assert_eq!(values.clone().weighted_median(|&x| x as f64), Some(707));
+    let quantiles = values.clone().weighted_quantiles(|&x| x as f64, &[0.0, 0.25, 1.0, 2.0]);
+    assert_eq!(quantiles, [Some(0), Some(500), Some(999), Some(999)]);
+
+    let uniform = values.clone().weighted_quantiles(|_| 1.0, &[0.1, 0.5]);
+    assert_eq!(uniform, [Some(99), Some(499)]);
+    let zeros = vec![(3, 0), (1, 5), (2, 0)];
+    assert_eq!(zeros.into_iter().weighted_median(|p| p.1 as f64), Some((1, 5)));
+    assert_eq!(values.weighted_median(|_| 0.0), None);
+}
@@ -0,0 +1,297 @@
+//! Sorted views of sliding and tumbling windows over an iterator.
+
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::iter::{Fuse, FusedIterator};
+use std::vec::Vec;
+
+/// A sorted view of each window of `window_len` consecutive elements of an iterator.
+///
+/// `SortedWindows` lends each window as a slice, so it isn't an `Iterator` itself; use
+/// [`next_window`](#method.next_window) directly, or [`kth`](#method.kth) and
+/// [`medians`](#method.medians) for an iterator over a statistic of each window. Sliding the window
+/// by one element costs `O(window_len)`.
+#[derive(Debug, Clone)]
+pub struct SortedWindows<I: Iterator> {
+    iter: Fuse<I>,
+    window_len: usize,
+    /// The elements of the current window in sorted order, with equal elements in input order.
+    sorted: Vec<I::Item>,
+    /// The position in the input of each element of `sorted`.
+    positions: Vec<usize>,
+    /// The number of elements taken from `iter`.
+    taken: usize,
+}
+
+impl<I> SortedWindows<I>
+    where I: Iterator,
+          I::Item: Ord
+{
+    pub(crate) fn new(iter: I, window_len: usize) -> SortedWindows<I> {
+        assert!(window_len > 0, "window_len must be nonzero");
+        SortedWindows {
+            iter: iter.fuse(),
+            window_len,
+            sorted: Vec::with_capacity(window_len),
+            positions: Vec::with_capacity(window_len),
+            taken: 0,
+        }
+    }
+
+    /// Slides the window forward by one element, returning the elements of the new window in
+    /// sorted order, or `None` once the input runs out. The first window returned holds the first
+    /// `window_len` elements.
+    pub fn next_window(&mut self) -> Option<&[I::Item]> {
+        if self.sorted.len() == self.window_len {
+            // The oldest element is the one that leaves the window.
+            let oldest = self.taken - self.window_len;
+            let idx = self.positions.iter().position(|&p| p == oldest).unwrap();
+            self.sorted.remove(idx);
+            self.positions.remove(idx);
+        }
+        while self.sorted.len() < self.window_len {
+            let el = self.iter.next()?;
+            // Inserting after any equal elements keeps equal elements in input order.
+            let idx = self.sorted.partition_point(|other| *other <= el);
+            self.sorted.insert(idx, el);
+            self.positions.insert(idx, self.taken);
+            self.taken += 1;
+        }
+        Some(&self.sorted)
+    }
+
+    /// Returns an iterator over the `k`th smallest element (counting from zero) of each window.
+    ///
+    /// Panics if `k >= window_len`.
+    pub fn kth(self, k: usize) -> Kth<I> {
+        assert!(k < self.window_len, "k must be less than window_len");
+        Kth { windows: self, k }
+    }
+
+    /// Returns an iterator over the median of each window. For windows of even length, this is the
+    /// lower of the two middle elements.
+    pub fn medians(self) -> Kth<I> {
+        let k = (self.window_len - 1) / 2;
+        self.kth(k)
+    }
+}
+
+/// An iterator over the `k`th smallest element of each window of an iterator.
+pub struct Kth<I: Iterator> {
+    windows: SortedWindows<I>,
+    k: usize,
+}
+
+impl<I> Clone for Kth<I>
+    where I: Iterator + Clone,
+          I::Item: Clone
+{
+    fn clone(&self) -> Self {
+        Kth {
+            windows: self.windows.clone(),
+            k: self.k,
+        }
+    }
+}
+
+impl<I> fmt::Debug for Kth<I>
+    where I: Iterator + fmt::Debug,
+          I::Item: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Kth").field("windows", &self.windows).field("k", &self.k).finish()
+    }
+}
+
+impl<I> Iterator for Kth<I>
+    where I: Iterator,
+          I::Item: Ord + Clone
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let k = self.k;
+        self.windows.next_window().map(|window| window[k].clone())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Until the first window fills, the elements that fill it yield only one window between
+        // them; after that, each element yields one.
+        let missing = (self.windows.window_len - self.windows.sorted.len()).saturating_sub(1);
+        let (lower, upper) = self.windows.iter.size_hint();
+        (lower.saturating_sub(missing), upper.map(|upper| upper.saturating_sub(missing)))
+    }
+}
+
+impl<I> FusedIterator for Kth<I>
+    where I: Iterator,
+          I::Item: Ord + Clone
+{
+}
+
+/// Yields the `k` smallest items of each tumbling window of timestamped input in sorted order.
+///
+/// The windows are `window` long and aligned to multiples of it, so an item with timestamp `ts`
+/// falls in the window starting at `ts - ts % window`. The timestamps are expected to be
+/// nondecreasing, and a window is yielded once an item from a later window arrives, or the input
+/// runs out. Windows without any items are skipped. Items timestamped before the current window
+/// are late, and are dropped; [`late`](struct.TumblingTopK.html#method.late) counts them.
+///
+/// Each window keeps only its `k` smallest items so far, in a bounded heap that is reused from
+/// window to window, so each item costs `O(log k)`. For the largest items, wrap them in
+/// `std::cmp::Reverse`.
+///
+/// Panics if `window` is zero.
+pub fn tumbling_top_k<I, T>(iter: I, window: u64, k: usize) -> TumblingTopK<I::IntoIter, T>
+    where I: IntoIterator<Item = (u64, T)>,
+          T: Ord
+{
+    assert!(window > 0, "window must be nonzero");
+    TumblingTopK {
+        iter: iter.into_iter().fuse(),
+        window,
+        k,
+        start: None,
+        heap: BinaryHeap::with_capacity(k),
+        late: 0,
+    }
+}
+
+/// The `k` smallest items of a window, yielded by [`TumblingTopK`](struct.TumblingTopK.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopKWindow<T> {
+    /// The timestamp at which the window starts.
+    pub start: u64,
+    /// The window's smallest items in ascending order, of which there are fewer than `k` only if
+    /// the window had fewer items.
+    pub items: Vec<T>,
+}
+
+/// An iterator over the `k` smallest items of each tumbling window of timestamped input, created
+/// by [`tumbling_top_k`](fn.tumbling_top_k.html).
+#[derive(Debug, Clone)]
+pub struct TumblingTopK<I, T> {
+    iter: Fuse<I>,
+    window: u64,
+    k: usize,
+    /// The start of the current window, or `None` before the first item and after the last.
+    start: Option<u64>,
+    /// A max-heap of the current window's smallest items.
+    heap: BinaryHeap<T>,
+    late: usize,
+}
+
+impl<I, T> TumblingTopK<I, T>
+    where I: Iterator<Item = (u64, T)>,
+          T: Ord
+{
+    /// Returns the number of late items dropped so far.
+    pub fn late(&self) -> usize {
+        self.late
+    }
+
+    fn push(&mut self, item: T) {
+        if self.heap.len() < self.k {
+            self.heap.push(item);
+        } else if let Some(mut max) = self.heap.peek_mut() {
+            if item < *max {
+                *max = item;
+            }
+        }
+    }
+
+    /// Takes the current window's items out of the heap, leaving it empty but allocated.
+    fn finish(&mut self, start: u64) -> TopKWindow<T> {
+        let mut items = Vec::with_capacity(self.heap.len());
+        while let Some(item) = self.heap.pop() {
+            items.push(item);
+        }
+        items.reverse();
+        TopKWindow { start, items }
+    }
+}
+
+impl<I, T> Iterator for TumblingTopK<I, T>
+    where I: Iterator<Item = (u64, T)>,
+          T: Ord
+{
+    type Item = TopKWindow<T>;
+
+    fn next(&mut self) -> Option<TopKWindow<T>> {
+        while let Some((ts, item)) = self.iter.next() {
+            let start = ts - ts % self.window;
+            match self.start {
+                Some(current) if start < current => self.late += 1,
+                Some(current) if start > current => {
+                    let done = self.finish(current);
+                    self.start = Some(start);
+                    self.push(item);
+                    return Some(done);
+                }
+                _ => {
+                    self.start = Some(start);
+                    self.push(item);
+                }
+            }
+        }
+        self.start.take().map(|start| self.finish(start))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let current = self.start.is_some() as usize;
+        let upper = self.iter.size_hint().1.and_then(|upper| upper.checked_add(current));
+        (current, upper)
+    }
+}
+
+impl<I, T> FusedIterator for TumblingTopK<I, T>
+    where I: Iterator<Item = (u64, T)>,
+          T: Ord
+{
+}
+
+#[test]
+fn tumbling_windows() {
+    let input = vec![(0, 5), (3, 1), (9, 7), (4, 4), (10, 2), (12, 8), (7, 0), (35, 6), (36, 3)];
+    let mut windows = tumbling_top_k(input, 10, 2);
+    assert_eq!(windows.next(), Some(TopKWindow { start: 0, items: vec![1, 4] }));
+    assert_eq!(windows.next(), Some(TopKWindow { start: 10, items: vec![2, 8] }));
+    assert_eq!(windows.late(), 1);
+    assert_eq!(windows.next(), Some(TopKWindow { start: 30, items: vec![3, 6] }));
+    assert_eq!(windows.next(), None);
+    assert_eq!(tumbling_top_k(vec![(1, 'a')], 5, 0).collect::<Vec<_>>(),
+               [TopKWindow { start: 0, items: vec![] }]);
+}
+
+#[test]
+fn sorted_windows() {
+    use super::LazySortIterator;
+
+    let mut windows = vec![5, 1, 4, 2, 3].into_iter().sorted_windows(3);
+    assert_eq!(windows.next_window(), Some(&[1, 4, 5][..]));
+    assert_eq!(windows.next_window(), Some(&[1, 2, 4][..]));
+    assert_eq!(windows.next_window(), Some(&[2, 3, 4][..]));
+    assert_eq!(windows.next_window(), None);
+
+    let medians: Vec<_> = vec![3, 9, 1, 1, 8, 7, 2].into_iter()
+                                                     .sorted_windows(3)
+                                                     .medians()
+                                                     .collect();
+    assert_eq!(medians, vec![3, 1, 1, 7, 7]);
+    let mut medians = vec![5, 1, 4, 2, 3].into_iter().sorted_windows(3).medians();
+    for remaining in (0..=3).rev() {
+        assert_eq!(medians.size_hint(), (remaining, Some(remaining)));
+        assert_eq!(medians.next().is_some(), remaining > 0);
+    }
+    assert_eq!(vec![1, 2].into_iter().sorted_windows(3).kth(2).count(), 0);
+
+    // The input isn't asked for more once it runs out, even if it would yield more.
+    let mut calls = 0;
+    let flaky = std::iter::from_fn(|| {
+        calls += 1;
+        if calls == 3 { None } else { Some(calls) }
+    });
+    let mut medians = flaky.sorted_windows(1).medians();
+    assert_eq!(medians.by_ref().collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(medians.next(), None);
+}